@@ -0,0 +1,349 @@
+//! Cooperative cancellation and timeouts of model calls.
+
+use futures::{
+    future::{self, Either},
+    stream, FutureExt, StreamExt,
+};
+use std::{
+    future::Future,
+    pin::pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
+
+use crate::{
+    errors::{AbortReason, AbortedError, ModelError},
+    model::{
+        stream_part::LanguageModelStreamPart, usage::LanguageModelUsage,
+        LanguageModelDoStreamResponse,
+    },
+};
+
+/// Aborts the calls it was passed to, e.g. through
+/// [`GenerateTextOptions::abort_handle`](crate::core::generate_text::GenerateTextOptions::abort_handle).
+///
+/// Clones share their state, so a clone can be moved to another task and
+/// aborted from there. Aborting drops in-flight HTTP requests and ends
+/// streams with a [`ModelError::Aborted`] error.
+#[derive(Debug, Clone, Default)]
+pub struct AbortHandle {
+    state: Arc<AbortState>,
+}
+
+#[derive(Debug, Default)]
+struct AbortState {
+    aborted: AtomicBool,
+    notify: Notify,
+}
+
+impl AbortHandle {
+    pub fn new() -> Self {
+        AbortHandle::default()
+    }
+
+    pub fn abort(&self) {
+        self.state.aborted.store(true, Ordering::SeqCst);
+        self.state.notify.notify_waiters();
+    }
+
+    pub fn is_aborted(&self) -> bool {
+        self.state.aborted.load(Ordering::SeqCst)
+    }
+
+    /// Resolves once the handle is aborted.
+    pub async fn aborted(&self) {
+        let mut notified = pin!(self.state.notify.notified());
+        notified.as_mut().enable();
+        if !self.is_aborted() {
+            notified.await;
+        }
+    }
+
+    /// Runs `future` until it finishes or the handle is aborted, e.g. to stop
+    /// a tool execution together with the call that requested it.
+    pub async fn run<F: Future>(&self, future: F) -> Result<F::Output, AbortReason> {
+        run_abortable(future, Some(self), None).await
+    }
+
+    /// Ends the stream with a [`ModelError::Aborted`] error once the handle is
+    /// aborted, see [`abortable_stream`].
+    pub fn wrap_stream(
+        &self,
+        response: LanguageModelDoStreamResponse,
+    ) -> LanguageModelDoStreamResponse {
+        abortable_stream(response, Some(self.clone()), None, None)
+    }
+}
+
+/// Ends the stream with a [`ModelError::Aborted`] error once `abort_handle` is
+/// aborted or a timeout elapses. Both timeouts count from this call; a
+/// stream is a single step, so the shorter one applies.
+///
+/// The error holds the text streamed until then and the usage of the last
+/// `Finish` part, if one was received.
+pub fn abortable_stream(
+    response: LanguageModelDoStreamResponse,
+    abort_handle: Option<AbortHandle>,
+    timeout: Option<Duration>,
+    step_timeout: Option<Duration>,
+) -> LanguageModelDoStreamResponse {
+    let started = Instant::now();
+    let deadline = next_step_timeout(timeout, step_timeout, started)
+        .map(|(timeout, reason)| (started + timeout, reason));
+    let state = StreamState {
+        abort_handle,
+        text: String::new(),
+        usage: LanguageModelUsage::default(),
+        done: false,
+    };
+    response.map_stream(move |parts| {
+        stream::unfold((parts, state), move |(mut parts, mut state)| async move {
+            if state.done {
+                return None;
+            }
+            let timeout = deadline.map(|(deadline, reason)| {
+                (deadline.saturating_duration_since(Instant::now()), reason)
+            });
+            match run_abortable(parts.next(), state.abort_handle.as_ref(), timeout).await {
+                Ok(Some(part)) => {
+                    match &part {
+                        Ok(LanguageModelStreamPart::TextDelta(delta)) => state.text.push_str(delta),
+                        Ok(LanguageModelStreamPart::Finish { usage, .. }) => {
+                            state.usage = usage.clone()
+                        }
+                        _ => {}
+                    }
+                    Some((part, (parts, state)))
+                }
+                Ok(None) => None,
+                Err(reason) => {
+                    state.done = true;
+                    let error = ModelError::Aborted(AbortedError {
+                        reason,
+                        text: std::mem::take(&mut state.text),
+                        usage: std::mem::take(&mut state.usage),
+                    });
+                    Some((Err(error), (parts, state)))
+                }
+            }
+        })
+        .boxed()
+    })
+}
+
+struct StreamState {
+    abort_handle: Option<AbortHandle>,
+    text: String,
+    usage: LanguageModelUsage,
+    done: bool,
+}
+
+/// The timeout of the next step: the step timeout or what is left of the
+/// call timeout, whichever is shorter.
+pub(crate) fn next_step_timeout(
+    timeout: Option<Duration>,
+    step_timeout: Option<Duration>,
+    started: Instant,
+) -> Option<(Duration, AbortReason)> {
+    let call = timeout.map(|timeout| {
+        (
+            timeout.saturating_sub(started.elapsed()),
+            AbortReason::CallTimeout(timeout),
+        )
+    });
+    let step = step_timeout.map(|timeout| (timeout, AbortReason::StepTimeout(timeout)));
+    match (call, step) {
+        (Some(call), Some(step)) if call.0 < step.0 => Some(call),
+        (call, None) => call,
+        (_, step) => step,
+    }
+}
+
+/// Runs `future` until it finishes, `abort_handle` is aborted or `timeout`
+/// elapses, in which case the given reason is returned.
+pub(crate) async fn run_abortable<F: Future>(
+    future: F,
+    abort_handle: Option<&AbortHandle>,
+    timeout: Option<(Duration, AbortReason)>,
+) -> Result<F::Output, AbortReason> {
+    let cancelled = async {
+        match abort_handle {
+            Some(abort_handle) => abort_handle.aborted().await,
+            None => future::pending().await,
+        }
+        AbortReason::Cancelled
+    };
+    let timed_out = async {
+        match timeout {
+            Some((timeout, reason)) => {
+                tokio::time::sleep(timeout).await;
+                reason
+            }
+            None => future::pending().await,
+        }
+    };
+    let (cancelled, timed_out) = (pin!(cancelled), pin!(timed_out));
+    let interrupted = future::select(cancelled, timed_out).map(|either| either.factor_first().0);
+
+    match future::select(pin!(future), pin!(interrupted)).await {
+        Either::Left((output, _)) => Ok(output),
+        Either::Right((reason, _)) => Err(reason),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use std::time::Duration;
+
+    use super::{abortable_stream, AbortHandle};
+    use crate::{
+        core::generate_text::{generate_text, GenerateTextOptions},
+        errors::{AbortReason, ModelError},
+        model::{
+            stream_part::LanguageModelStreamPart, usage::LanguageModelUsage, LanguageModel,
+            LanguageModelDoGenerateRequest, LanguageModelDoGenerateRequestInputFormat,
+        },
+        testing::{mock_text_stream, MockLanguageModel},
+    };
+
+    fn stream_request() -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest::new(
+            LanguageModelDoGenerateRequestInputFormat::Prompt,
+            Vec::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_generate_text_timeouts_and_abort() {
+        let mut model = MockLanguageModel::new()
+            .with_text_response("Kathmandu")
+            .with_text_response("Kathmandu")
+            .with_latency(Duration::from_millis(200));
+
+        let error = generate_text(
+            &mut model,
+            GenerateTextOptions::new()
+                .prompt("What is the capital of Nepal?".into())
+                .step_timeout(Duration::from_millis(20)),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            error,
+            ModelError::Aborted(error)
+                if error.reason == AbortReason::StepTimeout(Duration::from_millis(20))
+        ));
+
+        let abort_handle = AbortHandle::new();
+        tokio::spawn({
+            let abort_handle = abort_handle.clone();
+            async move {
+                tokio::time::sleep(Duration::from_millis(20)).await;
+                abort_handle.abort();
+            }
+        });
+        let error = generate_text(
+            &mut model,
+            GenerateTextOptions::new()
+                .prompt("What is the capital of Nepal?".into())
+                .timeout(Duration::from_secs(5))
+                .abort_handle(abort_handle),
+        )
+        .await
+        .err()
+        .unwrap();
+        assert!(matches!(
+            error,
+            ModelError::Aborted(error) if error.reason == AbortReason::Cancelled
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_abort_stream_keeps_partial_text() {
+        let model = MockLanguageModel::new()
+            .with_stream_response(mock_text_stream(&["Kath", "man", "du"]))
+            .with_chunk_delay(Duration::from_millis(30));
+        let abort_handle = AbortHandle::new();
+        let response = model.do_stream(stream_request()).await.unwrap();
+        let mut parts = abort_handle.wrap_stream(response).into_stream();
+
+        let mut text = String::new();
+        while let Some(part) = parts.next().await {
+            match part {
+                Ok(LanguageModelStreamPart::TextDelta(delta)) => {
+                    text.push_str(&delta);
+                    if text == "Kathman" {
+                        abort_handle.abort();
+                    }
+                }
+                Ok(_) => {}
+                Err(ModelError::Aborted(error)) => {
+                    assert_eq!(error.reason, AbortReason::Cancelled);
+                    assert_eq!(error.text, "Kathman");
+                    assert!(parts.next().await.is_none());
+                    return;
+                }
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+        }
+        panic!("the stream was not aborted");
+    }
+
+    #[tokio::test]
+    async fn test_stream_step_timeout() {
+        let model = MockLanguageModel::new()
+            .with_stream_response(mock_text_stream(&["Kath", "man", "du"]))
+            .with_chunk_delay(Duration::from_millis(50));
+        let response = model.do_stream(stream_request()).await.unwrap();
+        let parts: Vec<_> = abortable_stream(
+            response,
+            None,
+            Some(Duration::from_secs(5)),
+            Some(Duration::from_millis(75)),
+        )
+        .into_stream()
+        .collect()
+        .await;
+
+        assert_eq!(parts.len(), 2);
+        assert!(matches!(
+            &parts[1],
+            Err(ModelError::Aborted(error))
+                if error.reason == AbortReason::StepTimeout(Duration::from_millis(75))
+                    && error.text == "Kath"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_abort_stream_keeps_reported_usage() {
+        let model = MockLanguageModel::new()
+            .with_partial_stream_response(
+                mock_text_stream(&["Kathmandu"]),
+                ModelError::Other("connection reset".to_string()),
+            )
+            .with_chunk_delay(Duration::from_millis(10));
+        let abort_handle = AbortHandle::new();
+        let response = model.do_stream(stream_request()).await.unwrap();
+        let mut parts = abort_handle.wrap_stream(response).into_stream();
+
+        while let Some(part) = parts.next().await {
+            match part {
+                Ok(LanguageModelStreamPart::Finish { .. }) => abort_handle.abort(),
+                Ok(_) => {}
+                Err(ModelError::Aborted(error)) => {
+                    assert_eq!(error.text, "Kathmandu");
+                    assert_eq!(error.usage, LanguageModelUsage::new(10, 20));
+                    return;
+                }
+                Err(e) => panic!("unexpected error: {e}"),
+            }
+        }
+        panic!("the stream was not aborted");
+    }
+}
@@ -0,0 +1,350 @@
+//! Chats whose history is kept in a [`ConversationStore`].
+
+use futures::{stream, StreamExt};
+use std::{sync::Arc, time::Instant};
+
+use crate::{
+    core::{
+        abort::{abortable_stream, next_step_timeout, run_abortable},
+        generate_text::{
+            aborted, build_request, generate_text, GenerateTextOptions, GenerateTextResult,
+        },
+    },
+    errors::ModelError,
+    model::{stream_part::LanguageModelStreamPart, LanguageModel, LanguageModelDoStreamResponse},
+    prompt::{
+        AssistantContent, AssistantContentParts, CoreAssistantMessage, CoreMessage, ReasoningPart,
+        RedactedReasoningPart, TextPart, ToolCallPart,
+    },
+    store::ConversationStore,
+};
+
+/// A conversation in a [`ConversationStore`].
+pub struct Chat {
+    store: Arc<dyn ConversationStore>,
+    id: String,
+}
+
+impl Chat {
+    /// Starts a new, empty conversation.
+    pub async fn create(store: Arc<dyn ConversationStore>) -> Result<Self, ModelError> {
+        let id = store.create(Vec::new()).await?;
+        Ok(Chat { store, id })
+    }
+
+    /// Continues the conversation `id`.
+    pub fn open(store: Arc<dyn ConversationStore>, id: impl Into<String>) -> Self {
+        Chat {
+            store,
+            id: id.into(),
+        }
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub async fn messages(&self) -> Result<Vec<CoreMessage>, ModelError> {
+        Ok(self.store.load(&self.id).await?.messages)
+    }
+
+    /// Calls [`generate_text`] with the stored history followed by
+    /// `message`. The system prompt and settings are taken from `options`.
+    ///
+    /// `message` and the response messages are appended together once the
+    /// call succeeded; a failed call leaves the conversation unchanged.
+    pub async fn send<T: LanguageModel>(
+        &self,
+        model: &mut T,
+        message: CoreMessage,
+        options: GenerateTextOptions,
+    ) -> Result<GenerateTextResult, ModelError> {
+        let mut messages = self.messages().await?;
+        messages.push(message.clone());
+        let result = generate_text(model, options.messages(messages)).await?;
+
+        let mut new_messages = vec![message];
+        new_messages.extend(result.response_messages());
+        self.store.append(&self.id, new_messages).await?;
+        Ok(result)
+    }
+
+    /// Streams the response to the stored history followed by `message`,
+    /// like [`Chat::send`]. The abort handle and timeouts of `options` apply
+    /// to the stream.
+    ///
+    /// `message` and the streamed assistant message are appended together
+    /// once the stream finished. A stream that fails, is aborted or is
+    /// dropped early leaves the conversation unchanged; a failed append ends
+    /// the stream with its error.
+    pub async fn stream<T: LanguageModel>(
+        &self,
+        model: &mut T,
+        message: CoreMessage,
+        options: GenerateTextOptions,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let started = Instant::now();
+        let mut messages = self.messages().await?;
+        messages.push(message.clone());
+        let mut options = options.messages(messages);
+        let request = build_request(&*model, &mut options).await?;
+        let response = run_abortable(
+            model.do_stream(request),
+            options.abort_handle.as_ref(),
+            next_step_timeout(options.timeout, options.step_timeout, started),
+        )
+        .await
+        .map_err(aborted)??;
+        let response = abortable_stream(
+            response,
+            options.abort_handle,
+            options
+                .timeout
+                .map(|timeout| timeout.saturating_sub(started.elapsed())),
+            options.step_timeout,
+        );
+
+        let turn = StreamedTurn {
+            store: self.store.clone(),
+            id: self.id.clone(),
+            message,
+            parts: Vec::new(),
+            text: String::new(),
+            tool_calls: Vec::new(),
+            finished: false,
+            failed: false,
+        };
+        Ok(response.map_stream(|parts| {
+            stream::unfold(Some((parts, turn)), |state| async move {
+                let (mut parts, mut turn) = state?;
+                match parts.next().await {
+                    Some(part) => {
+                        turn.record(&part);
+                        Some((part, Some((parts, turn))))
+                    }
+                    None => turn.store().await.err().map(|error| (Err(error), None)),
+                }
+            })
+            .boxed()
+        }))
+    }
+}
+
+/// The user message and the response of [`Chat::stream`], collected from the
+/// stream until it finished.
+struct StreamedTurn {
+    store: Arc<dyn ConversationStore>,
+    id: String,
+    message: CoreMessage,
+    /// Reasoning, in the order it was streamed.
+    parts: Vec<AssistantContentParts>,
+    text: String,
+    tool_calls: Vec<ToolCallPart>,
+    finished: bool,
+    failed: bool,
+}
+
+impl StreamedTurn {
+    fn record(&mut self, part: &Result<LanguageModelStreamPart, ModelError>) {
+        match part {
+            Ok(LanguageModelStreamPart::TextDelta(delta)) => self.text.push_str(delta),
+            Ok(LanguageModelStreamPart::ReasoningDelta(delta)) => match self.parts.last_mut() {
+                Some(AssistantContentParts::Reasoning(reasoning))
+                    if reasoning.signature.is_none() =>
+                {
+                    reasoning.text.push_str(delta)
+                }
+                _ => self
+                    .parts
+                    .push(AssistantContentParts::Reasoning(ReasoningPart {
+                        text: delta.clone(),
+                        signature: None,
+                    })),
+            },
+            Ok(LanguageModelStreamPart::ReasoningSignature(signature)) => {
+                if let Some(AssistantContentParts::Reasoning(reasoning)) = self.parts.last_mut() {
+                    reasoning.signature = Some(signature.clone());
+                }
+            }
+            Ok(LanguageModelStreamPart::RedactedReasoning(data)) => {
+                self.parts.push(AssistantContentParts::RedactedReasoning(
+                    RedactedReasoningPart { data: data.clone() },
+                ))
+            }
+            Ok(LanguageModelStreamPart::ToolCall(call)) => self.tool_calls.push(ToolCallPart {
+                tool_call_id: call.tool_call_id.clone(),
+                tool_name: call.tool_name.clone(),
+                args: call.args.clone(),
+            }),
+            Ok(LanguageModelStreamPart::Finish { .. }) => self.finished = true,
+            Ok(LanguageModelStreamPart::Error(_)) | Err(_) => self.failed = true,
+            Ok(_) => {}
+        }
+    }
+
+    /// Appends the turn if the stream finished without errors.
+    async fn store(self) -> Result<(), ModelError> {
+        if !self.finished || self.failed {
+            return Ok(());
+        }
+        let mut parts = self.parts;
+        if !self.text.is_empty() {
+            parts.push(AssistantContentParts::Text(TextPart { text: self.text }));
+        }
+        parts.extend(
+            self.tool_calls
+                .into_iter()
+                .map(AssistantContentParts::ToolCall),
+        );
+        let response = CoreMessage::Assistant(CoreAssistantMessage {
+            content: AssistantContent::Parts(parts),
+        });
+        self.store
+            .append(&self.id, vec![self.message, response])
+            .await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use std::{sync::Arc, time::Duration};
+
+    use super::Chat;
+    use crate::{
+        core::{abort::AbortHandle, generate_text::GenerateTextOptions},
+        errors::ModelError,
+        model::{message::LanguageModelMessage, stream_part::LanguageModelStreamPart},
+        prompt::{
+            AssistantContent, AssistantContentParts, CoreMessage, CoreUserMessage, UserContent,
+        },
+        store::InMemoryConversationStore,
+        testing::{mock_text_stream, MockLanguageModel},
+    };
+
+    fn user(text: &str) -> CoreMessage {
+        CoreMessage::User(CoreUserMessage {
+            content: UserContent::Text(text.to_string()),
+        })
+    }
+
+    #[tokio::test]
+    async fn test_chat_appends_response_messages() {
+        let store = Arc::new(InMemoryConversationStore::new());
+        let chat = Chat::create(store.clone()).await.unwrap();
+        let mut model = MockLanguageModel::new()
+            .with_text_response("Kathmandu")
+            .with_error(ModelError::Other("offline".to_string()))
+            .with_text_response("About 1.5 million.");
+
+        let result = chat
+            .send(
+                &mut model,
+                user("What is the capital of Nepal?"),
+                GenerateTextOptions::default(),
+            )
+            .await
+            .unwrap();
+        assert_eq!(result.text(), "Kathmandu");
+
+        let failed = chat
+            .send(
+                &mut model,
+                user("How many people live there?"),
+                GenerateTextOptions::default(),
+            )
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(chat.messages().await.unwrap().len(), 2);
+
+        chat.send(
+            &mut model,
+            user("How many people live there?"),
+            GenerateTextOptions::default().system("Answer briefly.".into()),
+        )
+        .await
+        .unwrap();
+
+        let messages = chat.messages().await.unwrap();
+        assert_eq!(messages.len(), 4);
+        assert!(matches!(
+            &messages[1],
+            CoreMessage::Assistant(message)
+                if matches!(&message.content, AssistantContent::Parts(parts)
+                    if matches!(&parts[..], [AssistantContentParts::Text(part)]
+                        if part.text == "Kathmandu"))
+        ));
+
+        let requests = model.requests();
+        let last = &requests[requests.len() - 1].prompt;
+        assert_eq!(last.len(), 4);
+        assert!(
+            matches!(&last[0], LanguageModelMessage::System(system) if system == "Answer briefly.")
+        );
+        assert!(matches!(&last[2], LanguageModelMessage::Assistant(_)));
+    }
+
+    #[tokio::test]
+    async fn test_chat_stream_appends_finished_streams() {
+        let store = Arc::new(InMemoryConversationStore::new());
+        let chat = Chat::create(store.clone()).await.unwrap();
+        let mut model = MockLanguageModel::new()
+            .with_partial_stream_response(
+                vec![LanguageModelStreamPart::TextDelta("Kath".to_string())],
+                ModelError::Other("connection reset".to_string()),
+            )
+            .with_stream_response(mock_text_stream(&["Kath", "mandu"]))
+            .with_stream_response(mock_text_stream(&["About", " 1.5", " million."]))
+            .with_chunk_delay(Duration::from_millis(20));
+        let question = || user("What is the capital of Nepal?");
+
+        let failed: Vec<_> = chat
+            .stream(&mut model, question(), GenerateTextOptions::default())
+            .await
+            .unwrap()
+            .into_stream()
+            .collect()
+            .await;
+        assert!(failed.last().unwrap().is_err());
+        assert!(chat.messages().await.unwrap().is_empty());
+
+        let parts: Vec<_> = chat
+            .stream(&mut model, question(), GenerateTextOptions::default())
+            .await
+            .unwrap()
+            .into_stream()
+            .collect()
+            .await;
+        assert!(parts.iter().all(Result::is_ok));
+        let messages = chat.messages().await.unwrap();
+        assert_eq!(messages.len(), 2);
+        assert!(matches!(
+            &messages[1],
+            CoreMessage::Assistant(message)
+                if matches!(&message.content, AssistantContent::Parts(parts)
+                    if matches!(&parts[..], [AssistantContentParts::Text(part)]
+                        if part.text == "Kathmandu"))
+        ));
+
+        let abort_handle = AbortHandle::new();
+        let mut parts = chat
+            .stream(
+                &mut model,
+                user("How many people live there?"),
+                GenerateTextOptions::default().abort_handle(abort_handle.clone()),
+            )
+            .await
+            .unwrap()
+            .into_stream();
+        assert!(parts.next().await.unwrap().is_ok());
+        abort_handle.abort();
+        assert!(matches!(
+            parts.next().await,
+            Some(Err(ModelError::Aborted(_)))
+        ));
+        assert!(parts.next().await.is_none());
+        assert_eq!(chat.messages().await.unwrap().len(), 2);
+        assert_eq!(model.requests()[2].prompt.len(), 3);
+    }
+}
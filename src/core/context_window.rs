@@ -0,0 +1,606 @@
+//! Fits the conversation history into the context window of a model.
+
+use std::sync::Arc;
+
+use crate::{
+    errors::ModelError,
+    model::{
+        call_settings::LanguageModelCallSettings,
+        message::{
+            LanguageModelAssistantMessage, LanguageModelMessage, LanguageModelTextPart,
+            LanguageModelUserMessage,
+        },
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateRequestInputFormat,
+    },
+    prompt::{
+        convert_to_language_model_prompt::{convert_to_language_model_message, Downloads},
+        AssistantContent, AssistantContentParts, CoreMessage, CoreSystemMessage, UserContent,
+    },
+    tokenizer::{count_message_tokens, Encoding, Tokenizer},
+};
+
+/// Truncating a message to fewer tokens than this drops it instead.
+const MIN_TRUNCATED_TOKENS: usize = 32;
+
+const SUMMARY_INSTRUCTIONS: &str = "Summarize the conversation below. Keep facts, decisions, \
+    open questions and tool results that later messages may rely on. Reply with the summary only.";
+
+/// How messages are removed when the prompt exceeds the budget.
+#[derive(Clone, Default)]
+pub enum ContextStrategy {
+    /// Drops the oldest messages, truncating the oldest kept text message to
+    /// use up the budget.
+    #[default]
+    DropOldest,
+    /// Keeps at most the last `n` turns, a turn starting at a user message,
+    /// then drops the oldest messages.
+    KeepLastTurns(usize),
+    /// Keeps the first message, which usually states the task, and drops the
+    /// messages after it, oldest first.
+    TruncateMiddle,
+    /// Replaces the dropped messages with a summary written by `model`,
+    /// usually a cheaper one. The summary is added to the leading system
+    /// message.
+    Summarize {
+        model: Arc<dyn LanguageModel>,
+        max_summary_tokens: usize,
+    },
+}
+
+/// Keeps the prompt of every step within a token budget.
+///
+/// The strategy only applies when the prompt exceeds the budget. System
+/// messages are always kept, an assistant message with tool calls is dropped
+/// together with the tool results that answer it, and the kept conversation
+/// always starts with a user message. Tokens are counted with an OpenAI
+/// encoding, an estimate for other providers.
+#[derive(Clone)]
+pub struct ContextWindow {
+    max_tokens: usize,
+    strategy: ContextStrategy,
+    encoding: Encoding,
+}
+
+impl ContextWindow {
+    /// `max_tokens` is the budget of the prompt. When it is derived from the
+    /// context window of a model, leave room for the output.
+    pub fn new(max_tokens: usize) -> Self {
+        ContextWindow {
+            max_tokens,
+            strategy: ContextStrategy::default(),
+            encoding: Encoding::O200kBase,
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: ContextStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn with_encoding(mut self, encoding: Encoding) -> Self {
+        self.encoding = encoding;
+        self
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens
+    }
+
+    /// Returns the messages that fit the budget together with `system`.
+    pub async fn fit(
+        &self,
+        system: Option<&str>,
+        messages: Vec<CoreMessage>,
+    ) -> Result<Vec<CoreMessage>, ModelError> {
+        let tokenizer = Tokenizer::get(self.encoding);
+        let converted = messages
+            .iter()
+            .map(|message| convert_to_language_model_message(message, &Downloads::new()))
+            .collect::<Result<Vec<_>, _>>()?;
+        let counts: Vec<usize> = converted
+            .iter()
+            .map(|message| count_message_tokens(tokenizer, message))
+            .collect();
+
+        let mut system_tokens = system
+            .filter(|system| !system.is_empty())
+            .map(|system| {
+                count_message_tokens(tokenizer, &LanguageModelMessage::System(system.into()))
+            })
+            .unwrap_or_default();
+        if system_tokens + counts.iter().sum::<usize>() <= self.max_tokens {
+            return Ok(messages);
+        }
+        system_tokens += messages
+            .iter()
+            .zip(&counts)
+            .filter(|(message, _)| matches!(message, CoreMessage::System(_)))
+            .map(|(_, tokens)| tokens)
+            .sum::<usize>();
+        let Some(budget) = self.max_tokens.checked_sub(system_tokens) else {
+            return Err(ModelError::InvalidPrompt(format!(
+                "System messages need {system_tokens} tokens, more than the context window budget of {}",
+                self.max_tokens
+            )));
+        };
+
+        let units = units(&messages);
+        let fitter = Fitter {
+            tokenizer,
+            messages: &messages,
+            counts: &counts,
+        };
+        let selection = match &self.strategy {
+            ContextStrategy::DropOldest => fitter.keep_latest(&units, budget, true)?,
+            ContextStrategy::KeepLastTurns(turns) => {
+                let turn_starts: Vec<usize> = (0..units.len())
+                    .filter(|&unit| fitter.is_turn_start(&units[unit]))
+                    .collect();
+                let start = turn_starts
+                    .len()
+                    .checked_sub(*turns)
+                    .map_or(0, |turn| turn_starts[turn]);
+                let mut selection = fitter.keep_latest(&units[start..], budget, true)?;
+                selection.dropped.extend(units[..start].iter().flatten());
+                selection
+            }
+            ContextStrategy::TruncateMiddle => {
+                let head = units.first().map_or(0, |unit| fitter.tokens(unit));
+                if units.len() > 1 && head <= budget / 2 {
+                    let leading = !fitter.is_turn_start(&units[0]);
+                    fitter.keep_latest(&units[1..], budget - head, leading)?
+                } else {
+                    fitter.keep_latest(&units, budget, true)?
+                }
+            }
+            ContextStrategy::Summarize {
+                model,
+                max_summary_tokens,
+            } => {
+                let summary_tokens = count_message_tokens(
+                    tokenizer,
+                    &LanguageModelMessage::System(summary_text("")),
+                ) + max_summary_tokens;
+                let (start, _) = fitter.fit_latest(&units, budget.saturating_sub(summary_tokens));
+                let start = fitter.turn_start(&units, start);
+                if start == units.len() {
+                    return Err(fitter.latest_too_long(&units, budget));
+                }
+                let dropped: Vec<usize> = units[..start].iter().flatten().copied().collect();
+                let summary = summarize(
+                    model.as_ref(),
+                    *max_summary_tokens,
+                    dropped.iter().map(|&index| &converted[index]),
+                )
+                .await?;
+                Selection {
+                    dropped,
+                    truncated: None,
+                    summary: Some(summary),
+                }
+            }
+        };
+
+        Ok(selection.apply(messages, tokenizer))
+    }
+}
+
+/// Messages that are kept or dropped together: a single message, or an
+/// assistant message with tool calls followed by its tool results.
+type Unit = Vec<usize>;
+
+/// Groups the non-system messages into units.
+fn units(messages: &[CoreMessage]) -> Vec<Unit> {
+    let mut units: Vec<Unit> = Vec::new();
+    let mut awaiting_results = false;
+    for (index, message) in messages.iter().enumerate() {
+        match message {
+            CoreMessage::System(_) => {}
+            CoreMessage::Tool(_) if awaiting_results => {
+                units.last_mut().unwrap().push(index);
+            }
+            CoreMessage::Assistant(message) => {
+                awaiting_results = matches!(
+                    &message.content,
+                    AssistantContent::Parts(parts)
+                        if parts.iter().any(|part| matches!(part, AssistantContentParts::ToolCall(_)))
+                );
+                units.push(vec![index]);
+            }
+            _ => {
+                awaiting_results = false;
+                units.push(vec![index]);
+            }
+        }
+    }
+    units
+}
+
+struct Fitter<'a> {
+    tokenizer: &'a Tokenizer,
+    messages: &'a [CoreMessage],
+    counts: &'a [usize],
+}
+
+impl Fitter<'_> {
+    fn tokens(&self, unit: &Unit) -> usize {
+        unit.iter().map(|&index| self.counts[index]).sum()
+    }
+
+    fn is_turn_start(&self, unit: &Unit) -> bool {
+        matches!(self.messages[unit[0]], CoreMessage::User(_))
+    }
+
+    /// The first unit from `start` on that starts a turn, or `units.len()`.
+    fn turn_start(&self, units: &[Unit], start: usize) -> usize {
+        (start..units.len())
+            .find(|&unit| self.is_turn_start(&units[unit]))
+            .unwrap_or(units.len())
+    }
+
+    /// Keeps units from the newest backwards while they fit. Returns the
+    /// index of the oldest kept unit and the budget left.
+    fn fit_latest(&self, units: &[Unit], budget: usize) -> (usize, usize) {
+        let mut start = units.len();
+        let mut remaining = budget;
+        while let Some(tokens) = start.checked_sub(1).map(|unit| self.tokens(&units[unit])) {
+            if tokens > remaining {
+                break;
+            }
+            remaining -= tokens;
+            start -= 1;
+        }
+        (start, remaining)
+    }
+
+    /// Keeps the newest units that fit and truncates the next one if it is a
+    /// text message.
+    ///
+    /// When the units begin the conversation (`leading`), the kept units have
+    /// to start a turn: only a user message is truncated, and otherwise older
+    /// kept units are dropped up to the next user message.
+    fn keep_latest(
+        &self,
+        units: &[Unit],
+        budget: usize,
+        leading: bool,
+    ) -> Result<Selection, ModelError> {
+        let (mut start, remaining) = self.fit_latest(units, budget);
+        let mut truncated = None;
+        if let Some(unit) = start.checked_sub(1).map(|unit| &units[unit]) {
+            if let [index] = unit[..] {
+                let text_tokens = plain_text(&self.messages[index])
+                    .map(|text| self.tokenizer.count(text))
+                    .unwrap_or_default();
+                let overhead = self.counts[index].saturating_sub(text_tokens);
+                match remaining.checked_sub(overhead) {
+                    Some(tokens)
+                        if text_tokens > 0
+                            && tokens >= MIN_TRUNCATED_TOKENS
+                            && (!leading || self.is_turn_start(unit)) =>
+                    {
+                        start -= 1;
+                        truncated = Some((index, tokens));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        if leading && truncated.is_none() {
+            start = self.turn_start(units, start);
+        }
+        if start == units.len() {
+            return Err(self.latest_too_long(units, budget));
+        }
+        Ok(Selection {
+            dropped: units[..start].iter().flatten().copied().collect(),
+            truncated,
+            summary: None,
+        })
+    }
+
+    fn latest_too_long(&self, units: &[Unit], budget: usize) -> ModelError {
+        let start = (0..units.len())
+            .rev()
+            .find(|&unit| self.is_turn_start(&units[unit]))
+            .unwrap_or(0);
+        let tokens: usize = units[start..].iter().map(|unit| self.tokens(unit)).sum();
+        ModelError::InvalidPrompt(format!(
+            "The latest turn needs {tokens} tokens, more than the {budget} left in the context window budget"
+        ))
+    }
+}
+
+struct Selection {
+    dropped: Vec<usize>,
+    /// Message whose text is cut to its last tokens.
+    truncated: Option<(usize, usize)>,
+    /// Summary of the dropped messages, added to the leading system message.
+    summary: Option<String>,
+}
+
+impl Selection {
+    fn apply(self, messages: Vec<CoreMessage>, tokenizer: &Tokenizer) -> Vec<CoreMessage> {
+        let mut fitted = Vec::with_capacity(messages.len());
+        for (index, mut message) in messages.into_iter().enumerate() {
+            if self.dropped.contains(&index) {
+                continue;
+            }
+            if let Some((_, tokens)) = self.truncated.filter(|(truncated, _)| *truncated == index) {
+                truncate(&mut message, tokenizer, tokens);
+            }
+            fitted.push(message);
+        }
+        if let Some(summary) = self.summary {
+            let summary = summary_text(&summary);
+            match fitted.first_mut() {
+                Some(CoreMessage::System(system)) => {
+                    system.content = format!("{}\n\n{summary}", system.content);
+                }
+                _ => fitted.insert(
+                    0,
+                    CoreMessage::System(CoreSystemMessage { content: summary }),
+                ),
+            }
+        }
+        fitted
+    }
+}
+
+fn summary_text(summary: &str) -> String {
+    format!("Summary of the earlier conversation:\n{summary}")
+}
+
+/// The text of a message that consists of text only.
+fn plain_text(message: &CoreMessage) -> Option<&str> {
+    match message {
+        CoreMessage::User(message) => match &message.content {
+            UserContent::Text(text) => Some(text),
+            UserContent::Parts(_) => None,
+        },
+        CoreMessage::Assistant(message) => match &message.content {
+            AssistantContent::Text(text) => Some(text),
+            AssistantContent::Parts(_) => None,
+        },
+        _ => None,
+    }
+}
+
+/// Keeps the last `tokens` tokens of a text message.
+fn truncate(message: &mut CoreMessage, tokenizer: &Tokenizer, tokens: usize) {
+    let text = match message {
+        CoreMessage::User(message) => match &mut message.content {
+            UserContent::Text(text) => text,
+            UserContent::Parts(_) => return,
+        },
+        CoreMessage::Assistant(message) => match &mut message.content {
+            AssistantContent::Text(text) => text,
+            AssistantContent::Parts(_) => return,
+        },
+        _ => return,
+    };
+    let encoded = tokenizer.encode(text);
+    *text = tokenizer.decode(&encoded[encoded.len().saturating_sub(tokens)..]);
+}
+
+async fn summarize(
+    model: &dyn LanguageModel,
+    max_summary_tokens: usize,
+    messages: impl Iterator<Item = &LanguageModelMessage>,
+) -> Result<String, ModelError> {
+    let transcript = messages.map(transcript_line).collect::<Vec<_>>().join("\n");
+    let request = LanguageModelDoGenerateRequest::new(
+        LanguageModelDoGenerateRequestInputFormat::Messages,
+        vec![
+            LanguageModelMessage::System(SUMMARY_INSTRUCTIONS.to_string()),
+            LanguageModelMessage::User(vec![LanguageModelUserMessage::Text(
+                LanguageModelTextPart {
+                    text: transcript,
+                    provider_metadata: None,
+                },
+            )]),
+        ],
+    )
+    .with_call_settings(LanguageModelCallSettings {
+        max_tokens: Some(max_summary_tokens),
+        ..Default::default()
+    });
+    let response = model.do_generate(request).await?;
+    response
+        .text()
+        .map(str::to_string)
+        .ok_or_else(|| ModelError::NoContent("The summary model returned no text".to_string()))
+}
+
+fn transcript_line(message: &LanguageModelMessage) -> String {
+    match message {
+        LanguageModelMessage::System(text) => format!("system: {text}"),
+        LanguageModelMessage::User(parts) => {
+            let text: Vec<&str> = parts
+                .iter()
+                .filter_map(|part| match part {
+                    LanguageModelUserMessage::Text(part) => Some(part.text.as_str()),
+                    _ => None,
+                })
+                .collect();
+            format!("user: {}", text.join(" "))
+        }
+        LanguageModelMessage::Assistant(parts) => {
+            let text: Vec<String> = parts
+                .iter()
+                .filter_map(|part| match part {
+                    LanguageModelAssistantMessage::Text(part) => Some(part.text.clone()),
+                    LanguageModelAssistantMessage::ToolCall(part) => {
+                        Some(format!("[called {} with {}]", part.tool_name, part.args))
+                    }
+                    _ => None,
+                })
+                .collect();
+            format!("assistant: {}", text.join(" "))
+        }
+        LanguageModelMessage::Tool(parts) => parts
+            .iter()
+            .map(|part| format!("tool {}: {}", part.tool_name, part.result))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+
+    use super::{ContextStrategy, ContextWindow};
+    use crate::{
+        model::message::LanguageModelMessage,
+        prompt::{
+            AssistantContent, AssistantContentParts, CoreAssistantMessage, CoreMessage,
+            CoreSystemMessage, CoreToolMessage, CoreUserMessage, ToolCallPart, ToolResultPart,
+            UserContent,
+        },
+        testing::MockLanguageModel,
+    };
+
+    fn user(text: &str) -> CoreMessage {
+        CoreMessage::User(CoreUserMessage {
+            content: UserContent::Text(text.to_string()),
+        })
+    }
+
+    fn assistant(text: &str) -> CoreMessage {
+        CoreMessage::Assistant(CoreAssistantMessage {
+            content: AssistantContent::Text(text.to_string()),
+        })
+    }
+
+    fn conversation() -> Vec<CoreMessage> {
+        let long = "The weather in Kathmandu has been mild this week. ".repeat(20);
+        vec![
+            CoreMessage::System(CoreSystemMessage {
+                content: "You are a weather assistant.".to_string(),
+            }),
+            user(&long),
+            assistant(&long),
+            user("What is the weather in Pokhara?"),
+            CoreMessage::Assistant(CoreAssistantMessage {
+                content: AssistantContent::Parts(vec![AssistantContentParts::ToolCall(
+                    ToolCallPart {
+                        tool_call_id: "call_1".to_string(),
+                        tool_name: "weather".to_string(),
+                        args: r#"{"city":"Pokhara"}"#.to_string(),
+                    },
+                )]),
+            }),
+            CoreMessage::Tool(CoreToolMessage {
+                content: vec![ToolResultPart {
+                    tool_call_id: "call_1".to_string(),
+                    tool_name: "weather".to_string(),
+                    result: r#"{"temperature":24}"#.to_string(),
+                    is_error: None,
+                }],
+            }),
+            assistant("It is 24 degrees in Pokhara."),
+        ]
+    }
+
+    #[tokio::test]
+    async fn test_context_window_strategies() {
+        let messages = conversation();
+        let fitted = ContextWindow::new(10_000)
+            .fit(None, messages.clone())
+            .await
+            .unwrap();
+        assert_eq!(fitted.len(), messages.len());
+
+        // The long assistant message does not fit and is never truncated to
+        // lead the conversation, so the kept messages start at the next user
+        // message.
+        let fitted = ContextWindow::new(150)
+            .fit(None, messages.clone())
+            .await
+            .unwrap();
+        assert_eq!(fitted.len(), 5);
+        assert!(matches!(&fitted[0], CoreMessage::System(_)));
+        assert!(matches!(
+            &fitted[1],
+            CoreMessage::User(CoreUserMessage { content: UserContent::Text(text) })
+                if text == "What is the weather in Pokhara?"
+        ));
+        assert!(matches!(&fitted[3], CoreMessage::Tool(_)));
+
+        // A long user message is truncated to the remaining budget.
+        let fitted = ContextWindow::new(80)
+            .fit(None, vec![messages[1].clone(), assistant("It is mild.")])
+            .await
+            .unwrap();
+        assert_eq!(fitted.len(), 2);
+        assert!(matches!(
+            &fitted[0],
+            CoreMessage::User(CoreUserMessage { content: UserContent::Text(text) })
+                if text.len() < 500 && text.ends_with("mild this week. ")
+        ));
+
+        // The tool result is never kept without its call, and the last
+        // assistant message is never sent without the user message before it.
+        assert!(ContextWindow::new(35)
+            .fit(None, messages.clone())
+            .await
+            .is_err());
+
+        let fitted = ContextWindow::new(150)
+            .with_strategy(ContextStrategy::KeepLastTurns(1))
+            .fit(None, messages.clone())
+            .await
+            .unwrap();
+        assert_eq!(fitted.len(), 5);
+        assert!(matches!(&fitted[1], CoreMessage::User(_)));
+
+        assert!(ContextWindow::new(150)
+            .fit(Some(&"Be brief. ".repeat(100)), messages.clone())
+            .await
+            .is_err());
+
+        let fitted = ContextWindow::new(40)
+            .with_strategy(ContextStrategy::TruncateMiddle)
+            .fit(
+                None,
+                vec![
+                    user("Plan a trip."),
+                    assistant(&"Day one. ".repeat(100)),
+                    user("Shorter please."),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(fitted.len(), 2);
+        assert!(matches!(
+            &fitted[0],
+            CoreMessage::User(CoreUserMessage { content: UserContent::Text(text) })
+                if text == "Plan a trip."
+        ));
+
+        let summarizer =
+            Arc::new(MockLanguageModel::new().with_text_response("Mild week in Kathmandu."));
+        let fitted = ContextWindow::new(150)
+            .with_strategy(ContextStrategy::Summarize {
+                model: summarizer.clone(),
+                max_summary_tokens: 50,
+            })
+            .fit(None, messages)
+            .await
+            .unwrap();
+        assert_eq!(fitted.len(), 5);
+        assert!(matches!(
+            &fitted[0],
+            CoreMessage::System(CoreSystemMessage { content })
+                if content.starts_with("You are a weather assistant.")
+                    && content.ends_with("Mild week in Kathmandu.")
+        ));
+        assert!(matches!(&fitted[1], CoreMessage::User(_)));
+        let requests = summarizer.requests();
+        assert!(matches!(
+            &requests[0].prompt()[1],
+            LanguageModelMessage::User(_)
+        ));
+    }
+}
@@ -1,16 +1,35 @@
 pub mod options;
+mod result;
+
+use std::time::Instant;
+use tracing::Instrument;
 
 use crate::{
-    errors::ModelError,
-    model::{usage::LanguageModelUsage, LanguageModel, LanguageModelDoGenerateResponse},
-    prompt::{standarize_prompt::StandardizedPrompt, RetryPolicy},
+    core::{
+        abort::{next_step_timeout, run_abortable},
+        telemetry::{
+            chat_span, generate_text_span, record_error, record_finish, record_generate_response,
+        },
+    },
+    errors::{AbortReason, AbortedError, ModelError},
+    model::{
+        pricing::{CostEstimate, PricingCatalogue},
+        step_result::{StepResult, StepType},
+        usage::LanguageModelUsage,
+        LanguageModel, LanguageModelDoGenerateRequest,
+    },
+    prompt::{
+        convert_to_language_model_prompt::{convert_to_language_model_prompt, input_format},
+        standarize_prompt::StandardizedPrompt,
+    },
 };
 pub use options::GenerateTextOptions;
+pub use result::GenerateTextResult;
 
-pub fn generate_text<T: LanguageModel>(
+pub async fn generate_text<T: LanguageModel>(
     model: &mut T,
-    options: GenerateTextOptions,
-) -> Result<String, ModelError> {
+    mut options: GenerateTextOptions,
+) -> Result<GenerateTextResult, ModelError> {
     if options.max_steps < 1 {
         return Err(ModelError::InvalidArgument(format!(
             "OpenAIChatModel requires at least 1 step, got {}",
@@ -18,11 +37,92 @@ pub fn generate_text<T: LanguageModel>(
         )));
     }
 
-    let retry_policy = RetryPolicy::new(options.call_settings.max_retries);
-    let initial_prompt = StandardizedPrompt::try_from(options.prompt)?;
-    let current_model_response = LanguageModelDoGenerateResponse::default();
-    let current_tool_calls = Vec::new();
-    let current_mode_usage = LanguageModelUsage::default();
+    let started = Instant::now();
+    let max_retries = options.call_settings.max_retries_or_default();
+    let request = build_request(&*model, &mut options).await?;
+
+    let model = &*model;
+    let span = generate_text_span(model, &options.telemetry);
+    let result = async {
+        let step_span = chat_span(model, &request, &options.telemetry);
+        let response = run_abortable(
+            options
+                .retry_settings
+                .retry(max_retries, move || model.do_generate(request.clone()))
+                .instrument(step_span.clone()),
+            options.abort_handle.as_ref(),
+            next_step_timeout(options.timeout, options.step_timeout, started),
+        )
+        .await
+        .map_err(aborted)?;
+        match &response {
+            Ok(response) => record_generate_response(&step_span, response, &options.telemetry),
+            Err(error) => record_error(&step_span, error),
+        }
+
+        let step = StepResult::from_response(response?, StepType::Initial);
+        let cost = estimate_cost(&options.pricing, model, &step);
+        Ok(GenerateTextResult::new(vec![step.with_cost(cost)]))
+    }
+    .instrument(span.clone())
+    .await;
+
+    match &result {
+        Ok(result) => record_finish(&span, result.finish_reason(), &result.usage()),
+        Err(error) => record_error(&span, error),
+    }
+    result
+}
+
+/// Standardizes the prompt of `options`, fits it into the context window and
+/// converts it for `model`. The prompt is taken out of `options`.
+pub(crate) async fn build_request(
+    model: &dyn LanguageModel,
+    options: &mut GenerateTextOptions,
+) -> Result<LanguageModelDoGenerateRequest, ModelError> {
+    let mut prompt = StandardizedPrompt::try_from(std::mem::take(&mut options.prompt))?;
+    if let Some(context_window) = &options.context_window {
+        let messages = context_window
+            .fit(prompt.system(), prompt.messages().to_vec())
+            .await?;
+        prompt = prompt.with_messages(messages);
+    }
+    Ok(LanguageModelDoGenerateRequest {
+        call_settings: Some(options.call_settings.clone()),
+        input_format: input_format(&prompt),
+        prompt: convert_to_language_model_prompt(
+            &prompt,
+            model,
+            &*options.transport,
+            &options.download_settings,
+        )
+        .await?,
+        tools: None,
+        tool_choice: None,
+        provider_metadata: None,
+    })
+}
+
+/// Prices the step by the model that served it, which may be a snapshot of
+/// the requested model, falling back to the requested model.
+fn estimate_cost(
+    pricing: &PricingCatalogue,
+    model: &dyn LanguageModel,
+    step: &StepResult,
+) -> Option<CostEstimate> {
+    let provider = model.provider();
+    step.response()
+        .model_response()
+        .and_then(|response| pricing.estimate(&provider, &response.model_id, step.usage()))
+        .or_else(|| pricing.estimate(&provider, &model.model_id(), step.usage()))
+}
 
-    Ok("yeah yeah yeha".to_string())
+/// The error of a call aborted before it returned, which reports neither text
+/// nor usage, so both are empty. `generate_text` runs a single step.
+pub(crate) fn aborted(reason: AbortReason) -> ModelError {
+    ModelError::Aborted(AbortedError {
+        reason,
+        text: String::new(),
+        usage: LanguageModelUsage::default(),
+    })
 }
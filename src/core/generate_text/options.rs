@@ -1,6 +1,13 @@
+use std::{sync::Arc, time::Duration};
+
 use crate::{
-    model::call_settings::LanguageModelCallSettings,
-    prompt::{CoreMessage, Prompt},
+    core::{
+        abort::AbortHandle, context_window::ContextWindow, retry::RetrySettings,
+        telemetry::TelemetrySettings,
+    },
+    model::{call_settings::LanguageModelCallSettings, pricing::PricingCatalogue},
+    prompt::{CoreMessage, Prompt, UIMessage},
+    transport::{default_transport, DownloadSettings, HttpTransport},
 };
 
 pub struct GenerateTextOptions {
@@ -14,6 +21,24 @@ pub struct GenerateTextOptions {
     By default, it's set to 1, which means that only a single LLM call is made.
          */
     pub max_steps: u32,
+    /// How failed calls are retried. The number of retries is set by `max_retries`.
+    pub retry_settings: RetrySettings,
+    /// Maximum duration of the whole call, including retries and all steps.
+    pub timeout: Option<Duration>,
+    /// Maximum duration of a single step, including its retries.
+    pub step_timeout: Option<Duration>,
+    /// Aborts the call when triggered.
+    pub abort_handle: Option<AbortHandle>,
+    /// What is recorded on the tracing spans of the call.
+    pub telemetry: TelemetrySettings,
+    /// Prices used to estimate the cost of every step.
+    pub pricing: PricingCatalogue,
+    /// Token budget the messages of every step are fitted into.
+    pub context_window: Option<ContextWindow>,
+    /// Downloads image and file URLs that the model cannot fetch itself.
+    pub transport: Arc<dyn HttpTransport>,
+    /// Size limit and URL filter of those downloads.
+    pub download_settings: DownloadSettings,
 }
 
 impl Default for GenerateTextOptions {
@@ -22,6 +47,15 @@ impl Default for GenerateTextOptions {
             call_settings: LanguageModelCallSettings::default(),
             prompt: Prompt::default(),
             max_steps: 1,
+            retry_settings: RetrySettings::default(),
+            timeout: None,
+            step_timeout: None,
+            abort_handle: None,
+            telemetry: TelemetrySettings::default(),
+            pricing: PricingCatalogue::default(),
+            context_window: None,
+            transport: default_transport(),
+            download_settings: DownloadSettings::default(),
         }
     }
 }
@@ -31,8 +65,13 @@ impl GenerateTextOptions {
         GenerateTextOptions::default()
     }
 
+    pub fn max_tokens(mut self, max_tokens: usize) -> Self {
+        self.call_settings.max_tokens = Some(max_tokens);
+        self
+    }
+
     pub fn temperature(mut self, temperature: f32) -> Self {
-        self.call_settings.temperature = temperature;
+        self.call_settings.temperature = Some(temperature);
         self
     }
 
@@ -63,7 +102,43 @@ impl GenerateTextOptions {
         self
     }
     pub fn max_retries(mut self, max_retries: u32) -> Self {
-        self.call_settings.max_retries = max_retries;
+        self.call_settings.max_retries = Some(max_retries);
+        self
+    }
+    pub fn retry_settings(mut self, retry_settings: RetrySettings) -> Self {
+        self.retry_settings = retry_settings;
+        self
+    }
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+    pub fn step_timeout(mut self, step_timeout: Duration) -> Self {
+        self.step_timeout = Some(step_timeout);
+        self
+    }
+    pub fn abort_handle(mut self, abort_handle: AbortHandle) -> Self {
+        self.abort_handle = Some(abort_handle);
+        self
+    }
+    pub fn telemetry(mut self, telemetry: TelemetrySettings) -> Self {
+        self.telemetry = telemetry;
+        self
+    }
+    pub fn pricing(mut self, pricing: PricingCatalogue) -> Self {
+        self.pricing = pricing;
+        self
+    }
+    pub fn context_window(mut self, context_window: ContextWindow) -> Self {
+        self.context_window = Some(context_window);
+        self
+    }
+    pub fn transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+    pub fn download_settings(mut self, download_settings: DownloadSettings) -> Self {
+        self.download_settings = download_settings;
         self
     }
     pub fn headers(mut self, headers: Vec<(String, String)>) -> Self {
@@ -82,6 +157,10 @@ impl GenerateTextOptions {
         self.prompt.messages = Some(messages);
         self
     }
+    pub fn ui_messages(mut self, ui_messages: Vec<UIMessage>) -> Self {
+        self.prompt.ui_messages = Some(ui_messages);
+        self
+    }
     pub fn max_steps(mut self, max_steps: u32) -> Self {
         self.max_steps = max_steps;
         self
@@ -0,0 +1,90 @@
+use crate::{
+    model::{
+        finish_reason::LanguageModelFinishReason,
+        function_tool_call::LanguageModelFunctionToolCall,
+        pricing::CostEstimate,
+        request_metadata::LanguageModelRequestMetadata,
+        step_result::{ResponseMessage, StepResult},
+        usage::LanguageModelUsage,
+    },
+    prompt::CoreMessage,
+};
+
+/// The result of [`generate_text`](super::generate_text).
+///
+/// Accessors other than `steps` and `usage` describe the last step.
+pub struct GenerateTextResult {
+    steps: Vec<StepResult>,
+}
+
+impl GenerateTextResult {
+    pub(crate) fn new(steps: Vec<StepResult>) -> Self {
+        GenerateTextResult { steps }
+    }
+
+    pub fn steps(&self) -> &[StepResult] {
+        &self.steps
+    }
+
+    fn last_step(&self) -> Option<&StepResult> {
+        self.steps.last()
+    }
+
+    pub fn text(&self) -> &str {
+        self.last_step().map(StepResult::text).unwrap_or_default()
+    }
+
+    pub fn reasoning_text(&self) -> Option<&str> {
+        self.last_step().and_then(StepResult::reasoning_text)
+    }
+
+    pub fn tool_calls(&self) -> &[LanguageModelFunctionToolCall] {
+        self.last_step()
+            .map(StepResult::tool_calls)
+            .unwrap_or_default()
+    }
+
+    pub fn finish_reason(&self) -> LanguageModelFinishReason {
+        self.last_step()
+            .map(StepResult::finish_reason)
+            .unwrap_or_default()
+    }
+
+    /// The usage summed over all steps.
+    pub fn usage(&self) -> LanguageModelUsage {
+        self.steps
+            .iter()
+            .fold(LanguageModelUsage::default(), |total, step| {
+                &total + step.usage()
+            })
+    }
+
+    /// The estimated cost summed over all steps, or `None` if the price of a
+    /// step's model is unknown.
+    pub fn cost(&self) -> Option<CostEstimate> {
+        self.steps
+            .iter()
+            .try_fold(CostEstimate::default(), |total, step| {
+                Some(total + step.cost()?)
+            })
+    }
+
+    pub fn request(&self) -> Option<&LanguageModelRequestMetadata> {
+        self.last_step().map(StepResult::request)
+    }
+
+    /// The messages generated over all steps, to be appended to the
+    /// conversation.
+    pub fn response_messages(&self) -> Vec<CoreMessage> {
+        self.steps
+            .iter()
+            .flat_map(|step| step.response().messages())
+            .map(|message| match message {
+                ResponseMessage::AssistantResponse(_, message) => {
+                    CoreMessage::Assistant(message.clone())
+                }
+                ResponseMessage::ToolResponse(_, message) => CoreMessage::Tool(message.clone()),
+            })
+            .collect()
+    }
+}
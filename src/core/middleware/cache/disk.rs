@@ -0,0 +1,135 @@
+use async_trait::async_trait;
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+use tokio::fs;
+
+use super::{CacheBackend, CacheEntry};
+use crate::errors::ModelError;
+
+/// Stores every entry as a JSON file in a directory, so the cache survives
+/// across runs and can be shared between processes.
+pub struct DiskCache {
+    directory: PathBuf,
+}
+
+impl DiskCache {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        DiskCache {
+            directory: directory.into(),
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.directory.join(format!("{key}.json"))
+    }
+}
+
+fn io_error(action: &str, path: &Path, e: io::Error) -> ModelError {
+    ModelError::InternalError(format!("Failed to {action} {}: {e}", path.display()))
+}
+
+#[async_trait]
+impl CacheBackend for DiskCache {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, ModelError> {
+        let path = self.path(key);
+        let content = match fs::read_to_string(&path).await {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(io_error("read cache entry", &path, e)),
+        };
+        // An entry written by an incompatible version is treated as a miss
+        // and overwritten by the next response.
+        Ok(serde_json::from_str(&content).ok())
+    }
+
+    async fn set(&self, key: &str, entry: CacheEntry) -> Result<(), ModelError> {
+        fs::create_dir_all(&self.directory)
+            .await
+            .map_err(|e| io_error("create cache directory", &self.directory, e))?;
+        let content = serde_json::to_string(&entry).map_err(|e| {
+            ModelError::InternalError(format!("Failed to serialize cache entry: {e}"))
+        })?;
+
+        // Write to a temporary file first so readers never see a partial entry.
+        // The random suffix keeps concurrent writers of the same key apart.
+        let path = self.path(key);
+        let temporary = self
+            .directory
+            .join(format!("{key}.json.{:016x}.tmp", rand::random::<u64>()));
+        if let Err(e) = fs::write(&temporary, content).await {
+            return Err(io_error("write cache entry", &temporary, e));
+        }
+        if let Err(e) = fs::rename(&temporary, &path).await {
+            let _ = fs::remove_file(&temporary).await;
+            return Err(io_error("write cache entry", &path, e));
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), ModelError> {
+        let path = self.path(key);
+        match fs::remove_file(&path).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => {
+                Err(io_error("remove cache entry", &path, e))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::DiskCache;
+    use crate::{
+        core::middleware::cache::{CacheBackend, CacheEntry, CachedResponse},
+        model::LanguageModelDoGenerateResponse,
+        utils,
+    };
+
+    #[tokio::test]
+    async fn test_disk_cache_round_trip() {
+        let directory = std::env::temp_dir().join(format!(
+            "cortex-cache-{}-{}",
+            std::process::id(),
+            utils::current_timestamp_millis()
+        ));
+        let cache = DiskCache::new(&directory);
+        let mut response = LanguageModelDoGenerateResponse::default();
+        *response.text_mut() = Some("Kathmandu".to_string());
+
+        cache
+            .set(
+                "abc",
+                CacheEntry {
+                    request: json!({ "prompt": "Nepal?" }),
+                    response: CachedResponse::Generate(Box::new(response)),
+                    expires_at: None,
+                },
+            )
+            .await
+            .unwrap();
+
+        let entry = DiskCache::new(&directory)
+            .get("abc")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(entry.request, json!({ "prompt": "Nepal?" }));
+        assert!(matches!(
+            entry.response,
+            CachedResponse::Generate(response) if response.text() == Some("Kathmandu")
+        ));
+
+        cache.remove("abc").await.unwrap();
+        assert!(cache.get("abc").await.unwrap().is_none());
+        std::fs::remove_dir_all(&directory).unwrap();
+    }
+}
@@ -0,0 +1,108 @@
+use async_trait::async_trait;
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Mutex,
+};
+
+use super::{CacheBackend, CacheEntry};
+use crate::errors::ModelError;
+
+/// An in-memory cache that evicts the least recently used entry once it
+/// holds `capacity` entries.
+pub struct InMemoryCache {
+    capacity: usize,
+    state: Mutex<InMemoryCacheState>,
+}
+
+#[derive(Default)]
+struct InMemoryCacheState {
+    entries: HashMap<String, CacheEntry>,
+    /// Keys from least to most recently used.
+    order: VecDeque<String>,
+}
+
+impl InMemoryCacheState {
+    fn touch(&mut self, key: &str) {
+        self.order.retain(|k| k != key);
+        self.order.push_back(key.to_string());
+    }
+}
+
+impl InMemoryCache {
+    pub fn new(capacity: usize) -> Self {
+        InMemoryCache {
+            capacity: capacity.max(1),
+            state: Mutex::new(InMemoryCacheState::default()),
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.state.lock().unwrap().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl CacheBackend for InMemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, ModelError> {
+        let mut state = self.state.lock().unwrap();
+        let entry = state.entries.get(key).cloned();
+        if entry.is_some() {
+            state.touch(key);
+        }
+        Ok(entry)
+    }
+
+    async fn set(&self, key: &str, entry: CacheEntry) -> Result<(), ModelError> {
+        let mut state = self.state.lock().unwrap();
+        state.entries.insert(key.to_string(), entry);
+        state.touch(key);
+        while state.entries.len() > self.capacity {
+            let Some(oldest) = state.order.pop_front() else {
+                break;
+            };
+            state.entries.remove(&oldest);
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), ModelError> {
+        let mut state = self.state.lock().unwrap();
+        state.entries.remove(key);
+        state.order.retain(|k| k != key);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::InMemoryCache;
+    use crate::core::middleware::cache::{CacheBackend, CacheEntry, CachedResponse};
+
+    fn entry() -> CacheEntry {
+        CacheEntry {
+            request: json!({}),
+            response: CachedResponse::Stream(Box::default()),
+            expires_at: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_evicts_least_recently_used() {
+        let cache = InMemoryCache::new(2);
+        cache.set("a", entry()).await.unwrap();
+        cache.set("b", entry()).await.unwrap();
+        assert!(cache.get("a").await.unwrap().is_some());
+        cache.set("c", entry()).await.unwrap();
+
+        assert_eq!(cache.len(), 2);
+        assert!(cache.get("a").await.unwrap().is_some());
+        assert!(cache.get("b").await.unwrap().is_none());
+        assert!(cache.get("c").await.unwrap().is_some());
+    }
+}
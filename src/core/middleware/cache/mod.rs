@@ -0,0 +1,503 @@
+//! Response caching keyed on the normalized request.
+//!
+//! Two requests share a cache entry when they go to the same provider and
+//! model with the same prompt, call settings and tools. Per-call HTTP headers
+//! and retry settings do not affect the key.
+//!
+//! Pass `{"cache": {"bypass": true}}` in the request `provider_metadata` to
+//! skip the cache for a single call. Every response reports whether it was
+//! served from the cache under `provider_metadata["cache"]["hit"]`.
+//!
+//! Backend failures never fail a call: they are logged, a failed lookup is
+//! treated as a miss and a failed write only loses the entry.
+
+mod disk;
+mod memory;
+
+pub use disk::DiskCache;
+pub use memory::InMemoryCache;
+
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JSONValue};
+use std::{collections::HashMap, sync::Arc, time::Duration};
+
+use super::{LanguageModelMiddleware, LanguageModelMiddlewareCallType};
+use crate::{
+    errors::ModelError,
+    model::{
+        call_warning::LanguageModelCallWarning, request_metadata::LanguageModelRequestMetadata,
+        stream_part::LanguageModelStreamPart, LanguageModel, LanguageModelDoGenerateRequest,
+        LanguageModelDoGenerateResponse, LanguageModelDoStreamResponse,
+    },
+    provider::metadata::LanguageModelProviderMetadata,
+    utils,
+};
+
+/// Key of the cache options and of the hit indicator in `provider_metadata`.
+pub const CACHE_METADATA_KEY: &str = "cache";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum CachedResponse {
+    Generate(Box<LanguageModelDoGenerateResponse>),
+    Stream(Box<CachedStream>),
+}
+
+/// A stream that finished without errors, with the metadata of its response.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedStream {
+    pub parts: Vec<LanguageModelStreamPart>,
+    pub request_body: Option<LanguageModelRequestMetadata>,
+    pub response_headers: Vec<(String, String)>,
+    pub warnings: Vec<LanguageModelCallWarning>,
+}
+
+impl CachedStream {
+    fn from_response(response: &LanguageModelDoStreamResponse) -> Self {
+        CachedStream {
+            parts: Vec::new(),
+            request_body: response.request_body().cloned(),
+            response_headers: response.response_headers().to_vec(),
+            warnings: response.warnings().to_vec(),
+        }
+    }
+
+    fn into_response(self) -> LanguageModelDoStreamResponse {
+        let parts = self
+            .parts
+            .into_iter()
+            .map(|part| Ok(mark_part_hit(part, true)));
+        let response = LanguageModelDoStreamResponse::new(stream::iter(parts).boxed())
+            .with_response_headers(self.response_headers)
+            .with_warnings(self.warnings);
+        match self.request_body {
+            Some(request_body) => response.with_request_body(request_body),
+            None => response,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// The normalized request. It is compared on lookup, so a hash collision
+    /// can never return the response of another request.
+    pub request: JSONValue,
+    pub response: CachedResponse,
+    /// Unix timestamp in milliseconds after which the entry is stale.
+    pub expires_at: Option<u64>,
+}
+
+impl CacheEntry {
+    fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= utils::current_timestamp_millis())
+    }
+}
+
+/// Storage for cache entries.
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>, ModelError>;
+    async fn set(&self, key: &str, entry: CacheEntry) -> Result<(), ModelError>;
+    async fn remove(&self, key: &str) -> Result<(), ModelError>;
+}
+
+/// Serves repeated requests from a [`CacheBackend`] instead of the model.
+pub struct CacheMiddleware {
+    backend: Arc<dyn CacheBackend>,
+    ttl: Option<Duration>,
+}
+
+impl CacheMiddleware {
+    pub fn new(backend: Arc<dyn CacheBackend>) -> Self {
+        CacheMiddleware { backend, ttl: None }
+    }
+
+    /// Entries older than `ttl` are ignored and replaced. By default entries
+    /// never expire.
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    async fn lookup(&self, key: &str, normalized: &JSONValue) -> Option<CachedResponse> {
+        match self.backend.get(key).await {
+            Ok(Some(entry)) if entry.is_expired() => {
+                if let Err(error) = self.backend.remove(key).await {
+                    tracing::warn!(key, error = %error, "failed to remove expired cache entry");
+                }
+                None
+            }
+            Ok(Some(entry)) if &entry.request == normalized => Some(entry.response),
+            Ok(_) => None,
+            Err(error) => {
+                tracing::warn!(key, error = %error, "cache lookup failed");
+                None
+            }
+        }
+    }
+
+    fn entry(&self, request: JSONValue, response: CachedResponse) -> CacheEntry {
+        CacheEntry {
+            request,
+            response,
+            expires_at: self
+                .ttl
+                .map(|ttl| utils::current_timestamp_millis() + ttl.as_millis() as u64),
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageModelMiddleware for CacheMiddleware {
+    async fn wrap_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        if is_bypassed(&request) {
+            return model.do_generate(request).await;
+        }
+
+        let normalized =
+            normalize_request(model, &request, LanguageModelMiddlewareCallType::Generate)?;
+        let key = cache_key(&normalized);
+        if let Some(CachedResponse::Generate(mut response)) = self.lookup(&key, &normalized).await {
+            mark_hit(response.provider_metadata_mut(), true);
+            return Ok(*response);
+        }
+
+        let mut response = model.do_generate(request).await?;
+        let entry = self.entry(
+            normalized,
+            CachedResponse::Generate(Box::new(response.clone())),
+        );
+        store(self.backend.as_ref(), &key, entry).await;
+        mark_hit(response.provider_metadata_mut(), false);
+        Ok(response)
+    }
+
+    async fn wrap_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        if is_bypassed(&request) {
+            return model.do_stream(request).await;
+        }
+
+        let normalized =
+            normalize_request(model, &request, LanguageModelMiddlewareCallType::Stream)?;
+        let key = cache_key(&normalized);
+        if let Some(CachedResponse::Stream(cached)) = self.lookup(&key, &normalized).await {
+            return Ok(cached.into_response());
+        }
+
+        let response = model.do_stream(request).await?;
+        let backend = self.backend.clone();
+        let cached = CachedStream::from_response(&response);
+        let entry = self.entry(normalized, CachedResponse::Stream(Box::new(cached)));
+        Ok(response.map_stream(|stream| {
+            // Every part is forwarded and collected; the stream is only stored
+            // once it ended without errors.
+            stream::unfold(
+                (stream, entry, true),
+                move |(mut stream, mut entry, mut cacheable)| {
+                    let backend = backend.clone();
+                    let key = key.clone();
+                    async move {
+                        match stream.next().await {
+                            Some(part) => {
+                                match (&part, &mut entry.response) {
+                                    (Ok(LanguageModelStreamPart::Error(_)) | Err(_), _) => {
+                                        cacheable = false
+                                    }
+                                    (Ok(part), CachedResponse::Stream(cached)) => {
+                                        cached.parts.push(part.clone())
+                                    }
+                                    _ => {}
+                                }
+                                let part = part.map(|part| mark_part_hit(part, false));
+                                Some((part, (stream, entry, cacheable)))
+                            }
+                            None => {
+                                if cacheable {
+                                    store(backend.as_ref(), &key, entry).await;
+                                }
+                                None
+                            }
+                        }
+                    }
+                },
+            )
+            .boxed()
+        }))
+    }
+}
+
+async fn store(backend: &dyn CacheBackend, key: &str, entry: CacheEntry) {
+    if let Err(error) = backend.set(key, entry).await {
+        tracing::warn!(key, error = %error, "cache write failed");
+    }
+}
+
+fn is_bypassed(request: &LanguageModelDoGenerateRequest) -> bool {
+    request
+        .provider_metadata()
+        .and_then(|metadata| metadata.get(CACHE_METADATA_KEY))
+        .and_then(|options| options.get("bypass"))
+        .and_then(JSONValue::as_bool)
+        .unwrap_or(false)
+}
+
+/// The parts of a request that determine the response, as JSON. Maps are
+/// serialized with sorted keys, so equal requests give equal JSON.
+fn normalize_request(
+    model: &dyn LanguageModel,
+    request: &LanguageModelDoGenerateRequest,
+    call_type: LanguageModelMiddlewareCallType,
+) -> Result<JSONValue, ModelError> {
+    let mut request = request.clone();
+    if let Some(settings) = request.call_settings_mut() {
+        settings.headers.clear();
+        settings.max_retries = None;
+    }
+    if let Some(metadata) = request.provider_metadata_mut() {
+        metadata.remove(CACHE_METADATA_KEY);
+    }
+    let request = serde_json::to_value(&request)
+        .map_err(|e| ModelError::InternalError(format!("Failed to serialize request: {e}")))?;
+
+    Ok(json!({
+        "provider": model.provider(),
+        "model_id": model.model_id(),
+        "call_type": match call_type {
+            LanguageModelMiddlewareCallType::Generate => "generate",
+            LanguageModelMiddlewareCallType::Stream => "stream",
+        },
+        "request": request,
+    }))
+}
+
+/// FNV-1a hash of the normalized request. It only has to be stable across
+/// runs; collisions are ruled out by comparing the stored request.
+fn cache_key(normalized: &JSONValue) -> String {
+    let hash = normalized
+        .to_string()
+        .bytes()
+        .fold(0xcbf29ce484222325u64, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        });
+    format!("{hash:016x}")
+}
+
+fn mark_hit(metadata: &mut Option<LanguageModelProviderMetadata>, hit: bool) {
+    metadata.get_or_insert_with(HashMap::new).insert(
+        CACHE_METADATA_KEY.to_string(),
+        HashMap::from([("hit".to_string(), json!(hit))]),
+    );
+}
+
+fn mark_part_hit(part: LanguageModelStreamPart, hit: bool) -> LanguageModelStreamPart {
+    match part {
+        LanguageModelStreamPart::Finish {
+            finish_reason,
+            usage,
+            mut provider_metadata,
+            logprobs,
+        } => {
+            mark_hit(&mut provider_metadata, hit);
+            LanguageModelStreamPart::Finish {
+                finish_reason,
+                usage,
+                provider_metadata,
+                logprobs,
+            }
+        }
+        part => part,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use serde_json::json;
+    use std::{collections::HashMap, sync::Arc, time::Duration};
+
+    use async_trait::async_trait;
+
+    use super::{CacheBackend, CacheEntry, CacheMiddleware, InMemoryCache};
+    use crate::{
+        core::middleware::wrap_language_model,
+        errors::ModelError,
+        model::{
+            finish_reason::LanguageModelFinishReason,
+            message::{LanguageModelMessage, LanguageModelTextPart, LanguageModelUserMessage},
+            stream_part::LanguageModelStreamPart,
+            usage::LanguageModelUsage,
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat,
+        },
+        testing::{mock_text_stream, MockLanguageModel},
+    };
+
+    fn request(text: &str) -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest::new(
+            LanguageModelDoGenerateRequestInputFormat::Messages,
+            vec![LanguageModelMessage::User(vec![
+                LanguageModelUserMessage::Text(LanguageModelTextPart {
+                    text: text.to_string(),
+                    provider_metadata: None,
+                }),
+            ])],
+        )
+    }
+
+    fn cache_hit(metadata: Option<&HashMap<String, HashMap<String, serde_json::Value>>>) -> bool {
+        metadata.unwrap()["cache"]["hit"] == json!(true)
+    }
+
+    #[tokio::test]
+    async fn test_cache_generate_and_stream() {
+        // Every scripted response can only be served once, so a second
+        // successful call has to come from the cache.
+        let model = wrap_language_model(
+            MockLanguageModel::new()
+                .with_text_response("Kathmandu")
+                .with_stream_response(mock_text_stream(&["Thim", "phu"]))
+                .with_text_response("Pokhara"),
+            vec![Box::new(CacheMiddleware::new(Arc::new(
+                InMemoryCache::new(10),
+            )))],
+        );
+
+        let first = model.do_generate(request("Nepal?")).await.unwrap();
+        assert!(!cache_hit(first.provider_metadata()));
+        let second = model.do_generate(request("Nepal?")).await.unwrap();
+        assert_eq!(second.text(), Some("Kathmandu"));
+        assert!(cache_hit(second.provider_metadata()));
+
+        for hit in [false, true] {
+            let response = model.do_stream(request("Bhutan?")).await.unwrap();
+            assert!(response.request_body().is_some());
+            let parts: Vec<_> = response
+                .into_stream()
+                .map(|part| part.unwrap())
+                .collect()
+                .await;
+            assert!(
+                matches!(&parts[0], LanguageModelStreamPart::TextDelta(text) if text == "Thim")
+            );
+            assert!(matches!(
+                parts.last(),
+                Some(LanguageModelStreamPart::Finish { provider_metadata, .. })
+                    if cache_hit(provider_metadata.as_ref()) == hit
+            ));
+        }
+
+        let bypass = request("Nepal?").with_provider_metadata(HashMap::from([(
+            "cache".to_string(),
+            HashMap::from([("bypass".to_string(), json!(true))]),
+        )]));
+        let third = model.do_generate(bypass).await.unwrap();
+        assert_eq!(third.text(), Some("Pokhara"));
+    }
+
+    #[tokio::test]
+    async fn test_cache_ttl() {
+        let model = wrap_language_model(
+            MockLanguageModel::new().with_text_response("Kathmandu"),
+            vec![Box::new(
+                CacheMiddleware::new(Arc::new(InMemoryCache::new(10)))
+                    .with_ttl(Duration::from_millis(20)),
+            )],
+        );
+
+        model.do_generate(request("Nepal?")).await.unwrap();
+        assert!(model.do_generate(request("Nepal?")).await.is_ok());
+        tokio::time::sleep(Duration::from_millis(40)).await;
+        assert!(model.do_generate(request("Nepal?")).await.is_err());
+    }
+
+    struct BrokenCache;
+
+    #[async_trait]
+    impl CacheBackend for BrokenCache {
+        async fn get(&self, _key: &str) -> Result<Option<CacheEntry>, ModelError> {
+            Err(ModelError::Other("read-only".to_string()))
+        }
+
+        async fn set(&self, _key: &str, _entry: CacheEntry) -> Result<(), ModelError> {
+            Err(ModelError::Other("read-only".to_string()))
+        }
+
+        async fn remove(&self, _key: &str) -> Result<(), ModelError> {
+            Err(ModelError::Other("read-only".to_string()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_cache_failures_do_not_fail_calls() {
+        let model = wrap_language_model(
+            MockLanguageModel::new()
+                .with_text_response("Kathmandu")
+                .with_stream_response(mock_text_stream(&["Thim", "phu"])),
+            vec![Box::new(CacheMiddleware::new(Arc::new(BrokenCache)))],
+        );
+
+        let response = model.do_generate(request("Nepal?")).await.unwrap();
+        assert_eq!(response.text(), Some("Kathmandu"));
+        assert!(!cache_hit(response.provider_metadata()));
+
+        let parts: Vec<_> = model
+            .do_stream(request("Bhutan?"))
+            .await
+            .unwrap()
+            .into_stream()
+            .collect()
+            .await;
+        assert!(parts.iter().all(|part| part.is_ok()));
+        assert!(matches!(
+            parts.last(),
+            Some(Ok(LanguageModelStreamPart::Finish { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_cache_forwards_parts_after_stream_errors() {
+        let cache = Arc::new(InMemoryCache::new(10));
+        let model = wrap_language_model(
+            MockLanguageModel::new().with_stream_response(vec![
+                LanguageModelStreamPart::TextDelta("Thim".to_string()),
+                LanguageModelStreamPart::Error("overloaded".to_string()),
+                LanguageModelStreamPart::Finish {
+                    finish_reason: LanguageModelFinishReason::Error,
+                    usage: LanguageModelUsage::new(10, 2),
+                    provider_metadata: None,
+                    logprobs: None,
+                },
+            ]),
+            vec![Box::new(CacheMiddleware::new(cache.clone()))],
+        );
+
+        let parts: Vec<_> = model
+            .do_stream(request("Bhutan?"))
+            .await
+            .unwrap()
+            .into_stream()
+            .map(|part| part.unwrap())
+            .collect()
+            .await;
+        assert_eq!(parts.len(), 3);
+        assert!(matches!(&parts[1], LanguageModelStreamPart::Error(_)));
+        assert!(matches!(
+            &parts[2],
+            LanguageModelStreamPart::Finish {
+                finish_reason: LanguageModelFinishReason::Error,
+                ..
+            }
+        ));
+        assert!(cache.is_empty());
+    }
+}
@@ -0,0 +1,73 @@
+use async_trait::async_trait;
+
+use super::{LanguageModelMiddleware, LanguageModelMiddlewareCallType};
+use crate::{
+    errors::ModelError,
+    model::{call_settings::LanguageModelCallSettings, LanguageModelDoGenerateRequest},
+};
+
+/// Applies model level default settings. Settings of the call itself take
+/// precedence; settings that neither set fall back to the provider defaults.
+pub struct DefaultSettingsMiddleware {
+    settings: LanguageModelCallSettings,
+}
+
+impl DefaultSettingsMiddleware {
+    pub fn new(settings: LanguageModelCallSettings) -> Self {
+        DefaultSettingsMiddleware { settings }
+    }
+}
+
+#[async_trait]
+impl LanguageModelMiddleware for DefaultSettingsMiddleware {
+    async fn transform_params(
+        &self,
+        mut request: LanguageModelDoGenerateRequest,
+        _call_type: LanguageModelMiddlewareCallType,
+    ) -> Result<LanguageModelDoGenerateRequest, ModelError> {
+        let settings = request.call_settings_with_defaults(&self.settings);
+        *request.call_settings_mut() = Some(settings);
+        Ok(request)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DefaultSettingsMiddleware;
+    use crate::{
+        core::{
+            generate_text::{generate_text, GenerateTextOptions},
+            middleware::wrap_language_model,
+        },
+        model::call_settings::LanguageModelCallSettings,
+        testing::MockLanguageModel,
+    };
+
+    #[tokio::test]
+    async fn test_call_settings_override_model_defaults() {
+        let mut model = wrap_language_model(
+            MockLanguageModel::new().with_text_response("Kathmandu"),
+            vec![Box::new(DefaultSettingsMiddleware::new(
+                LanguageModelCallSettings {
+                    max_tokens: Some(256),
+                    temperature: Some(0.2),
+                    ..Default::default()
+                },
+            ))],
+        );
+
+        let result = generate_text(
+            &mut model,
+            GenerateTextOptions::default()
+                .prompt("What is the capital of Nepal?".into())
+                .temperature(0.8),
+        )
+        .await
+        .unwrap();
+
+        let settings = result.steps()[0].request().call_settings.as_ref().unwrap();
+        assert_eq!(settings.max_tokens, Some(256));
+        assert_eq!(settings.temperature, Some(0.8));
+        assert_eq!(settings.top_p, None);
+    }
+}
@@ -0,0 +1,348 @@
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+
+use super::LanguageModelMiddleware;
+use crate::{
+    errors::ModelError,
+    model::{
+        stream_part::LanguageModelStreamPart, LanguageModel, LanguageModelDoGenerateRequest,
+        LanguageModelDoGenerateResponse, LanguageModelDoGenerateResponseReasoning,
+        LanguageModelDoStreamResponse,
+    },
+};
+
+/// Moves reasoning that a model emits inline, e.g. `<think>...</think>`, out
+/// of the text and into the reasoning of the response.
+///
+/// Text that is left on both sides of a reasoning section is joined with the
+/// separator, as are multiple reasoning sections.
+#[derive(Debug, Clone)]
+pub struct ExtractReasoningMiddleware {
+    tag_name: String,
+    separator: String,
+    start_with_reasoning: bool,
+}
+
+impl Default for ExtractReasoningMiddleware {
+    fn default() -> Self {
+        ExtractReasoningMiddleware {
+            tag_name: "think".to_string(),
+            separator: "\n".to_string(),
+            start_with_reasoning: false,
+        }
+    }
+}
+
+impl ExtractReasoningMiddleware {
+    pub fn new() -> Self {
+        ExtractReasoningMiddleware::default()
+    }
+
+    /// Name of the tag that wraps the reasoning. Defaults to `think`.
+    pub fn with_tag_name(mut self, tag_name: &str) -> Self {
+        self.tag_name = tag_name.to_string();
+        self
+    }
+
+    /// Defaults to a newline.
+    pub fn with_separator(mut self, separator: &str) -> Self {
+        self.separator = separator.to_string();
+        self
+    }
+
+    /// Treat the output as reasoning until the first closing tag, for models
+    /// whose chat template already opened the tag in the prompt.
+    pub fn with_start_with_reasoning(mut self, start_with_reasoning: bool) -> Self {
+        self.start_with_reasoning = start_with_reasoning;
+        self
+    }
+
+    fn extractor(&self) -> ReasoningExtractor {
+        ReasoningExtractor {
+            open_tag: format!("<{}>", self.tag_name),
+            close_tag: format!("</{}>", self.tag_name),
+            separator: self.separator.clone(),
+            is_reasoning: self.start_with_reasoning,
+            buffer: String::new(),
+            after_switch: false,
+            emitted_text: false,
+            emitted_reasoning: false,
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageModelMiddleware for ExtractReasoningMiddleware {
+    async fn wrap_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let mut response = model.do_generate(request).await?;
+        let Some(text) = response.text_mut().take() else {
+            return Ok(response);
+        };
+
+        let mut extractor = self.extractor();
+        let mut segments = extractor.push(&text);
+        segments.extend(extractor.finish());
+
+        let mut text = String::new();
+        let mut reasoning = String::new();
+        for segment in segments {
+            match segment {
+                Segment::Text(delta) => text.push_str(&delta),
+                Segment::Reasoning(delta) => reasoning.push_str(&delta),
+            }
+        }
+        if !reasoning.is_empty() {
+            response
+                .reasoning_mut()
+                .push(LanguageModelDoGenerateResponseReasoning::Text {
+                    text: reasoning,
+                    signature: None,
+                });
+        }
+        *response.text_mut() = (!text.is_empty()).then_some(text);
+        Ok(response)
+    }
+
+    async fn wrap_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let response = model.do_stream(request).await?;
+        let extractor = self.extractor();
+        Ok(response.map_stream(|stream| {
+            stream::unfold(
+                (stream, Some(extractor)),
+                |(mut stream, extractor)| async move {
+                    let mut extractor = extractor?;
+                    let parts: Vec<_> = match stream.next().await {
+                        Some(Ok(LanguageModelStreamPart::TextDelta(delta))) => extractor
+                            .push(&delta)
+                            .into_iter()
+                            .map(|segment| Ok(segment.into_stream_part()))
+                            .collect(),
+                        // Text that might have been the start of a tag is
+                        // flushed before the stream finishes.
+                        Some(Ok(part @ LanguageModelStreamPart::Finish { .. })) => extractor
+                            .finish()
+                            .into_iter()
+                            .map(|segment| Ok(segment.into_stream_part()))
+                            .chain([Ok(part)])
+                            .collect(),
+                        Some(part) => vec![part],
+                        None => {
+                            let parts: Vec<_> = extractor
+                                .finish()
+                                .into_iter()
+                                .map(|segment| Ok(segment.into_stream_part()))
+                                .collect();
+                            return (!parts.is_empty()).then_some((parts, (stream, None)));
+                        }
+                    };
+                    Some((parts, (stream, Some(extractor))))
+                },
+            )
+            .flat_map(stream::iter)
+            .boxed()
+        }))
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum Segment {
+    Text(String),
+    Reasoning(String),
+}
+
+impl Segment {
+    fn into_stream_part(self) -> LanguageModelStreamPart {
+        match self {
+            Segment::Text(delta) => LanguageModelStreamPart::TextDelta(delta),
+            Segment::Reasoning(delta) => LanguageModelStreamPart::ReasoningDelta(delta),
+        }
+    }
+}
+
+/// Splits text into text and reasoning segments as it arrives. Text that
+/// could be the beginning of a tag is held back until the next chunk shows
+/// whether it is one.
+struct ReasoningExtractor {
+    open_tag: String,
+    close_tag: String,
+    separator: String,
+    is_reasoning: bool,
+    buffer: String,
+    /// Whether a tag was passed since the last emitted segment.
+    after_switch: bool,
+    emitted_text: bool,
+    emitted_reasoning: bool,
+}
+
+impl ReasoningExtractor {
+    fn push(&mut self, delta: &str) -> Vec<Segment> {
+        self.buffer.push_str(delta);
+        let mut segments = Vec::new();
+        loop {
+            let tag = if self.is_reasoning {
+                &self.close_tag
+            } else {
+                &self.open_tag
+            };
+            match self.buffer.find(tag.as_str()) {
+                Some(index) => {
+                    let tag_len = tag.len();
+                    let content: String = self.buffer.drain(..index).collect();
+                    self.buffer.drain(..tag_len);
+                    self.emit(content, &mut segments);
+                    self.is_reasoning = !self.is_reasoning;
+                    self.after_switch = true;
+                }
+                None => {
+                    let keep = partial_tag_len(&self.buffer, tag);
+                    let content: String = self.buffer.drain(..self.buffer.len() - keep).collect();
+                    self.emit(content, &mut segments);
+                    return segments;
+                }
+            }
+        }
+    }
+
+    fn finish(&mut self) -> Vec<Segment> {
+        let mut segments = Vec::new();
+        let content = std::mem::take(&mut self.buffer);
+        self.emit(content, &mut segments);
+        segments
+    }
+
+    fn emit(&mut self, content: String, segments: &mut Vec<Segment>) {
+        if content.is_empty() {
+            return;
+        }
+        let emitted = if self.is_reasoning {
+            self.emitted_reasoning
+        } else {
+            self.emitted_text
+        };
+        let content = if self.after_switch && emitted {
+            format!("{}{content}", self.separator)
+        } else {
+            content
+        };
+        self.after_switch = false;
+        if self.is_reasoning {
+            self.emitted_reasoning = true;
+            segments.push(Segment::Reasoning(content));
+        } else {
+            self.emitted_text = true;
+            segments.push(Segment::Text(content));
+        }
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `tag`.
+fn partial_tag_len(text: &str, tag: &str) -> usize {
+    tag.char_indices()
+        .rev()
+        .map(|(index, _)| index)
+        .find(|&len| len > 0 && text.ends_with(&tag[..len]))
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+
+    use super::ExtractReasoningMiddleware;
+    use crate::{
+        core::middleware::wrap_language_model,
+        model::{
+            stream_part::LanguageModelStreamPart, LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat, LanguageModelDoGenerateResponseReasoning,
+        },
+        testing::{mock_text_response, mock_text_stream, MockLanguageModel},
+    };
+
+    fn request() -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest::new(
+            LanguageModelDoGenerateRequestInputFormat::Prompt,
+            Vec::new(),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_extract_reasoning_generate() {
+        let model = wrap_language_model(
+            MockLanguageModel::new().with_generate_response(mock_text_response(
+                "<think>Nepal is in Asia.</think>Kathmandu<think>Double check.</think>.",
+            )),
+            vec![Box::new(ExtractReasoningMiddleware::new())],
+        );
+        let response = model.do_generate(request()).await.unwrap();
+        assert_eq!(response.text(), Some("Kathmandu\n."));
+        assert!(matches!(
+            response.reasoning(),
+            [LanguageModelDoGenerateResponseReasoning::Text { text, .. }]
+                if text == "Nepal is in Asia.\nDouble check."
+        ));
+
+        let model = wrap_language_model(
+            MockLanguageModel::new()
+                .with_generate_response(mock_text_response("Thinking.</answer>Kathmandu")),
+            vec![Box::new(
+                ExtractReasoningMiddleware::new()
+                    .with_tag_name("answer")
+                    .with_start_with_reasoning(true),
+            )],
+        );
+        let response = model.do_generate(request()).await.unwrap();
+        assert_eq!(response.text(), Some("Kathmandu"));
+        assert!(matches!(
+            response.reasoning(),
+            [LanguageModelDoGenerateResponseReasoning::Text { text, .. }] if text == "Thinking."
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_extract_reasoning_stream_with_split_tags() {
+        let model = wrap_language_model(
+            MockLanguageModel::new().with_stream_response(mock_text_stream(&[
+                "<th",
+                "ink>Nepal is ",
+                "in Asia.</",
+                "think",
+                ">Kath",
+                "mandu <",
+                "3",
+            ])),
+            vec![Box::new(ExtractReasoningMiddleware::new())],
+        );
+        let parts: Vec<_> = model
+            .do_stream(request())
+            .await
+            .unwrap()
+            .into_stream()
+            .map(|part| part.unwrap())
+            .collect()
+            .await;
+
+        let mut text = String::new();
+        let mut reasoning = String::new();
+        for part in &parts {
+            match part {
+                LanguageModelStreamPart::TextDelta(delta) => text.push_str(delta),
+                LanguageModelStreamPart::ReasoningDelta(delta) => reasoning.push_str(delta),
+                _ => {}
+            }
+        }
+        assert_eq!(reasoning, "Nepal is in Asia.");
+        assert_eq!(text, "Kathmandu <3");
+        assert!(matches!(
+            parts.last(),
+            Some(LanguageModelStreamPart::Finish { .. })
+        ));
+    }
+}
@@ -0,0 +1,67 @@
+pub mod cache;
+mod default_settings;
+mod extract_reasoning;
+mod pii_redaction;
+mod simulate_streaming;
+mod telemetry;
+mod wrap_language_model;
+
+pub use default_settings::DefaultSettingsMiddleware;
+pub use extract_reasoning::ExtractReasoningMiddleware;
+pub use pii_redaction::{PiiDetector, PiiRedaction, PiiRedactionMiddleware, PII_METADATA_KEY};
+pub use simulate_streaming::SimulateStreamingMiddleware;
+pub use telemetry::TelemetryMiddleware;
+pub use wrap_language_model::{wrap_language_model, WrappedLanguageModel};
+
+use async_trait::async_trait;
+
+use crate::{
+    errors::ModelError,
+    model::{
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateResponse,
+        LanguageModelDoStreamResponse,
+    },
+};
+
+/// The call a middleware hook runs for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageModelMiddlewareCallType {
+    Generate,
+    Stream,
+}
+
+/// Adds behaviour to a [`LanguageModel`] without changing the model itself,
+/// e.g. logging, caching or guardrails. Apply it with [`wrap_language_model`].
+///
+/// Every hook defaults to passing the call through unchanged.
+#[async_trait]
+pub trait LanguageModelMiddleware: Send + Sync {
+    /// Rewrites the request before it reaches `wrap_generate` or `wrap_stream`.
+    async fn transform_params(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        _call_type: LanguageModelMiddlewareCallType,
+    ) -> Result<LanguageModelDoGenerateRequest, ModelError> {
+        Ok(request)
+    }
+
+    /// Wraps `do_generate`. `model` is the next layer; a middleware may also
+    /// call its `do_stream`, or not call it at all.
+    async fn wrap_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        model.do_generate(request).await
+    }
+
+    /// Wraps `do_stream`. `model` is the next layer; a middleware may also
+    /// call its `do_generate`, or not call it at all.
+    async fn wrap_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        model.do_stream(request).await
+    }
+}
@@ -0,0 +1,655 @@
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JSONValue};
+use std::{
+    collections::{HashMap, HashSet},
+    sync::Arc,
+};
+
+use super::LanguageModelMiddleware;
+use crate::{
+    errors::ModelError,
+    model::{
+        message::{
+            LanguageModelAssistantMessage, LanguageModelMessage,
+            LanguageModelToolResultPartContent, LanguageModelUserMessage,
+        },
+        stream_part::LanguageModelStreamPart,
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateResponse,
+        LanguageModelDoGenerateResponseReasoning, LanguageModelDoStreamResponse,
+    },
+    provider::metadata::LanguageModelProviderMetadata,
+};
+
+/// Key of the redaction audit in `provider_metadata`.
+pub const PII_METADATA_KEY: &str = "pii";
+
+/// Finds one kind of personally identifiable information in text.
+#[derive(Debug, Clone)]
+pub struct PiiDetector {
+    name: String,
+    pattern: Regex,
+    validator: Option<fn(&str) -> bool>,
+}
+
+impl PiiDetector {
+    /// `name` is used in the placeholders, e.g. `EMAIL` gives `[EMAIL_1]`.
+    pub fn new(name: &str, pattern: &str) -> Result<Self, ModelError> {
+        let pattern = Regex::new(pattern).map_err(|e| {
+            ModelError::InvalidArgument(format!("Invalid pattern for PII detector {name}: {e}"))
+        })?;
+        Ok(PiiDetector {
+            name: name.to_uppercase(),
+            pattern,
+            validator: None,
+        })
+    }
+
+    /// Only redact matches for which `validator` returns `true`.
+    pub fn with_validator(mut self, validator: fn(&str) -> bool) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    pub fn email() -> Self {
+        PiiDetector::new("EMAIL", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}").unwrap()
+    }
+
+    /// Phone numbers with 7 to 15 digits, optionally with a country code and
+    /// separators.
+    pub fn phone_number() -> Self {
+        PiiDetector::new(
+            "PHONE",
+            r"(?:\+\d{1,3}[\s.-]?)?(?:\(\d{1,4}\)[\s.-]?)?\d{2,4}(?:[\s.-]?\d{2,4}){2,4}",
+        )
+        .unwrap()
+        .with_validator(|text| (7..=15).contains(&count_digits(text)))
+    }
+
+    /// Card numbers with 13 to 19 digits that pass the Luhn check.
+    pub fn card_number() -> Self {
+        PiiDetector::new("CARD", r"\b(?:\d[ -]?){12,18}\d\b")
+            .unwrap()
+            .with_validator(is_luhn_valid)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+}
+
+fn count_digits(text: &str) -> usize {
+    text.chars().filter(char::is_ascii_digit).count()
+}
+
+fn is_luhn_valid(text: &str) -> bool {
+    let digits: Vec<u32> = text.chars().filter_map(|c| c.to_digit(10)).collect();
+    let sum: u32 = digits
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(index, digit)| match (index % 2, digit * 2) {
+            (1, doubled) if doubled > 9 => doubled - 9,
+            (1, doubled) => doubled,
+            _ => *digit,
+        })
+        .sum();
+    (13..=19).contains(&digits.len()) && sum.is_multiple_of(10)
+}
+
+/// One redacted occurrence. The original value is never recorded.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PiiRedaction {
+    pub detector: String,
+    pub placeholder: String,
+    /// Index of the prompt message the value was found in.
+    pub message_index: usize,
+}
+
+impl PiiRedaction {
+    /// Reads the audit of a call from the `provider_metadata` of its response
+    /// or finish part.
+    pub fn from_provider_metadata(metadata: &LanguageModelProviderMetadata) -> Vec<PiiRedaction> {
+        metadata
+            .get(PII_METADATA_KEY)
+            .and_then(|pii| pii.get("redactions"))
+            .and_then(|redactions| serde_json::from_value(redactions.clone()).ok())
+            .unwrap_or_default()
+    }
+}
+
+/// Replaces PII in the outgoing prompt with placeholders such as `[EMAIL_1]`
+/// and restores the original values in the generated text, reasoning and
+/// tool call arguments, so the provider never sees them.
+///
+/// Equal values get the same placeholder within a call. The audit of a call
+/// is reported under `provider_metadata["pii"]["redactions"]`, see
+/// [`PiiRedaction::from_provider_metadata`].
+pub struct PiiRedactionMiddleware {
+    detectors: Vec<PiiDetector>,
+}
+
+impl Default for PiiRedactionMiddleware {
+    fn default() -> Self {
+        PiiRedactionMiddleware {
+            detectors: vec![
+                PiiDetector::email(),
+                PiiDetector::card_number(),
+                PiiDetector::phone_number(),
+            ],
+        }
+    }
+}
+
+impl PiiRedactionMiddleware {
+    /// Detects emails, card numbers and phone numbers.
+    pub fn new() -> Self {
+        PiiRedactionMiddleware::default()
+    }
+
+    /// Replaces the detectors. Detectors run in order, so more specific ones
+    /// should come first.
+    pub fn with_detectors(mut self, detectors: Vec<PiiDetector>) -> Self {
+        self.detectors = detectors;
+        self
+    }
+
+    pub fn with_detector(mut self, detector: PiiDetector) -> Self {
+        self.detectors.push(detector);
+        self
+    }
+
+    fn redact_request(
+        &self,
+        request: &mut LanguageModelDoGenerateRequest,
+    ) -> (Restorer, Vec<PiiRedaction>) {
+        let mut redactor = Redactor {
+            detectors: &self.detectors,
+            placeholders: HashMap::new(),
+            counters: HashMap::new(),
+            redactions: Vec::new(),
+        };
+        for (index, message) in request.prompt_mut().iter_mut().enumerate() {
+            redactor.redact_message(message, index);
+        }
+
+        let restorer = Restorer {
+            originals: Arc::new(
+                redactor
+                    .placeholders
+                    .into_iter()
+                    .map(|(original, placeholder)| (placeholder, original))
+                    .collect(),
+            ),
+        };
+        (restorer, redactor.redactions)
+    }
+}
+
+#[async_trait]
+impl LanguageModelMiddleware for PiiRedactionMiddleware {
+    async fn wrap_generate(
+        &self,
+        mut request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let (restorer, redactions) = self.redact_request(&mut request);
+        let mut response = model.do_generate(request).await?;
+
+        if let Some(text) = response.text_mut() {
+            *text = restorer.restore(text);
+        }
+        for reasoning in response.reasoning_mut() {
+            if let LanguageModelDoGenerateResponseReasoning::Text { text, .. } = reasoning {
+                *text = restorer.restore(text);
+            }
+        }
+        for tool_call in response.tool_calls_mut() {
+            tool_call.args = restorer.restore(&tool_call.args);
+        }
+        add_audit(response.provider_metadata_mut(), &redactions);
+        Ok(response)
+    }
+
+    async fn wrap_stream(
+        &self,
+        mut request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let (restorer, redactions) = self.redact_request(&mut request);
+        let response = model.do_stream(request).await?;
+
+        let state = StreamRestorer {
+            text: restorer.chunked(),
+            reasoning: restorer.chunked(),
+            tool_calls: HashMap::new(),
+            restorer,
+            redactions,
+        };
+        Ok(response.map_stream(|stream| {
+            stream::unfold((stream, Some(state)), |(mut stream, state)| async move {
+                let mut state = state?;
+                match stream.next().await {
+                    Some(Ok(part)) => {
+                        let parts = state.process(part).into_iter().map(Ok).collect();
+                        Some((parts, (stream, Some(state))))
+                    }
+                    Some(Err(e)) => Some((vec![Err(e)], (stream, Some(state)))),
+                    None => {
+                        let parts: Vec<_> = state.flush().into_iter().map(Ok).collect();
+                        (!parts.is_empty()).then_some((parts, (stream, None)))
+                    }
+                }
+            })
+            .flat_map(stream::iter)
+            .boxed()
+        }))
+    }
+}
+
+fn add_audit(metadata: &mut Option<LanguageModelProviderMetadata>, redactions: &[PiiRedaction]) {
+    metadata.get_or_insert_with(HashMap::new).insert(
+        PII_METADATA_KEY.to_string(),
+        HashMap::from([("redactions".to_string(), json!(redactions))]),
+    );
+}
+
+struct Redactor<'a> {
+    detectors: &'a [PiiDetector],
+    /// Original value to placeholder.
+    placeholders: HashMap<String, String>,
+    counters: HashMap<String, usize>,
+    redactions: Vec<PiiRedaction>,
+}
+
+impl Redactor<'_> {
+    fn redact_message(&mut self, message: &mut LanguageModelMessage, index: usize) {
+        match message {
+            LanguageModelMessage::System(text) => *text = self.redact(text, index),
+            LanguageModelMessage::User(parts) => {
+                for part in parts {
+                    if let LanguageModelUserMessage::Text(part) = part {
+                        part.text = self.redact(&part.text, index);
+                    }
+                }
+            }
+            LanguageModelMessage::Assistant(parts) => {
+                for part in parts {
+                    match part {
+                        LanguageModelAssistantMessage::Text(part) => {
+                            part.text = self.redact(&part.text, index);
+                        }
+                        LanguageModelAssistantMessage::Reasoning(part) => {
+                            part.text = self.redact(&part.text, index);
+                        }
+                        LanguageModelAssistantMessage::ToolCall(part) => {
+                            self.redact_value(&mut part.args, index);
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            LanguageModelMessage::Tool(parts) => {
+                for part in parts {
+                    self.redact_value(&mut part.result, index);
+                    for content in &mut part.content {
+                        if let LanguageModelToolResultPartContent::Text(text) = content {
+                            *text = self.redact(text, index);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    fn redact_value(&mut self, value: &mut JSONValue, index: usize) {
+        match value {
+            JSONValue::String(text) => *text = self.redact(text, index),
+            JSONValue::Array(values) => {
+                for value in values {
+                    self.redact_value(value, index);
+                }
+            }
+            JSONValue::Object(values) => {
+                for value in values.values_mut() {
+                    self.redact_value(value, index);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn redact(&mut self, text: &str, index: usize) -> String {
+        let mut text = text.to_string();
+        for detector in self.detectors {
+            let mut redacted = String::with_capacity(text.len());
+            let mut last = 0;
+            for found in detector.pattern.find_iter(&text) {
+                if detector
+                    .validator
+                    .is_some_and(|validator| !validator(found.as_str()))
+                {
+                    continue;
+                }
+                redacted.push_str(&text[last..found.start()]);
+                redacted.push_str(&self.placeholder(detector, found.as_str(), index));
+                last = found.end();
+            }
+            redacted.push_str(&text[last..]);
+            text = redacted;
+        }
+        text
+    }
+
+    fn placeholder(&mut self, detector: &PiiDetector, original: &str, index: usize) -> String {
+        let placeholder = match self.placeholders.get(original) {
+            Some(placeholder) => placeholder.clone(),
+            None => {
+                let counter = self.counters.entry(detector.name.clone()).or_default();
+                *counter += 1;
+                let placeholder = format!("[{}_{}]", detector.name, counter);
+                self.placeholders
+                    .insert(original.to_string(), placeholder.clone());
+                placeholder
+            }
+        };
+        self.redactions.push(PiiRedaction {
+            detector: detector.name.clone(),
+            placeholder: placeholder.clone(),
+            message_index: index,
+        });
+        placeholder
+    }
+}
+
+#[derive(Clone)]
+struct Restorer {
+    /// Placeholder to original value.
+    originals: Arc<HashMap<String, String>>,
+}
+
+impl Restorer {
+    fn restore(&self, text: &str) -> String {
+        self.originals
+            .iter()
+            .fold(text.to_string(), |text, (placeholder, original)| {
+                text.replace(placeholder, original)
+            })
+    }
+
+    fn chunked(&self) -> ChunkedRestorer {
+        ChunkedRestorer {
+            restorer: self.clone(),
+            buffer: String::new(),
+        }
+    }
+}
+
+/// Restores placeholders in text that arrives in chunks. A chunk that ends
+/// with the beginning of a placeholder is held back until the placeholder is
+/// complete.
+struct ChunkedRestorer {
+    restorer: Restorer,
+    buffer: String,
+}
+
+impl ChunkedRestorer {
+    fn push(&mut self, delta: &str) -> String {
+        self.buffer.push_str(delta);
+        let keep = match self.buffer.rfind('[') {
+            Some(start)
+                if self.restorer.originals.keys().any(|placeholder| {
+                    placeholder.len() > self.buffer.len() - start
+                        && placeholder.starts_with(&self.buffer[start..])
+                }) =>
+            {
+                self.buffer.len() - start
+            }
+            _ => 0,
+        };
+        let ready: String = self.buffer.drain(..self.buffer.len() - keep).collect();
+        self.restorer.restore(&ready)
+    }
+
+    fn flush(&mut self) -> String {
+        let rest = std::mem::take(&mut self.buffer);
+        self.restorer.restore(&rest)
+    }
+}
+
+struct StreamRestorer {
+    restorer: Restorer,
+    text: ChunkedRestorer,
+    reasoning: ChunkedRestorer,
+    tool_calls: HashMap<String, (String, ChunkedRestorer)>,
+    redactions: Vec<PiiRedaction>,
+}
+
+impl StreamRestorer {
+    fn process(&mut self, part: LanguageModelStreamPart) -> Vec<LanguageModelStreamPart> {
+        match part {
+            LanguageModelStreamPart::TextDelta(delta) => {
+                non_empty(self.text.push(&delta), LanguageModelStreamPart::TextDelta)
+            }
+            LanguageModelStreamPart::ReasoningDelta(delta) => non_empty(
+                self.reasoning.push(&delta),
+                LanguageModelStreamPart::ReasoningDelta,
+            ),
+            LanguageModelStreamPart::ToolCallDelta {
+                tool_call_id,
+                tool_name,
+                args_text_delta,
+            } => {
+                let (_, restorer) = self
+                    .tool_calls
+                    .entry(tool_call_id.clone())
+                    .or_insert_with(|| (tool_name.clone(), self.restorer.chunked()));
+                let args_text_delta = restorer.push(&args_text_delta);
+                non_empty(args_text_delta, |args_text_delta| {
+                    LanguageModelStreamPart::ToolCallDelta {
+                        tool_call_id,
+                        tool_name,
+                        args_text_delta,
+                    }
+                })
+            }
+            LanguageModelStreamPart::ToolCall(mut tool_call) => {
+                let mut parts = self.flush_tool_call(&tool_call.tool_call_id);
+                tool_call.args = self.restorer.restore(&tool_call.args);
+                parts.push(LanguageModelStreamPart::ToolCall(tool_call));
+                parts
+            }
+            LanguageModelStreamPart::Finish {
+                finish_reason,
+                usage,
+                mut provider_metadata,
+                logprobs,
+            } => {
+                let mut parts = self.flush_text();
+                add_audit(&mut provider_metadata, &self.redactions);
+                parts.push(LanguageModelStreamPart::Finish {
+                    finish_reason,
+                    usage,
+                    provider_metadata,
+                    logprobs,
+                });
+                parts
+            }
+            part => vec![part],
+        }
+    }
+
+    fn flush_tool_call(&mut self, tool_call_id: &str) -> Vec<LanguageModelStreamPart> {
+        let Some((tool_name, mut restorer)) = self.tool_calls.remove(tool_call_id) else {
+            return Vec::new();
+        };
+        non_empty(restorer.flush(), |args_text_delta| {
+            LanguageModelStreamPart::ToolCallDelta {
+                tool_call_id: tool_call_id.to_string(),
+                tool_name,
+                args_text_delta,
+            }
+        })
+    }
+
+    fn flush_text(&mut self) -> Vec<LanguageModelStreamPart> {
+        let mut parts = non_empty(
+            self.reasoning.flush(),
+            LanguageModelStreamPart::ReasoningDelta,
+        );
+        parts.extend(non_empty(
+            self.text.flush(),
+            LanguageModelStreamPart::TextDelta,
+        ));
+        parts
+    }
+
+    /// Flushes everything that is still held back when the stream ends
+    /// without a finish part.
+    fn flush(&mut self) -> Vec<LanguageModelStreamPart> {
+        let tool_call_ids: HashSet<String> = self.tool_calls.keys().cloned().collect();
+        let mut parts = self.flush_text();
+        for tool_call_id in tool_call_ids {
+            parts.extend(self.flush_tool_call(&tool_call_id));
+        }
+        parts
+    }
+}
+
+fn non_empty(
+    text: String,
+    part: impl FnOnce(String) -> LanguageModelStreamPart,
+) -> Vec<LanguageModelStreamPart> {
+    if text.is_empty() {
+        Vec::new()
+    } else {
+        vec![part(text)]
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use std::sync::Arc;
+
+    use super::{PiiDetector, PiiRedaction, PiiRedactionMiddleware};
+    use crate::{
+        core::middleware::wrap_language_model,
+        model::{
+            function_tool_call::LanguageModelFunctionToolCall,
+            message::{LanguageModelMessage, LanguageModelTextPart, LanguageModelUserMessage},
+            stream_part::LanguageModelStreamPart,
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat,
+        },
+        testing::{mock_text_response, mock_text_stream, MockLanguageModel},
+    };
+
+    fn request(text: &str) -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest::new(
+            LanguageModelDoGenerateRequestInputFormat::Messages,
+            vec![LanguageModelMessage::User(vec![
+                LanguageModelUserMessage::Text(LanguageModelTextPart {
+                    text: text.to_string(),
+                    provider_metadata: None,
+                }),
+            ])],
+        )
+    }
+
+    fn sent_text(model: &MockLanguageModel) -> String {
+        match &model.requests()[0].prompt()[0] {
+            LanguageModelMessage::User(parts) => match &parts[0] {
+                LanguageModelUserMessage::Text(part) => part.text.clone(),
+                _ => unreachable!(),
+            },
+            _ => unreachable!(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_redact_and_restore_generate() {
+        let mut response = mock_text_response("I emailed [EMAIL_1] and charged [CARD_1].");
+        *response.tool_calls_mut() = vec![LanguageModelFunctionToolCall {
+            tool_name: "call".to_string(),
+            tool_call_id: "call_1".to_string(),
+            args: r#"{"phone":"[PHONE_1]"}"#.to_string(),
+        }];
+        let mock = Arc::new(MockLanguageModel::new().with_generate_response(response));
+        let model =
+            wrap_language_model(mock.clone(), vec![Box::new(PiiRedactionMiddleware::new())]);
+
+        let response = model
+            .do_generate(request(
+                "Email jane.doe@example.com or call +1 415-555-0132, again jane.doe@example.com. \
+                 Card 4111 1111 1111 1111, order 12345.",
+            ))
+            .await
+            .unwrap();
+
+        assert_eq!(
+            sent_text(&mock),
+            "Email [EMAIL_1] or call [PHONE_1], again [EMAIL_1]. Card [CARD_1], order 12345."
+        );
+        assert_eq!(
+            response.text(),
+            Some("I emailed jane.doe@example.com and charged 4111 1111 1111 1111.")
+        );
+        assert_eq!(
+            response.tool_calls()[0].args,
+            r#"{"phone":"+1 415-555-0132"}"#
+        );
+
+        let redactions =
+            PiiRedaction::from_provider_metadata(response.provider_metadata().unwrap());
+        let placeholders: Vec<_> = redactions.iter().map(|r| r.placeholder.as_str()).collect();
+        assert_eq!(
+            placeholders,
+            ["[EMAIL_1]", "[EMAIL_1]", "[CARD_1]", "[PHONE_1]"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_restore_across_stream_chunks() {
+        let mock = Arc::new(
+            MockLanguageModel::new().with_stream_response(mock_text_stream(&[
+                "Ticket for [EM",
+                "PLOYEE_1",
+                "] is [",
+                "open]",
+            ])),
+        );
+        let model = wrap_language_model(
+            mock.clone(),
+            vec![Box::new(PiiRedactionMiddleware::new().with_detectors(
+                vec![PiiDetector::new("employee", r"E-\d{6}").unwrap()],
+            ))],
+        );
+
+        let parts: Vec<_> = model
+            .do_stream(request("Open a ticket for E-123456"))
+            .await
+            .unwrap()
+            .into_stream()
+            .map(|part| part.unwrap())
+            .collect()
+            .await;
+        assert_eq!(sent_text(&mock), "Open a ticket for [EMPLOYEE_1]");
+
+        let text: String = parts
+            .iter()
+            .filter_map(|part| match part {
+                LanguageModelStreamPart::TextDelta(delta) => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(text, "Ticket for E-123456 is [open]");
+        assert!(matches!(
+            parts.last(),
+            Some(LanguageModelStreamPart::Finish { provider_metadata: Some(metadata), .. })
+                if PiiRedaction::from_provider_metadata(metadata).len() == 1
+        ));
+    }
+}
@@ -0,0 +1,168 @@
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+
+use super::LanguageModelMiddleware;
+use crate::{
+    errors::ModelError,
+    model::{
+        stream_part::LanguageModelStreamPart, LanguageModel, LanguageModelDoGenerateRequest,
+        LanguageModelDoGenerateResponseReasoning, LanguageModelDoStreamResponse,
+    },
+};
+
+/// Implements `do_stream` with `do_generate`, for models that only support
+/// blocking generation.
+///
+/// The generated response is emitted as a single well-formed stream: response
+/// metadata, reasoning, text, sources, files and tool calls, then a finish
+/// part with the usage.
+#[derive(Debug, Clone, Default)]
+pub struct SimulateStreamingMiddleware;
+
+impl SimulateStreamingMiddleware {
+    pub fn new() -> Self {
+        SimulateStreamingMiddleware
+    }
+}
+
+#[async_trait]
+impl LanguageModelMiddleware for SimulateStreamingMiddleware {
+    async fn wrap_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let response = model.do_generate(request).await?;
+
+        let mut parts = Vec::new();
+        if let Some(metadata) = &response.response {
+            parts.push(LanguageModelStreamPart::ResponseMetadata {
+                id: Some(metadata.id.clone()),
+                timestamp: Some(metadata.timestamp),
+                model_id: Some(metadata.model_id.clone()),
+            });
+        }
+        for reasoning in response.reasoning {
+            match reasoning {
+                LanguageModelDoGenerateResponseReasoning::Text { text, signature } => {
+                    parts.push(LanguageModelStreamPart::ReasoningDelta(text));
+                    if let Some(signature) = signature {
+                        parts.push(LanguageModelStreamPart::ReasoningSignature(signature));
+                    }
+                }
+                LanguageModelDoGenerateResponseReasoning::Redacted(data) => {
+                    parts.push(LanguageModelStreamPart::RedactedReasoning(data));
+                }
+            }
+        }
+        if let Some(text) = response.text.filter(|text| !text.is_empty()) {
+            parts.push(LanguageModelStreamPart::TextDelta(text));
+        }
+        parts.extend(
+            response
+                .sources
+                .into_iter()
+                .map(LanguageModelStreamPart::Source),
+        );
+        parts.extend(
+            response
+                .files
+                .into_iter()
+                .map(LanguageModelStreamPart::File),
+        );
+        for tool_call in response.tool_calls {
+            parts.push(LanguageModelStreamPart::ToolCallDelta {
+                tool_call_id: tool_call.tool_call_id.clone(),
+                tool_name: tool_call.tool_name.clone(),
+                args_text_delta: tool_call.args.clone(),
+            });
+            parts.push(LanguageModelStreamPart::ToolCall(tool_call));
+        }
+        parts.push(LanguageModelStreamPart::Finish {
+            finish_reason: response.finish_reason,
+            usage: response.usage,
+            provider_metadata: response.provider_metadata,
+            logprobs: response.logprobs,
+        });
+
+        let mut stream_response =
+            LanguageModelDoStreamResponse::new(stream::iter(parts.into_iter().map(Ok)).boxed())
+                .with_warnings(response.warnings);
+        if let Some(request_body) = response.request_body {
+            stream_response = stream_response.with_request_body(request_body);
+        }
+        if let Some(metadata) = response.response {
+            stream_response = stream_response.with_response_headers(metadata.headers);
+        }
+        Ok(stream_response)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+
+    use super::SimulateStreamingMiddleware;
+    use crate::{
+        core::middleware::wrap_language_model,
+        model::{
+            finish_reason::LanguageModelFinishReason,
+            function_tool_call::LanguageModelFunctionToolCall,
+            stream_part::LanguageModelStreamPart, LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat, LanguageModelDoGenerateResponse,
+            LanguageModelDoGenerateResponseReasoning,
+        },
+        testing::MockLanguageModel,
+    };
+
+    #[tokio::test]
+    async fn test_simulated_stream() {
+        let mut response = LanguageModelDoGenerateResponse::default();
+        *response.text_mut() = Some("Let me check.".to_string());
+        *response.reasoning_mut() = vec![LanguageModelDoGenerateResponseReasoning::Text {
+            text: "Use the weather tool.".to_string(),
+            signature: None,
+        }];
+        *response.tool_calls_mut() = vec![LanguageModelFunctionToolCall {
+            tool_name: "weather".to_string(),
+            tool_call_id: "call_1".to_string(),
+            args: r#"{"city":"Kathmandu"}"#.to_string(),
+        }];
+        *response.finish_reason_mut() = LanguageModelFinishReason::ToolCalls;
+        response.usage_mut().total_tokens = 42;
+
+        let model = wrap_language_model(
+            MockLanguageModel::new().with_generate_response(response),
+            vec![Box::new(SimulateStreamingMiddleware::new())],
+        );
+        let parts: Vec<_> = model
+            .do_stream(LanguageModelDoGenerateRequest::new(
+                LanguageModelDoGenerateRequestInputFormat::Prompt,
+                Vec::new(),
+            ))
+            .await
+            .unwrap()
+            .into_stream()
+            .map(|part| part.unwrap())
+            .collect()
+            .await;
+
+        assert!(matches!(
+            &parts[..],
+            [
+                LanguageModelStreamPart::ReasoningDelta(reasoning),
+                LanguageModelStreamPart::TextDelta(text),
+                LanguageModelStreamPart::ToolCallDelta { .. },
+                LanguageModelStreamPart::ToolCall(tool_call),
+                LanguageModelStreamPart::Finish {
+                    finish_reason: LanguageModelFinishReason::ToolCalls,
+                    usage,
+                    ..
+                },
+            ] if reasoning == "Use the weather tool."
+                && text == "Let me check."
+                && tool_call.tool_call_id == "call_1"
+                && usage.total_tokens == 42
+        ));
+    }
+}
@@ -0,0 +1,59 @@
+use async_trait::async_trait;
+use tracing::Instrument;
+
+use super::LanguageModelMiddleware;
+use crate::{
+    core::telemetry::{
+        chat_span, instrument_stream, record_error, record_generate_response, TelemetrySettings,
+    },
+    errors::ModelError,
+    model::{
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateResponse,
+        LanguageModelDoStreamResponse,
+    },
+};
+
+/// Traces every call of the wrapped model with a GenAI chat span, see
+/// [`crate::core::telemetry`]. Stream spans stay open until the stream ends.
+#[derive(Debug, Clone, Default)]
+pub struct TelemetryMiddleware {
+    settings: TelemetrySettings,
+}
+
+impl TelemetryMiddleware {
+    pub fn new(settings: TelemetrySettings) -> Self {
+        TelemetryMiddleware { settings }
+    }
+}
+
+#[async_trait]
+impl LanguageModelMiddleware for TelemetryMiddleware {
+    async fn wrap_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let span = chat_span(model, &request, &self.settings);
+        let result = model.do_generate(request).instrument(span.clone()).await;
+        match &result {
+            Ok(response) => record_generate_response(&span, response, &self.settings),
+            Err(error) => record_error(&span, error),
+        }
+        result
+    }
+
+    async fn wrap_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+        model: &dyn LanguageModel,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let span = chat_span(model, &request, &self.settings);
+        match model.do_stream(request).instrument(span.clone()).await {
+            Ok(response) => Ok(instrument_stream(span, response, &self.settings)),
+            Err(error) => {
+                record_error(&span, &error);
+                Err(error)
+            }
+        }
+    }
+}
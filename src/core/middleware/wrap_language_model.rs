@@ -0,0 +1,197 @@
+use async_trait::async_trait;
+
+use super::{LanguageModelMiddleware, LanguageModelMiddlewareCallType};
+use crate::{
+    errors::ModelError,
+    model::{
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateResponse,
+        LanguageModelDoStreamResponse,
+    },
+};
+
+/// A [`LanguageModel`] with a middleware applied, see [`wrap_language_model`].
+pub struct WrappedLanguageModel {
+    model: Box<dyn LanguageModel>,
+    middleware: Box<dyn LanguageModelMiddleware>,
+}
+
+/// Applies `middlewares` to `model`.
+///
+/// The first middleware is the outermost one: its `transform_params` runs
+/// first and its `wrap_generate`/`wrap_stream` sees the final response.
+pub fn wrap_language_model(
+    model: impl LanguageModel + 'static,
+    middlewares: Vec<Box<dyn LanguageModelMiddleware>>,
+) -> Box<dyn LanguageModel> {
+    middlewares
+        .into_iter()
+        .rev()
+        .fold(Box::new(model), |model, middleware| {
+            Box::new(WrappedLanguageModel { model, middleware })
+        })
+}
+
+#[async_trait]
+impl LanguageModel for WrappedLanguageModel {
+    fn provider(&self) -> String {
+        self.model.provider()
+    }
+
+    fn model_id(&self) -> String {
+        self.model.model_id()
+    }
+
+    fn supports_urls(&self, url: String) -> bool {
+        self.model.supports_urls(url)
+    }
+
+    fn supports_file_urls(&self, url: String) -> bool {
+        self.model.supports_file_urls(url)
+    }
+
+    async fn do_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let request = self
+            .middleware
+            .transform_params(request, LanguageModelMiddlewareCallType::Generate)
+            .await?;
+        self.middleware
+            .wrap_generate(request, self.model.as_ref())
+            .await
+    }
+
+    async fn do_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let request = self
+            .middleware
+            .transform_params(request, LanguageModelMiddlewareCallType::Stream)
+            .await?;
+        self.middleware
+            .wrap_stream(request, self.model.as_ref())
+            .await
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use async_trait::async_trait;
+    use futures::StreamExt;
+
+    use super::wrap_language_model;
+    use crate::{
+        core::middleware::{LanguageModelMiddleware, LanguageModelMiddlewareCallType},
+        errors::ModelError,
+        model::{
+            message::LanguageModelMessage, stream_part::LanguageModelStreamPart, LanguageModel,
+            LanguageModelDoGenerateRequest, LanguageModelDoGenerateRequestInputFormat,
+            LanguageModelDoGenerateResponse, LanguageModelDoStreamResponse,
+        },
+        testing::{mock_text_stream, MockLanguageModel},
+    };
+
+    /// Appends its name to the system prompt and wraps the generated text in it.
+    struct Tag(&'static str);
+
+    #[async_trait]
+    impl LanguageModelMiddleware for Tag {
+        async fn transform_params(
+            &self,
+            mut request: LanguageModelDoGenerateRequest,
+            _call_type: LanguageModelMiddlewareCallType,
+        ) -> Result<LanguageModelDoGenerateRequest, ModelError> {
+            if let Some(LanguageModelMessage::System(system)) = request.prompt_mut().first_mut() {
+                system.push_str(self.0);
+            }
+            Ok(request)
+        }
+
+        async fn wrap_generate(
+            &self,
+            request: LanguageModelDoGenerateRequest,
+            model: &dyn LanguageModel,
+        ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+            let mut response = model.do_generate(request).await?;
+            let text = format!("{}({})", self.0, response.text().unwrap_or_default());
+            *response.text_mut() = Some(text);
+            Ok(response)
+        }
+
+        async fn wrap_stream(
+            &self,
+            request: LanguageModelDoGenerateRequest,
+            model: &dyn LanguageModel,
+        ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+            let tag = self.0;
+            let response = model.do_stream(request).await?;
+            Ok(response.map_stream(|stream| {
+                stream
+                    .map(move |part| match part {
+                        Ok(LanguageModelStreamPart::TextDelta(delta)) => {
+                            Ok(LanguageModelStreamPart::TextDelta(format!("{tag}{delta}")))
+                        }
+                        part => part,
+                    })
+                    .boxed()
+            }))
+        }
+    }
+
+    /// Echoes the system prompt it receives as the generated text.
+    struct EchoSystem;
+
+    #[async_trait]
+    impl LanguageModelMiddleware for EchoSystem {
+        async fn wrap_generate(
+            &self,
+            request: LanguageModelDoGenerateRequest,
+            model: &dyn LanguageModel,
+        ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+            let system = match request.prompt().first() {
+                Some(LanguageModelMessage::System(system)) => system.clone(),
+                _ => String::new(),
+            };
+            let mut response = model.do_generate(request).await?;
+            *response.text_mut() = Some(system);
+            Ok(response)
+        }
+    }
+
+    fn request() -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest::new(
+            LanguageModelDoGenerateRequestInputFormat::Messages,
+            vec![LanguageModelMessage::System("system:".to_string())],
+        )
+    }
+
+    #[tokio::test]
+    async fn test_middlewares_compose_in_order() {
+        let model = wrap_language_model(
+            MockLanguageModel::new()
+                .with_text_response("ignored")
+                .with_stream_response(mock_text_stream(&["Kath", "mandu"])),
+            vec![Box::new(Tag("a")), Box::new(Tag("b")), Box::new(EchoSystem)],
+        );
+
+        let response = model.do_generate(request()).await.unwrap();
+        assert_eq!(response.text(), Some("a(b(system:ab))"));
+
+        let text: String = model
+            .do_stream(request())
+            .await
+            .unwrap()
+            .into_stream()
+            .filter_map(|part| async move {
+                match part {
+                    Ok(LanguageModelStreamPart::TextDelta(delta)) => Some(delta),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await;
+        assert_eq!(text, "abKathabmandu");
+    }
+}
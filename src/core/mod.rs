@@ -1 +1,7 @@
+pub mod abort;
+pub mod chat;
+pub mod context_window;
 pub mod generate_text;
+pub mod middleware;
+pub mod retry;
+pub mod telemetry;
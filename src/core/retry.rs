@@ -0,0 +1,299 @@
+//! Retries of failed model calls with exponential backoff.
+
+use std::{future::Future, sync::Arc, time::Duration};
+
+use crate::errors::{ModelError, RetryError, RetryErrorReason};
+
+/// A failed attempt that is about to be retried.
+#[derive(Debug)]
+pub struct RetryAttempt<'a> {
+    /// The attempt that failed, starting at 1.
+    pub attempt: u32,
+    /// Total number of attempts allowed, including the first one.
+    pub max_attempts: u32,
+    /// How long to wait before the next attempt.
+    pub delay: Duration,
+    pub error: &'a ModelError,
+}
+
+pub type OnRetry = Arc<dyn Fn(&RetryAttempt<'_>) + Send + Sync>;
+
+/// How failed calls are retried. Only retryable errors, i.e. rate limits,
+/// server errors and connection failures, are retried; see
+/// [`ModelError::is_retryable`].
+///
+/// The delay before attempt `n + 1` is `base_delay * backoff_factor^(n - 1)`,
+/// capped at `max_delay` and randomized by `jitter`. A `retry-after-ms` or
+/// `Retry-After` response header takes precedence, up to `max_delay`.
+#[derive(Clone)]
+pub struct RetrySettings {
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub backoff_factor: f64,
+    /// Fraction of the delay, between 0 and 1, by which it is randomly
+    /// shortened or lengthened.
+    pub jitter: f64,
+    /// Called before every retry.
+    pub on_retry: Option<OnRetry>,
+}
+
+impl Default for RetrySettings {
+    fn default() -> Self {
+        RetrySettings {
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(60),
+            backoff_factor: 2.0,
+            jitter: 0.2,
+            on_retry: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for RetrySettings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RetrySettings")
+            .field("base_delay", &self.base_delay)
+            .field("max_delay", &self.max_delay)
+            .field("backoff_factor", &self.backoff_factor)
+            .field("jitter", &self.jitter)
+            .field("on_retry", &self.on_retry.is_some())
+            .finish()
+    }
+}
+
+impl RetrySettings {
+    pub fn new() -> Self {
+        RetrySettings::default()
+    }
+
+    pub fn with_base_delay(mut self, base_delay: Duration) -> Self {
+        self.base_delay = base_delay;
+        self
+    }
+
+    pub fn with_max_delay(mut self, max_delay: Duration) -> Self {
+        self.max_delay = max_delay;
+        self
+    }
+
+    pub fn with_backoff_factor(mut self, backoff_factor: f64) -> Self {
+        self.backoff_factor = backoff_factor;
+        self
+    }
+
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_on_retry(
+        mut self,
+        on_retry: impl Fn(&RetryAttempt<'_>) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_retry = Some(Arc::new(on_retry));
+        self
+    }
+
+    /// Runs `operation` until it succeeds, fails with an error that is not
+    /// retryable, or `max_retries` retries have failed.
+    ///
+    /// Returns the error unchanged if the first attempt is not retried,
+    /// otherwise a [`RetryError`] with the errors of all attempts.
+    pub async fn retry<T, F, Fut>(
+        &self,
+        max_retries: u32,
+        mut operation: F,
+    ) -> Result<T, ModelError>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, ModelError>>,
+    {
+        let max_attempts = max_retries.saturating_add(1);
+        let mut errors = Vec::new();
+
+        loop {
+            let error = match operation().await {
+                Ok(result) => return Ok(result),
+                Err(error) => error,
+            };
+            let attempt = errors.len() as u32 + 1;
+
+            let reason = if !error.is_retryable() {
+                RetryErrorReason::ErrorNotRetryable
+            } else if attempt >= max_attempts {
+                RetryErrorReason::MaxRetriesExceeded
+            } else {
+                let delay = self.delay(attempt, &error);
+                if let Some(on_retry) = &self.on_retry {
+                    on_retry(&RetryAttempt {
+                        attempt,
+                        max_attempts,
+                        delay,
+                        error: &error,
+                    });
+                }
+                errors.push(error);
+                tokio::time::sleep(delay).await;
+                continue;
+            };
+
+            if errors.is_empty() {
+                return Err(error);
+            }
+            errors.push(error);
+            return Err(ModelError::Retry(RetryError { reason, errors }));
+        }
+    }
+
+    /// Delay before the attempt after `attempt`.
+    fn delay(&self, attempt: u32, error: &ModelError) -> Duration {
+        if let Some(delay) = retry_after(error) {
+            return delay.min(self.max_delay);
+        }
+
+        let backoff = self.base_delay.as_secs_f64() * self.backoff_factor.powi(attempt as i32 - 1);
+        let jitter = 1.0 + self.jitter * (2.0 * rand::random::<f64>() - 1.0);
+        Duration::from_secs_f64((backoff.min(self.max_delay.as_secs_f64()) * jitter).max(0.0))
+    }
+}
+
+/// Delay requested by the provider in the `retry-after-ms` header (used by
+/// OpenAI), or the `Retry-After` header as seconds or an HTTP date.
+fn retry_after(error: &ModelError) -> Option<Duration> {
+    let ModelError::ApiCall(error) = error else {
+        return None;
+    };
+    if let Some(ms) = error
+        .header("retry-after-ms")
+        .and_then(|value| value.trim().parse::<f64>().ok())
+    {
+        return duration_from_secs(ms / 1000.0);
+    }
+
+    let value = error.header("retry-after")?.trim();
+    match value.parse::<f64>() {
+        Ok(seconds) => duration_from_secs(seconds),
+        Err(_) => httpdate::parse_http_date(value).ok().map(|date| {
+            date.duration_since(std::time::SystemTime::now())
+                .unwrap_or_default()
+        }),
+    }
+}
+
+/// Negative values give no delay and values too large for a [`Duration`] the
+/// longest one, which the caller caps; `NaN` is ignored.
+fn duration_from_secs(seconds: f64) -> Option<Duration> {
+    if seconds.is_nan() {
+        return None;
+    }
+    Some(Duration::try_from_secs_f64(seconds.max(0.0)).unwrap_or(Duration::MAX))
+}
+
+#[cfg(test)]
+mod test {
+    use std::{
+        sync::{Arc, Mutex},
+        time::Duration,
+    };
+
+    use super::RetrySettings;
+    use crate::errors::{ApiCallError, ModelError, RetryErrorReason};
+
+    fn api_error(status: u16, headers: &[(&str, &str)]) -> ModelError {
+        ApiCallError::from_response(
+            "https://api.example.com/v1/chat",
+            status,
+            headers
+                .iter()
+                .map(|(key, value)| (key.to_string(), value.to_string()))
+                .collect(),
+            String::new(),
+        )
+        .into()
+    }
+
+    #[tokio::test]
+    async fn test_retry_classification_and_delays() {
+        let delays = Arc::new(Mutex::new(Vec::new()));
+        let settings = RetrySettings::new()
+            .with_base_delay(Duration::from_millis(2))
+            .with_jitter(0.0)
+            .with_on_retry({
+                let delays = delays.clone();
+                move |attempt| delays.lock().unwrap().push(attempt.delay)
+            });
+
+        let mut errors = vec![
+            api_error(401, &[]),
+            api_error(503, &[]),
+            api_error(429, &[("Retry-After-Ms", "5")]),
+            api_error(500, &[]),
+        ];
+        let result = settings
+            .retry(5, || {
+                let error = errors.pop().unwrap();
+                async move { Err::<(), _>(error) }
+            })
+            .await;
+
+        let Err(ModelError::Retry(error)) = result else {
+            panic!("expected a retry error, got {result:?}");
+        };
+        assert_eq!(error.reason, RetryErrorReason::ErrorNotRetryable);
+        let statuses: Vec<_> = error
+            .errors
+            .iter()
+            .map(|error| match error {
+                ModelError::ApiCall(error) => error.status.unwrap(),
+                _ => unreachable!(),
+            })
+            .collect();
+        assert_eq!(statuses, [500, 429, 503, 401]);
+        assert_eq!(
+            *delays.lock().unwrap(),
+            [
+                Duration::from_millis(2),
+                Duration::from_millis(5),
+                Duration::from_millis(8)
+            ]
+        );
+    }
+
+    #[test]
+    fn test_retry_after_out_of_range() {
+        let settings = RetrySettings::new()
+            .with_base_delay(Duration::from_millis(2))
+            .with_max_delay(Duration::from_secs(60))
+            .with_jitter(0.0);
+        let delay = |header| settings.delay(1, &api_error(429, &[header]));
+
+        assert_eq!(delay(("Retry-After", "1e20")), Duration::from_secs(60));
+        assert_eq!(delay(("Retry-After-Ms", "inf")), Duration::from_secs(60));
+        assert_eq!(delay(("Retry-After", "-inf")), Duration::ZERO);
+        assert_eq!(delay(("Retry-After", "NaN")), Duration::from_millis(2));
+    }
+
+    #[tokio::test]
+    async fn test_retry_gives_up_after_max_retries() {
+        let settings = RetrySettings::new().with_base_delay(Duration::from_millis(1));
+        let mut attempts = 0;
+        let result = settings
+            .retry(2, || {
+                attempts += 1;
+                async { Err::<(), _>(api_error(429, &[("retry-after", "0")])) }
+            })
+            .await;
+
+        assert_eq!(attempts, 3);
+        assert!(matches!(
+            result,
+            Err(ModelError::Retry(error))
+                if error.reason == RetryErrorReason::MaxRetriesExceeded && error.errors.len() == 3
+        ));
+
+        let result = settings
+            .retry(2, || async { Err::<(), _>(api_error(400, &[])) })
+            .await;
+        assert!(matches!(result, Err(ModelError::ApiCall(error)) if error.status == Some(400)));
+    }
+}
@@ -0,0 +1,422 @@
+//! `tracing` spans for model calls, following the OpenTelemetry GenAI
+//! semantic conventions, https://opentelemetry.io/docs/specs/semconv/gen-ai/.
+//!
+//! Spans are always created; they only cost something when a subscriber is
+//! installed, e.g. `tracing-opentelemetry`. The `otel.name` and `otel.kind`
+//! fields set the OpenTelemetry span name and kind. Prompt and completion
+//! content is only recorded when enabled in [`TelemetrySettings`].
+
+use futures::StreamExt;
+use serde_json::{json, Value as JSONValue};
+use tracing::{field::Empty, Span};
+
+use crate::{
+    errors::ModelError,
+    model::{
+        finish_reason::LanguageModelFinishReason,
+        function_tool_call::LanguageModelFunctionToolCall, stream_part::LanguageModelStreamPart,
+        usage::LanguageModelUsage, LanguageModel, LanguageModelDoGenerateRequest,
+        LanguageModelDoGenerateResponse, LanguageModelDoStreamResponse,
+    },
+};
+
+/// Which content is recorded on the spans of a call.
+#[derive(Debug, Clone, Default)]
+pub struct TelemetrySettings {
+    /// Record the prompt messages as `gen_ai.input.messages`.
+    pub record_inputs: bool,
+    /// Record the generated messages as `gen_ai.output.messages`.
+    pub record_outputs: bool,
+    /// Identifies the calling function in the spans, as `cortex.function_id`.
+    pub function_id: Option<String>,
+}
+
+impl TelemetrySettings {
+    pub fn new() -> Self {
+        TelemetrySettings::default()
+    }
+
+    pub fn with_record_inputs(mut self, record_inputs: bool) -> Self {
+        self.record_inputs = record_inputs;
+        self
+    }
+
+    pub fn with_record_outputs(mut self, record_outputs: bool) -> Self {
+        self.record_outputs = record_outputs;
+        self
+    }
+
+    pub fn with_function_id(mut self, function_id: &str) -> Self {
+        self.function_id = Some(function_id.to_string());
+        self
+    }
+}
+
+/// Span of a whole `generate_text` call, the parent of its step spans.
+pub(crate) fn generate_text_span(model: &dyn LanguageModel, settings: &TelemetrySettings) -> Span {
+    tracing::info_span!(
+        "gen_ai.generate_text",
+        otel.name = format!("generate_text {}", model.model_id()),
+        gen_ai.operation.name = "generate_text",
+        gen_ai.provider.name = model.provider(),
+        gen_ai.request.model = model.model_id(),
+        gen_ai.response.finish_reasons = Empty,
+        gen_ai.usage.input_tokens = Empty,
+        gen_ai.usage.output_tokens = Empty,
+        cortex.function_id = settings.function_id.as_deref(),
+        error.type = Empty,
+    )
+}
+
+/// Span of a single `do_generate` or `do_stream` call.
+pub(crate) fn chat_span(
+    model: &dyn LanguageModel,
+    request: &LanguageModelDoGenerateRequest,
+    settings: &TelemetrySettings,
+) -> Span {
+    let span = tracing::info_span!(
+        "gen_ai.chat",
+        otel.name = format!("chat {}", model.model_id()),
+        otel.kind = "client",
+        gen_ai.operation.name = "chat",
+        gen_ai.provider.name = model.provider(),
+        gen_ai.request.model = model.model_id(),
+        gen_ai.request.max_tokens = Empty,
+        gen_ai.request.temperature = Empty,
+        gen_ai.request.top_p = Empty,
+        gen_ai.request.top_k = Empty,
+        gen_ai.request.presence_penalty = Empty,
+        gen_ai.request.frequency_penalty = Empty,
+        gen_ai.request.stop_sequences = Empty,
+        gen_ai.request.seed = Empty,
+        gen_ai.tool.names = Empty,
+        gen_ai.input.messages = Empty,
+        gen_ai.response.id = Empty,
+        gen_ai.response.model = Empty,
+        gen_ai.response.finish_reasons = Empty,
+        gen_ai.usage.input_tokens = Empty,
+        gen_ai.usage.output_tokens = Empty,
+        gen_ai.output.messages = Empty,
+        cortex.function_id = settings.function_id.as_deref(),
+        error.type = Empty,
+    );
+
+    if let Some(call_settings) = request.call_settings() {
+        if let Some(max_tokens) = call_settings.max_tokens {
+            span.record("gen_ai.request.max_tokens", max_tokens as u64);
+        }
+        if let Some(temperature) = call_settings.temperature {
+            span.record("gen_ai.request.temperature", temperature as f64);
+        }
+        if let Some(top_p) = call_settings.top_p {
+            span.record("gen_ai.request.top_p", top_p as f64);
+        }
+        if let Some(top_k) = call_settings.top_k {
+            span.record("gen_ai.request.top_k", top_k as u64);
+        }
+        if let Some(presence_penalty) = call_settings.presence_penalty {
+            span.record("gen_ai.request.presence_penalty", presence_penalty as f64);
+        }
+        if let Some(frequency_penalty) = call_settings.frequency_penalty {
+            span.record("gen_ai.request.frequency_penalty", frequency_penalty as f64);
+        }
+        if let Some(stop_sequences) = &call_settings.stop_sequences {
+            span.record(
+                "gen_ai.request.stop_sequences",
+                json!(stop_sequences).to_string(),
+            );
+        }
+        if let Some(seed) = call_settings.seed {
+            span.record("gen_ai.request.seed", seed);
+        }
+    }
+    if let Some(tools) = request.tools().filter(|tools| !tools.is_empty()) {
+        let names: Vec<_> = tools.iter().map(|tool| tool.name.as_str()).collect();
+        span.record("gen_ai.tool.names", json!(names).to_string());
+    }
+    if settings.record_inputs {
+        span.record(
+            "gen_ai.input.messages",
+            serde_json::to_string(request.prompt()).unwrap_or_default(),
+        );
+    }
+    span
+}
+
+/// Span of a tool execution. Tool names are not considered content, so they
+/// are always recorded.
+pub fn tool_execution_span(tool_name: &str, tool_call_id: &str) -> Span {
+    tracing::info_span!(
+        "gen_ai.execute_tool",
+        otel.name = format!("execute_tool {tool_name}"),
+        gen_ai.operation.name = "execute_tool",
+        gen_ai.tool.name = tool_name,
+        gen_ai.tool.call.id = tool_call_id,
+        error.type = Empty,
+    )
+}
+
+/// Records the outcome of a `do_generate` call on its chat span.
+pub(crate) fn record_generate_response(
+    span: &Span,
+    response: &LanguageModelDoGenerateResponse,
+    settings: &TelemetrySettings,
+) {
+    if let Some(metadata) = response.response() {
+        span.record("gen_ai.response.id", metadata.id.as_str());
+        span.record("gen_ai.response.model", metadata.model_id.as_str());
+    }
+    record_finish(span, response.finish_reason(), response.usage());
+    if settings.record_outputs {
+        record_output_messages(
+            span,
+            response.text().unwrap_or_default(),
+            response.tool_calls(),
+            response.finish_reason(),
+        );
+    }
+}
+
+/// Records finish reason and token usage.
+pub(crate) fn record_finish(
+    span: &Span,
+    finish_reason: LanguageModelFinishReason,
+    usage: &LanguageModelUsage,
+) {
+    span.record(
+        "gen_ai.response.finish_reasons",
+        json!([finish_reason_name(finish_reason)]).to_string(),
+    );
+    span.record("gen_ai.usage.input_tokens", usage.prompt_tokens);
+    span.record("gen_ai.usage.output_tokens", usage.completion_tokens);
+}
+
+pub(crate) fn record_error(span: &Span, error: &ModelError) {
+    span.record("error.type", error_type(error));
+    tracing::error!(parent: span, error = %error, "model call failed");
+}
+
+/// Wraps the stream so that its chunks are processed inside `span`, and the
+/// span records the response once the stream finishes.
+pub(crate) fn instrument_stream(
+    span: Span,
+    response: LanguageModelDoStreamResponse,
+    settings: &TelemetrySettings,
+) -> LanguageModelDoStreamResponse {
+    let record_outputs = settings.record_outputs;
+    let mut text = String::new();
+    let mut tool_calls = Vec::new();
+    response.map_stream(move |stream| {
+        stream
+            .inspect(move |part| {
+                let _entered = span.enter();
+                match part {
+                    Ok(LanguageModelStreamPart::ResponseMetadata { id, model_id, .. }) => {
+                        if let Some(id) = id {
+                            span.record("gen_ai.response.id", id.as_str());
+                        }
+                        if let Some(model_id) = model_id {
+                            span.record("gen_ai.response.model", model_id.as_str());
+                        }
+                    }
+                    Ok(LanguageModelStreamPart::TextDelta(delta)) if record_outputs => {
+                        text.push_str(delta);
+                    }
+                    Ok(LanguageModelStreamPart::ToolCall(tool_call)) if record_outputs => {
+                        tool_calls.push(tool_call.clone());
+                    }
+                    Ok(LanguageModelStreamPart::Finish {
+                        finish_reason,
+                        usage,
+                        ..
+                    }) => {
+                        record_finish(&span, *finish_reason, usage);
+                        if record_outputs {
+                            record_output_messages(&span, &text, &tool_calls, *finish_reason);
+                        }
+                    }
+                    Err(error) => record_error(&span, error),
+                    _ => {}
+                }
+            })
+            .boxed()
+    })
+}
+
+/// Records the generated message in the GenAI output messages format.
+fn record_output_messages(
+    span: &Span,
+    text: &str,
+    tool_calls: &[LanguageModelFunctionToolCall],
+    finish_reason: LanguageModelFinishReason,
+) {
+    let mut parts = Vec::new();
+    if !text.is_empty() {
+        parts.push(json!({ "type": "text", "content": text }));
+    }
+    for tool_call in tool_calls {
+        parts.push(json!({
+            "type": "tool_call",
+            "id": tool_call.tool_call_id,
+            "name": tool_call.tool_name,
+            "arguments": serde_json::from_str::<JSONValue>(&tool_call.args)
+                .unwrap_or_else(|_| json!(tool_call.args)),
+        }));
+    }
+    let messages = json!([{
+        "role": "assistant",
+        "parts": parts,
+        "finish_reason": finish_reason_name(finish_reason),
+    }]);
+    span.record("gen_ai.output.messages", messages.to_string());
+}
+
+fn finish_reason_name(finish_reason: LanguageModelFinishReason) -> &'static str {
+    match finish_reason {
+        LanguageModelFinishReason::Stop => "stop",
+        LanguageModelFinishReason::Length => "length",
+        LanguageModelFinishReason::ContentFilter => "content_filter",
+        LanguageModelFinishReason::ToolCalls => "tool_calls",
+        LanguageModelFinishReason::Error => "error",
+        LanguageModelFinishReason::Other => "other",
+        LanguageModelFinishReason::Unknown => "unknown",
+    }
+}
+
+/// The `error.type` attribute: the HTTP status code of failed API calls,
+/// otherwise the kind of error.
+fn error_type(error: &ModelError) -> String {
+    if let Some(status) = error.api_call_error().and_then(|error| error.status) {
+        return status.to_string();
+    }
+    match error {
+        ModelError::NotFound(_) => "not_found",
+        ModelError::NotSupported(_) => "not_supported",
+        ModelError::InvalidArgument(_) => "invalid_argument",
+        ModelError::InternalError(_) => "internal_error",
+        ModelError::InvalidPrompt(_) => "invalid_prompt",
+        ModelError::ApiCall(_) => "api_call",
+        ModelError::ContextLengthExceeded(_) => "context_length_exceeded",
+        ModelError::ContentFiltered(_) => "content_filtered",
+        ModelError::NoContent(_) => "no_content",
+        ModelError::InvalidResponseJson { .. } => "invalid_response_json",
+        ModelError::Download { .. } => "download",
+        ModelError::Retry(_) => "retry",
+        ModelError::Aborted(_) => "aborted",
+        ModelError::Store(_) => "store",
+        ModelError::Other(_) => "other",
+    }
+    .to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use opentelemetry::{trace::TracerProvider, Value};
+    use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider, SpanData};
+    use tracing_subscriber::layer::SubscriberExt;
+
+    use super::TelemetrySettings;
+    use crate::{
+        core::{
+            generate_text::{generate_text, GenerateTextOptions},
+            middleware::{wrap_language_model, TelemetryMiddleware},
+        },
+        model::{
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat,
+        },
+        testing::{mock_text_stream, MockLanguageModel},
+    };
+
+    fn attribute(span: &SpanData, key: &str) -> Option<Value> {
+        span.attributes
+            .iter()
+            .find(|attribute| attribute.key.as_str() == key)
+            .map(|attribute| attribute.value.clone())
+    }
+
+    fn span<'a>(spans: &'a [SpanData], name: &str) -> &'a SpanData {
+        spans.iter().find(|span| span.name == name).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_generate_and_stream_spans() {
+        let exporter = InMemorySpanExporter::default();
+        let provider = SdkTracerProvider::builder()
+            .with_simple_exporter(exporter.clone())
+            .build();
+        let subscriber = tracing_subscriber::registry()
+            .with(tracing_opentelemetry::layer().with_tracer(provider.tracer("cortex-test")));
+        let _guard = tracing::subscriber::set_default(subscriber);
+
+        let mut model = MockLanguageModel::new().with_text_response("Kathmandu");
+        generate_text(
+            &mut model,
+            GenerateTextOptions::new()
+                .prompt("What is the capital of Nepal?".into())
+                .temperature(0.5)
+                .telemetry(TelemetrySettings::new().with_record_outputs(true)),
+        )
+        .await
+        .unwrap();
+
+        let model = wrap_language_model(
+            MockLanguageModel::new()
+                .with_model_id("mock-stream")
+                .with_stream_response(mock_text_stream(&["Kath", "mandu"])),
+            vec![Box::new(TelemetryMiddleware::new(
+                TelemetrySettings::new().with_record_inputs(true),
+            ))],
+        );
+        let parts: Vec<_> = model
+            .do_stream(LanguageModelDoGenerateRequest::new(
+                LanguageModelDoGenerateRequestInputFormat::Prompt,
+                Vec::new(),
+            ))
+            .await
+            .unwrap()
+            .into_stream()
+            .collect()
+            .await;
+        assert_eq!(parts.len(), 3);
+        drop(model);
+
+        provider.force_flush().unwrap();
+        let spans = exporter.get_finished_spans().unwrap();
+
+        let outer = span(&spans, "generate_text mock-model");
+        let chat = span(&spans, "chat mock-model");
+        assert_eq!(chat.parent_span_id, outer.span_context.span_id());
+        assert_eq!(
+            attribute(chat, "gen_ai.operation.name"),
+            Some("chat".into())
+        );
+        assert_eq!(
+            attribute(chat, "gen_ai.request.model"),
+            Some("mock-model".into())
+        );
+        assert_eq!(
+            attribute(chat, "gen_ai.request.temperature"),
+            Some(0.5.into())
+        );
+        assert_eq!(
+            attribute(chat, "gen_ai.response.finish_reasons"),
+            Some(r#"["stop"]"#.into())
+        );
+        assert!(attribute(chat, "gen_ai.usage.output_tokens").is_some());
+        assert!(attribute(chat, "gen_ai.input.messages").is_none());
+        assert!(matches!(
+            attribute(chat, "gen_ai.output.messages"),
+            Some(Value::String(messages)) if messages.as_str().contains("Kathmandu")
+        ));
+
+        let stream = span(&spans, "chat mock-stream");
+        assert_eq!(
+            attribute(stream, "gen_ai.response.finish_reasons"),
+            Some(r#"["stop"]"#.into())
+        );
+        assert!(attribute(stream, "gen_ai.input.messages").is_some());
+        assert!(attribute(stream, "gen_ai.output.messages").is_none());
+    }
+}
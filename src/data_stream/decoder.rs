@@ -0,0 +1,23 @@
+use futures::{stream::BoxStream, StreamExt};
+
+use super::DataStreamPart;
+use crate::{errors::ModelError, utils};
+
+/// Decodes the body of a data stream response fetched from `url`, e.g. the
+/// body of an [`HttpResponse`](crate::transport::HttpResponse).
+pub fn decode_data_stream(
+    url: &str,
+    body: BoxStream<'static, Result<Vec<u8>, ModelError>>,
+) -> BoxStream<'static, Result<DataStreamPart, ModelError>> {
+    let url = url.to_string();
+    utils::parse_sse_stream(url.clone(), body)
+        .map(move |event| {
+            let json = event?;
+            DataStreamPart::from_json(json.clone()).map_err(|e| ModelError::InvalidResponseJson {
+                url: url.clone(),
+                message: e.to_string(),
+                text: json.to_string(),
+            })
+        })
+        .boxed()
+}
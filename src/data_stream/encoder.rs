@@ -0,0 +1,294 @@
+use base64::{engine::general_purpose, Engine as _};
+use futures::{future, stream, stream::BoxStream, StreamExt};
+use std::collections::HashSet;
+
+use super::{DataStreamPart, DATA_STREAM_DONE};
+use crate::{
+    errors::ModelError,
+    model::{
+        source::{LanguageModelSource, LanguageModelSourceType},
+        stream_part::LanguageModelStreamPart,
+        LanguageModelDoGenerateResponseFilesContent, LanguageModelStream,
+    },
+    prompt::{convert_to_language_model_prompt::parse_json_or_string, ToolResultPart},
+};
+
+/// An item of the stream encoded by [`DataStreamEncoder::encode_events`].
+#[derive(Debug, Clone)]
+pub enum DataStreamEvent {
+    /// A part of a model stream. The streams of several steps can follow
+    /// each other, each ending with its `Finish` part.
+    Model(LanguageModelStreamPart),
+    /// The result of a tool call executed by the application, sent as
+    /// `tool-output-error` when it is an error.
+    ToolResult(ToolResultPart),
+    /// A part sent as is, e.g. custom [`DataStreamPart::Data`].
+    Part(DataStreamPart),
+}
+
+impl From<LanguageModelStreamPart> for DataStreamEvent {
+    fn from(part: LanguageModelStreamPart) -> Self {
+        DataStreamEvent::Model(part)
+    }
+}
+
+/// Translates the parts of a model stream into data stream parts.
+///
+/// Consecutive text and reasoning deltas are grouped into blocks. Reasoning
+/// signatures, redacted reasoning and response metadata are not part of the
+/// protocol and are dropped.
+#[derive(Debug, Default)]
+pub struct DataStreamEncoder {
+    message_id: Option<String>,
+    text_id: Option<String>,
+    reasoning_id: Option<String>,
+    next_id: usize,
+    started_tool_calls: HashSet<String>,
+    step_finished: bool,
+}
+
+impl DataStreamEncoder {
+    pub fn new() -> Self {
+        DataStreamEncoder::default()
+    }
+
+    /// Id of the assistant message, sent with the `start` part.
+    pub fn with_message_id(mut self, message_id: &str) -> Self {
+        self.message_id = Some(message_id.to_string());
+        self
+    }
+
+    /// The parts that open the message and its first step.
+    pub fn start(&self) -> Vec<DataStreamPart> {
+        vec![
+            DataStreamPart::Start {
+                message_id: self.message_id.clone(),
+            },
+            DataStreamPart::StartStep,
+        ]
+    }
+
+    /// Encodes a part of a model stream. A `Finish` part only ends the step:
+    /// tool results can still follow, and the message is closed by
+    /// [`DataStreamEncoder::finish`].
+    pub fn encode(&mut self, part: LanguageModelStreamPart) -> Vec<DataStreamPart> {
+        let mut parts = Vec::new();
+        if self.step_finished && !matches!(part, LanguageModelStreamPart::Finish { .. }) {
+            self.step_finished = false;
+            parts.push(DataStreamPart::FinishStep);
+            parts.push(DataStreamPart::StartStep);
+        }
+        match part {
+            LanguageModelStreamPart::TextDelta(delta) => {
+                parts.extend(self.end_reasoning());
+                let id = match &self.text_id {
+                    Some(id) => id.clone(),
+                    None => {
+                        let id = self.block_id("text");
+                        parts.push(DataStreamPart::TextStart { id: id.clone() });
+                        self.text_id = Some(id.clone());
+                        id
+                    }
+                };
+                parts.push(DataStreamPart::TextDelta { id, delta });
+            }
+            LanguageModelStreamPart::ReasoningDelta(delta) => {
+                parts.extend(self.end_text());
+                let id = match &self.reasoning_id {
+                    Some(id) => id.clone(),
+                    None => {
+                        let id = self.block_id("reasoning");
+                        parts.push(DataStreamPart::ReasoningStart { id: id.clone() });
+                        self.reasoning_id = Some(id.clone());
+                        id
+                    }
+                };
+                parts.push(DataStreamPart::ReasoningDelta { id, delta });
+            }
+            LanguageModelStreamPart::ToolCallDelta {
+                tool_call_id,
+                tool_name,
+                args_text_delta,
+            } => {
+                parts.extend(self.end_blocks());
+                if self.started_tool_calls.insert(tool_call_id.clone()) {
+                    parts.push(DataStreamPart::ToolInputStart {
+                        tool_call_id: tool_call_id.clone(),
+                        tool_name,
+                    });
+                }
+                parts.push(DataStreamPart::ToolInputDelta {
+                    tool_call_id,
+                    input_text_delta: args_text_delta,
+                });
+            }
+            LanguageModelStreamPart::ToolCall(call) => {
+                parts.extend(self.end_blocks());
+                parts.push(DataStreamPart::ToolInputAvailable {
+                    input: parse_json_or_string(&call.args),
+                    tool_call_id: call.tool_call_id,
+                    tool_name: call.tool_name,
+                });
+            }
+            LanguageModelStreamPart::Source(source) => parts.push(source_part(source)),
+            LanguageModelStreamPart::File(file) => {
+                let data = match file.file_content {
+                    LanguageModelDoGenerateResponseFilesContent::Base64(data) => data,
+                    LanguageModelDoGenerateResponseFilesContent::Buffer(data) => {
+                        general_purpose::STANDARD.encode(data)
+                    }
+                };
+                parts.push(DataStreamPart::File {
+                    url: format!("data:{};base64,{data}", file.mime_type),
+                    media_type: file.mime_type,
+                });
+            }
+            LanguageModelStreamPart::Finish { .. } => {
+                parts.extend(self.end_blocks());
+                self.step_finished = true;
+            }
+            LanguageModelStreamPart::Error(error_text) => {
+                parts.extend(self.end_blocks());
+                parts.push(DataStreamPart::Error { error_text });
+            }
+            LanguageModelStreamPart::ReasoningSignature(_)
+            | LanguageModelStreamPart::RedactedReasoning(_)
+            | LanguageModelStreamPart::ResponseMetadata { .. } => {}
+        }
+        parts
+    }
+
+    /// Encodes the result of an executed tool call.
+    pub fn encode_tool_result(&mut self, result: ToolResultPart) -> Vec<DataStreamPart> {
+        let mut parts = self.end_blocks();
+        parts.push(if result.is_error.unwrap_or(false) {
+            DataStreamPart::ToolOutputError {
+                tool_call_id: result.tool_call_id,
+                error_text: result.result,
+            }
+        } else {
+            DataStreamPart::ToolOutputAvailable {
+                output: parse_json_or_string(&result.result),
+                tool_call_id: result.tool_call_id,
+            }
+        });
+        parts
+    }
+
+    pub fn encode_event(&mut self, event: DataStreamEvent) -> Vec<DataStreamPart> {
+        match event {
+            DataStreamEvent::Model(part) => self.encode(part),
+            DataStreamEvent::ToolResult(result) => self.encode_tool_result(result),
+            DataStreamEvent::Part(part) => vec![part],
+        }
+    }
+
+    /// Closes the open blocks, the step and the message.
+    pub fn finish(&mut self) -> Vec<DataStreamPart> {
+        let mut parts = self.end_blocks();
+        self.step_finished = false;
+        parts.push(DataStreamPart::FinishStep);
+        parts.push(DataStreamPart::Finish {
+            message_metadata: None,
+        });
+        parts
+    }
+
+    /// Encodes a model stream as the body of a data stream response, one
+    /// server-sent event per item. Errors of the stream are sent as `error`
+    /// parts.
+    pub fn encode_stream(self, stream: LanguageModelStream) -> BoxStream<'static, String> {
+        self.encode_events(stream.map(|part| part.map(DataStreamEvent::Model)).boxed())
+    }
+
+    /// Like [`DataStreamEncoder::encode_stream`], with the custom parts of
+    /// `data` sent as they arrive, e.g. from a channel. The response ends
+    /// with the model stream; data that arrives later is dropped.
+    pub fn encode_stream_with_data(
+        self,
+        stream: LanguageModelStream,
+        data: BoxStream<'static, DataStreamPart>,
+    ) -> BoxStream<'static, String> {
+        let model = stream
+            .map(|part| Some(part.map(DataStreamEvent::Model)))
+            .chain(stream::once(future::ready(None)));
+        let data = data.map(|part| Some(Ok(DataStreamEvent::Part(part))));
+        let events = stream::select(model, data)
+            .take_while(|event| future::ready(event.is_some()))
+            .filter_map(future::ready);
+        self.encode_events(events.boxed())
+    }
+
+    /// Encodes model stream parts, tool results and custom parts as the body
+    /// of a data stream response. The message is finished when the stream
+    /// ends, unless it failed.
+    pub fn encode_events(
+        self,
+        events: BoxStream<'static, Result<DataStreamEvent, ModelError>>,
+    ) -> BoxStream<'static, String> {
+        let start = self.start();
+        let parts = events
+            .map(Some)
+            .chain(stream::once(future::ready(None)))
+            .scan((self, false), |(encoder, failed), event| {
+                let parts = match event {
+                    Some(Ok(event)) => encoder.encode_event(event),
+                    Some(Err(error)) => {
+                        *failed = true;
+                        let mut parts = encoder.end_blocks();
+                        parts.push(DataStreamPart::Error {
+                            error_text: error.to_string(),
+                        });
+                        parts
+                    }
+                    None if *failed => Vec::new(),
+                    None => encoder.finish(),
+                };
+                future::ready(Some(stream::iter(parts)))
+            });
+        stream::iter(start)
+            .chain(parts.flatten())
+            .map(|part| part.to_sse())
+            .chain(stream::once(future::ready(DATA_STREAM_DONE.to_string())))
+            .boxed()
+    }
+
+    fn block_id(&mut self, kind: &str) -> String {
+        let id = format!("{kind}-{}", self.next_id);
+        self.next_id += 1;
+        id
+    }
+
+    fn end_text(&mut self) -> Option<DataStreamPart> {
+        self.text_id.take().map(|id| DataStreamPart::TextEnd { id })
+    }
+
+    fn end_reasoning(&mut self) -> Option<DataStreamPart> {
+        self.reasoning_id
+            .take()
+            .map(|id| DataStreamPart::ReasoningEnd { id })
+    }
+
+    fn end_blocks(&mut self) -> Vec<DataStreamPart> {
+        self.end_reasoning()
+            .into_iter()
+            .chain(self.end_text())
+            .collect()
+    }
+}
+
+/// Documents have no media type in the model stream and are sent as text.
+fn source_part(source: LanguageModelSource) -> DataStreamPart {
+    match (source.source_type, source.url) {
+        (LanguageModelSourceType::Url, Some(url)) => DataStreamPart::SourceUrl {
+            source_id: source.id,
+            url,
+            title: source.title,
+        },
+        _ => DataStreamPart::SourceDocument {
+            title: source.title.unwrap_or_else(|| source.id.clone()),
+            source_id: source.id,
+            media_type: "text/plain".to_string(),
+        },
+    }
+}
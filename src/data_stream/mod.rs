@@ -0,0 +1,373 @@
+//! The data stream protocol spoken by AI SDK chat frontends.
+//!
+//! A response is a stream of server-sent events, one JSON [`DataStreamPart`]
+//! per `data:` line, terminated by `data: [DONE]`. [`DataStreamEncoder`]
+//! turns model streams, tool results and custom data into such a body,
+//! [`decode_data_stream`] reads one.
+//!
+//! https://ai-sdk.dev/docs/ai-sdk-ui/stream-protocol#data-stream-protocol
+
+mod decoder;
+mod encoder;
+
+pub use decoder::decode_data_stream;
+pub use encoder::{DataStreamEncoder, DataStreamEvent};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JSONValue};
+
+/// The event that ends a data stream.
+pub const DATA_STREAM_DONE: &str = "data: [DONE]\n\n";
+
+/// Headers of a data stream response.
+pub const DATA_STREAM_HEADERS: [(&str, &str); 5] = [
+    ("content-type", "text/event-stream"),
+    ("cache-control", "no-cache"),
+    ("connection", "keep-alive"),
+    ("x-vercel-ai-ui-message-stream", "v1"),
+    // Keeps proxies such as nginx from buffering the stream.
+    ("x-accel-buffering", "no"),
+];
+
+/// A part of a data stream. Text and reasoning are streamed in blocks that
+/// are opened, filled with deltas and closed, identified by `id`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum DataStreamPart {
+    Start {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message_id: Option<String>,
+    },
+    StartStep,
+    TextStart {
+        id: String,
+    },
+    TextDelta {
+        id: String,
+        delta: String,
+    },
+    TextEnd {
+        id: String,
+    },
+    ReasoningStart {
+        id: String,
+    },
+    ReasoningDelta {
+        id: String,
+        delta: String,
+    },
+    ReasoningEnd {
+        id: String,
+    },
+    ToolInputStart {
+        tool_call_id: String,
+        tool_name: String,
+    },
+    ToolInputDelta {
+        tool_call_id: String,
+        input_text_delta: String,
+    },
+    ToolInputAvailable {
+        tool_call_id: String,
+        tool_name: String,
+        input: JSONValue,
+    },
+    ToolOutputAvailable {
+        tool_call_id: String,
+        output: JSONValue,
+    },
+    ToolOutputError {
+        tool_call_id: String,
+        error_text: String,
+    },
+    SourceUrl {
+        source_id: String,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    SourceDocument {
+        source_id: String,
+        media_type: String,
+        title: String,
+    },
+    /// A generated file, `url` is usually a `data:` URL.
+    File {
+        url: String,
+        media_type: String,
+    },
+    /// Custom data, sent with the type `data-{name}`. Frontends replace the
+    /// data part with the same `id`.
+    #[serde(skip)]
+    Data {
+        name: String,
+        id: Option<String>,
+        data: JSONValue,
+    },
+    Error {
+        error_text: String,
+    },
+    FinishStep,
+    Finish {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        message_metadata: Option<JSONValue>,
+    },
+}
+
+impl DataStreamPart {
+    pub fn to_json(&self) -> JSONValue {
+        match self {
+            DataStreamPart::Data { name, id, data } => {
+                let mut json = json!({ "type": format!("data-{name}"), "data": data });
+                if let Some(id) = id {
+                    json["id"] = json!(id);
+                }
+                json
+            }
+            part => serde_json::to_value(part).expect("data stream parts serialize to JSON"),
+        }
+    }
+
+    pub fn from_json(json: JSONValue) -> Result<Self, serde_json::Error> {
+        match json["type"]
+            .as_str()
+            .and_then(|kind| kind.strip_prefix("data-"))
+        {
+            Some(name) => Ok(DataStreamPart::Data {
+                name: name.to_string(),
+                id: json["id"].as_str().map(str::to_string),
+                data: json["data"].clone(),
+            }),
+            None => serde_json::from_value(json),
+        }
+    }
+
+    /// The part as a server-sent event.
+    pub fn to_sse(&self) -> String {
+        format!("data: {}\n\n", self.to_json())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::{stream, StreamExt};
+    use serde_json::json;
+
+    use super::{
+        decode_data_stream, DataStreamEncoder, DataStreamEvent, DataStreamPart, DATA_STREAM_DONE,
+    };
+    use crate::{
+        model::{
+            finish_reason::LanguageModelFinishReason,
+            function_tool_call::LanguageModelFunctionToolCall,
+            stream_part::LanguageModelStreamPart, usage::LanguageModelUsage,
+        },
+        prompt::ToolResultPart,
+    };
+
+    fn finish() -> LanguageModelStreamPart {
+        LanguageModelStreamPart::Finish {
+            finish_reason: LanguageModelFinishReason::Stop,
+            usage: LanguageModelUsage::new(10, 20),
+            provider_metadata: None,
+            logprobs: None,
+        }
+    }
+
+    async fn decode(body: Vec<String>) -> Vec<DataStreamPart> {
+        let chunks = vec![Ok(body.concat().into_bytes())];
+        decode_data_stream("http://localhost/chat", stream::iter(chunks).boxed())
+            .map(Result::unwrap)
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_encode_and_decode_data_stream() {
+        let model_stream = stream::iter(vec![
+            Ok(LanguageModelStreamPart::ReasoningDelta(
+                "Look it up.".to_string(),
+            )),
+            Ok(LanguageModelStreamPart::TextDelta("Checking".to_string())),
+            Ok(LanguageModelStreamPart::TextDelta(
+                " the weather.".to_string(),
+            )),
+            Ok(LanguageModelStreamPart::ToolCall(
+                LanguageModelFunctionToolCall {
+                    tool_name: "weather".to_string(),
+                    tool_call_id: "call_1".to_string(),
+                    args: r#"{"city":"Kathmandu"}"#.to_string(),
+                },
+            )),
+            Ok(LanguageModelStreamPart::Finish {
+                finish_reason: LanguageModelFinishReason::ToolCalls,
+                usage: LanguageModelUsage::new(10, 20),
+                provider_metadata: None,
+                logprobs: None,
+            }),
+        ])
+        .boxed();
+
+        let body: Vec<String> = DataStreamEncoder::new()
+            .with_message_id("msg_1")
+            .encode_stream(model_stream)
+            .collect()
+            .await;
+        assert!(body[0].starts_with("data: {") && body[0].ends_with("}\n\n"));
+        assert_eq!(body.last().unwrap(), DATA_STREAM_DONE);
+
+        let custom = DataStreamPart::Data {
+            name: "weather".to_string(),
+            id: Some("weather_1".to_string()),
+            data: json!({ "city": "Kathmandu" }),
+        };
+        assert_eq!(
+            custom.to_json(),
+            json!({ "type": "data-weather", "id": "weather_1", "data": { "city": "Kathmandu" } })
+        );
+
+        let mut body = body;
+        body.insert(body.len() - 1, custom.to_sse());
+        let chunks = body
+            .concat()
+            .into_bytes()
+            .chunks(7)
+            .map(|chunk| Ok(chunk.to_vec()))
+            .collect::<Vec<_>>();
+        let parts: Vec<DataStreamPart> =
+            decode_data_stream("http://localhost/chat", stream::iter(chunks).boxed())
+                .map(Result::unwrap)
+                .collect()
+                .await;
+        assert_eq!(
+            parts,
+            vec![
+                DataStreamPart::Start {
+                    message_id: Some("msg_1".to_string())
+                },
+                DataStreamPart::StartStep,
+                DataStreamPart::ReasoningStart {
+                    id: "reasoning-0".to_string()
+                },
+                DataStreamPart::ReasoningDelta {
+                    id: "reasoning-0".to_string(),
+                    delta: "Look it up.".to_string()
+                },
+                DataStreamPart::ReasoningEnd {
+                    id: "reasoning-0".to_string()
+                },
+                DataStreamPart::TextStart {
+                    id: "text-1".to_string()
+                },
+                DataStreamPart::TextDelta {
+                    id: "text-1".to_string(),
+                    delta: "Checking".to_string()
+                },
+                DataStreamPart::TextDelta {
+                    id: "text-1".to_string(),
+                    delta: " the weather.".to_string()
+                },
+                DataStreamPart::TextEnd {
+                    id: "text-1".to_string()
+                },
+                DataStreamPart::ToolInputAvailable {
+                    tool_call_id: "call_1".to_string(),
+                    tool_name: "weather".to_string(),
+                    input: json!({ "city": "Kathmandu" })
+                },
+                DataStreamPart::FinishStep,
+                DataStreamPart::Finish {
+                    message_metadata: None
+                },
+                custom,
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_encode_tool_results_across_steps() {
+        let tool_result = |id: &str, result: &str, is_error| {
+            Ok(DataStreamEvent::ToolResult(ToolResultPart {
+                tool_call_id: id.to_string(),
+                tool_name: "weather".to_string(),
+                result: result.to_string(),
+                is_error,
+            }))
+        };
+        let events = stream::iter(vec![
+            Ok(finish().into()),
+            tool_result("call_1", r#"{"temperature":21}"#, None),
+            tool_result("call_2", "city not found", Some(true)),
+            Ok(LanguageModelStreamPart::TextDelta("Sunny.".to_string()).into()),
+            Ok(finish().into()),
+        ])
+        .boxed();
+
+        let body = DataStreamEncoder::new()
+            .encode_events(events)
+            .collect()
+            .await;
+        assert_eq!(
+            decode(body).await,
+            vec![
+                DataStreamPart::Start { message_id: None },
+                DataStreamPart::StartStep,
+                DataStreamPart::ToolOutputAvailable {
+                    tool_call_id: "call_1".to_string(),
+                    output: json!({ "temperature": 21 })
+                },
+                DataStreamPart::ToolOutputError {
+                    tool_call_id: "call_2".to_string(),
+                    error_text: "city not found".to_string()
+                },
+                DataStreamPart::FinishStep,
+                DataStreamPart::StartStep,
+                DataStreamPart::TextStart {
+                    id: "text-0".to_string()
+                },
+                DataStreamPart::TextDelta {
+                    id: "text-0".to_string(),
+                    delta: "Sunny.".to_string()
+                },
+                DataStreamPart::TextEnd {
+                    id: "text-0".to_string()
+                },
+                DataStreamPart::FinishStep,
+                DataStreamPart::Finish {
+                    message_metadata: None
+                },
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_encode_stream_with_data() {
+        let custom = DataStreamPart::Data {
+            name: "status".to_string(),
+            id: None,
+            data: json!("searching"),
+        };
+        // The data stream never ends; the response ends with the model stream.
+        let data = stream::iter(vec![custom.clone()])
+            .chain(stream::pending())
+            .boxed();
+        let model_stream = stream::iter(vec![Ok(finish())]).boxed();
+
+        let body = DataStreamEncoder::new()
+            .encode_stream_with_data(model_stream, data)
+            .collect()
+            .await;
+        let parts = decode(body).await;
+        assert!(parts.contains(&custom));
+        assert_eq!(
+            parts.last(),
+            Some(&DataStreamPart::Finish {
+                message_metadata: None
+            })
+        );
+    }
+}
@@ -0,0 +1,34 @@
+use std::time::Duration;
+
+use crate::model::usage::LanguageModelUsage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AbortReason {
+    /// The call was aborted through its [`AbortHandle`](crate::core::abort::AbortHandle).
+    Cancelled,
+    /// The whole call took longer than its timeout.
+    CallTimeout(Duration),
+    /// A single step took longer than the step timeout.
+    StepTimeout(Duration),
+}
+
+/// A call that was stopped before it finished. Holds what was generated
+/// until then.
+#[derive(Debug, Clone)]
+pub struct AbortedError {
+    pub reason: AbortReason,
+    /// Text generated before the call was stopped.
+    pub text: String,
+    /// Usage of the steps that finished before the call was stopped.
+    pub usage: LanguageModelUsage,
+}
+
+impl std::fmt::Display for AbortedError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.reason {
+            AbortReason::Cancelled => write!(f, "The call was aborted"),
+            AbortReason::CallTimeout(timeout) => write!(f, "The call timed out after {timeout:?}"),
+            AbortReason::StepTimeout(timeout) => write!(f, "A step timed out after {timeout:?}"),
+        }
+    }
+}
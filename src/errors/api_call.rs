@@ -0,0 +1,155 @@
+use serde_json::Value as JSONValue;
+
+/// A failed call to a provider API, either because the provider answered with
+/// an error status or because the request did not get an answer at all.
+#[derive(Debug, Clone)]
+pub struct ApiCallError {
+    pub message: String,
+    pub url: String,
+    /// `None` when no response was received, e.g. on a connection reset.
+    pub status: Option<u16>,
+    pub response_headers: Vec<(String, String)>,
+    pub response_body: Option<String>,
+    /// Error code from the provider's error payload, e.g.
+    /// `context_length_exceeded` or `insufficient_quota`.
+    pub code: Option<String>,
+    /// Whether repeating the same request may succeed.
+    pub is_retryable: bool,
+}
+
+/// The code and message of a provider's error payload.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ApiErrorPayload {
+    pub code: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Extracts the error details from a provider's JSON error body.
+pub type ApiErrorPayloadParser = fn(&JSONValue) -> ApiErrorPayload;
+
+/// Error codes of quota and billing errors, which are sent with status 429
+/// but do not go away by retrying.
+const NON_RETRYABLE_CODES: [&str; 2] = ["insufficient_quota", "billing_hard_limit_reached"];
+
+impl ApiCallError {
+    /// An error response. Rate limits, timeouts and server errors are retryable.
+    pub fn from_response(
+        url: &str,
+        status: u16,
+        response_headers: Vec<(String, String)>,
+        response_body: String,
+    ) -> Self {
+        ApiCallError {
+            message: format!("Request to {url} failed with status {status}: {response_body}"),
+            url: url.to_string(),
+            status: Some(status),
+            response_headers,
+            response_body: Some(response_body),
+            code: None,
+            is_retryable: matches!(status, 408 | 409 | 429) || status >= 500,
+        }
+    }
+
+    /// A request that did not get a response.
+    pub fn connection(url: &str, message: String, is_retryable: bool) -> Self {
+        ApiCallError {
+            message: format!("Request to {url} failed: {message}"),
+            url: url.to_string(),
+            status: None,
+            response_headers: Vec::new(),
+            response_body: None,
+            code: None,
+            is_retryable,
+        }
+    }
+
+    /// Fills in the code and message from the provider's error payload, if
+    /// the response body is JSON.
+    pub fn with_payload(mut self, parse: ApiErrorPayloadParser) -> Self {
+        let Some(body) = self
+            .response_body
+            .as_deref()
+            .and_then(|body| serde_json::from_str::<JSONValue>(body).ok())
+        else {
+            return self;
+        };
+        let payload = parse(&body);
+        if let Some(message) = payload.message {
+            self.message = match self.status {
+                Some(status) => format!("{} (status {status})", message),
+                None => message,
+            };
+        }
+        if let Some(code) = &payload.code {
+            self.is_retryable &= !NON_RETRYABLE_CODES.contains(&code.as_str());
+        }
+        self.code = payload.code;
+        self
+    }
+
+    /// Returns the value of the response header `name`, ignoring case.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.response_headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(name))
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Whether the prompt did not fit into the model's context window.
+    pub(crate) fn is_context_length_exceeded(&self) -> bool {
+        if !self
+            .status
+            .is_some_and(|status| (400..500).contains(&status))
+        {
+            return false;
+        }
+        let message = self.message.to_lowercase();
+        matches!(
+            self.code.as_deref(),
+            Some("context_length_exceeded" | "string_above_max_length")
+        ) || [
+            "maximum context length",
+            "context length",
+            "too many tokens",
+            "prompt is too long",
+        ]
+        .iter()
+        .any(|pattern| message.contains(pattern))
+    }
+
+    /// Whether the provider refused the request because of its content policy.
+    pub(crate) fn is_content_filtered(&self) -> bool {
+        matches!(
+            self.code.as_deref(),
+            Some("content_filter" | "content_policy_violation" | "content_filtered")
+        )
+    }
+}
+
+impl std::fmt::Display for ApiCallError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+/// Reads the `{"error": {"message", "code", "type"}}` payload used by OpenAI
+/// and most OpenAI compatible providers. Falls back to a top-level `message`.
+pub fn parse_openai_error_payload(body: &JSONValue) -> ApiErrorPayload {
+    let error = if body["error"].is_object() {
+        &body["error"]
+    } else {
+        body
+    };
+    let code = match &error["code"] {
+        JSONValue::String(code) => Some(code.clone()),
+        JSONValue::Number(code) => Some(code.to_string()),
+        _ => error["type"].as_str().map(str::to_string),
+    };
+    ApiErrorPayload {
+        code,
+        message: error["message"]
+            .as_str()
+            .or_else(|| body["error"].as_str())
+            .map(str::to_string),
+    }
+}
@@ -1,5 +1,15 @@
+mod aborted;
+mod api_call;
 mod model;
 mod provider;
+mod retry;
+mod store;
 
+pub use aborted::{AbortReason, AbortedError};
+pub use api_call::{
+    parse_openai_error_payload, ApiCallError, ApiErrorPayload, ApiErrorPayloadParser,
+};
 pub use model::ModelError;
 pub use provider::ProviderError;
+pub use retry::{RetryError, RetryErrorReason};
+pub use store::StoreError;
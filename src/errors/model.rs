@@ -1,5 +1,7 @@
 use thiserror::Error;
 
+use super::{AbortedError, ApiCallError, RetryError, StoreError};
+
 #[derive(Error, Debug)]
 pub enum ModelError {
     #[error("Model not found")]
@@ -12,6 +14,69 @@ pub enum ModelError {
     InternalError(String),
     #[error("Invalid Prompt Provided: {0}")]
     InvalidPrompt(String),
+    #[error("API call failed: {0}")]
+    ApiCall(Box<ApiCallError>),
+    #[error("The prompt exceeds the model's context length: {0}")]
+    ContextLengthExceeded(Box<ApiCallError>),
+    #[error("The request was rejected by the content filter: {0}")]
+    ContentFiltered(Box<ApiCallError>),
+    #[error("No content generated: {0}")]
+    NoContent(String),
+    #[error("Invalid JSON response from {url}: {message}")]
+    InvalidResponseJson {
+        url: String,
+        message: String,
+        text: String,
+    },
+    #[error("Failed to download {url}: {message}")]
+    Download { url: String, message: String },
+    #[error("{0}")]
+    Retry(RetryError),
+    #[error("{0}")]
+    Aborted(AbortedError),
+    #[error("{0}")]
+    Store(StoreError),
     #[error("Some Unknown Error Occured: {0}")]
     Other(String),
 }
+
+impl ModelError {
+    /// Whether repeating the call that failed may succeed.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            ModelError::ApiCall(error) => error.is_retryable,
+            _ => false,
+        }
+    }
+
+    /// The failed API call behind this error, if any.
+    pub fn api_call_error(&self) -> Option<&ApiCallError> {
+        match self {
+            ModelError::ApiCall(error)
+            | ModelError::ContextLengthExceeded(error)
+            | ModelError::ContentFiltered(error) => Some(error),
+            ModelError::Retry(error) => error.last_error()?.api_call_error(),
+            _ => None,
+        }
+    }
+}
+
+impl From<ApiCallError> for ModelError {
+    /// Wraps the error in the variant matching its provider error code.
+    fn from(error: ApiCallError) -> Self {
+        let error = Box::new(error);
+        if error.is_context_length_exceeded() {
+            ModelError::ContextLengthExceeded(error)
+        } else if error.is_content_filtered() {
+            ModelError::ContentFiltered(error)
+        } else {
+            ModelError::ApiCall(error)
+        }
+    }
+}
+
+impl From<StoreError> for ModelError {
+    fn from(error: StoreError) -> Self {
+        ModelError::Store(error)
+    }
+}
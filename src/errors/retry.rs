@@ -0,0 +1,37 @@
+use super::ModelError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryErrorReason {
+    /// Every attempt failed with a retryable error.
+    MaxRetriesExceeded,
+    /// An attempt failed with an error that is not worth retrying.
+    ErrorNotRetryable,
+}
+
+/// A call that failed after being retried. Holds the error of every attempt,
+/// in order.
+#[derive(Debug)]
+pub struct RetryError {
+    pub reason: RetryErrorReason,
+    pub errors: Vec<ModelError>,
+}
+
+impl RetryError {
+    pub fn last_error(&self) -> Option<&ModelError> {
+        self.errors.last()
+    }
+}
+
+impl std::fmt::Display for RetryError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let reason = match self.reason {
+            RetryErrorReason::MaxRetriesExceeded => "Failed after",
+            RetryErrorReason::ErrorNotRetryable => "Failed with a non-retryable error after",
+        };
+        write!(f, "{reason} {} attempts", self.errors.len())?;
+        if let Some(error) = self.last_error() {
+            write!(f, ". Last error: {error}")?;
+        }
+        Ok(())
+    }
+}
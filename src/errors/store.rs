@@ -0,0 +1,15 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum StoreError {
+    #[error("Conversation not found: {0}")]
+    NotFound(String),
+    #[error("Invalid conversation id: {0}")]
+    InvalidId(String),
+    #[error("Invalid stored conversation {id}: {message}")]
+    Corrupted { id: String, message: String },
+    #[error("Conversation store I/O failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Conversation database error: {0}")]
+    Database(String),
+}
@@ -23,6 +23,21 @@ impl GenerateFile {
         }
     }
 
+    pub fn mime_type(&self) -> &str {
+        &self.mime_type
+    }
+
+    /// The file as a `data:` URL.
+    pub fn to_data_url(&self) -> String {
+        let base64 = match (&self.base64, &self.buffer) {
+            (Some(base64), _) => base64.clone(),
+            (None, buffer) => {
+                general_purpose::STANDARD.encode(buffer.as_deref().unwrap_or_default())
+            }
+        };
+        format!("data:{};base64,{base64}", self.mime_type)
+    }
+
     pub fn get_base64(&mut self) -> String {
         if self.base64.is_none() {
             self.base64 = Some(general_purpose::STANDARD.encode(self.buffer.as_ref().unwrap()));
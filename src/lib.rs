@@ -1,35 +1,49 @@
 pub mod core;
-mod errors;
-mod generate_file;
-mod model;
-mod prompt;
-mod provider;
-mod providers;
+pub mod data_stream;
+pub mod errors;
+pub mod generate_file;
+pub mod model;
+pub mod prompt;
+pub mod provider;
+pub mod providers;
+pub mod store;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+pub mod tokenizer;
+pub mod transport;
 mod utils;
 
 #[cfg(test)]
 mod test {
     use crate::{
-        core::generate_text, model::GenerateTextOptions, provider::LanguageModelProvider,
-        providers::openai::OpenAIProvider,
+        core::generate_text::{generate_text, GenerateTextOptions},
+        model::message::{LanguageModelMessage, LanguageModelUserMessage},
+        testing::MockLanguageModel,
     };
 
-    #[test]
-    fn test_build() {
-        let openai = OpenAIProvider::default();
-        let mut model = match openai.language_model("gpt-4o") {
-            Ok(model) => model,
-            Err(e) => panic!("Failed to build an openai model: {}", e),
-        };
+    #[tokio::test]
+    async fn test_build() {
+        let mut model = MockLanguageModel::new().with_text_response("Kathmandu");
         let response = generate_text(
             &mut model,
             GenerateTextOptions::default()
                 .system("You are a helpful assistant.".into())
                 .prompt("What is the capital of Nepal?".into()),
-        );
+        )
+        .await;
         match response {
-            Ok(text) => println!("Response: {}", text),
+            Ok(result) => assert_eq!(result.text(), "Kathmandu"),
             Err(e) => panic!("Failed to generate text: {}", e),
         }
+
+        let requests = model.requests();
+        assert_eq!(requests.len(), 1);
+        assert!(matches!(
+            &requests[0].prompt[..],
+            [LanguageModelMessage::System(system), LanguageModelMessage::User(user)]
+                if system == "You are a helpful assistant."
+                    && matches!(&user[..], [LanguageModelUserMessage::Text(part)]
+                        if part.text == "What is the capital of Nepal?")
+        ));
     }
 }
@@ -1,19 +1,22 @@
 use crate::errors::ModelError;
-
-#[derive(Debug, PartialEq)]
+use serde::{Deserialize, Serialize};
+
+/// Settings of a single model call.
+///
+/// Every setting is optional; settings that are not set are left out of the
+/// provider request, so the provider default applies. Settings are layered
+/// with [`LanguageModelCallSettings::merge`]: provider defaults, then model
+/// defaults (see `DefaultSettingsMiddleware`), then the per-call settings.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct LanguageModelCallSettings {
     /// Maximum number of tokens to generate.
-    ///
-    /// Default: 2056
-    pub max_tokens: usize,
+    pub max_tokens: Option<usize>,
 
     /// Temperature setting. This is a number between 0 (almost no randomness) and
     /// 1 (very random).
     ///
     /// It is recommended to set either `temperature` or `top_p`, but not both.
-    ///
-    /// Default: 0
-    pub temperature: f32,
+    pub temperature: Option<f32>,
 
     /// Nucleus sampling. This is a number between 0 and 1.
     ///
@@ -55,7 +58,7 @@ pub struct LanguageModelCallSettings {
     /// Maximum number of retries. Set to 0 to disable retries.
     ///
     /// Default: 2
-    pub max_retries: u32,
+    pub max_retries: Option<u32>,
 
     /// Additional HTTP headers to be sent with the request.
     /// Only applicable for HTTP-based providers.
@@ -69,33 +72,60 @@ pub struct LanguageModelCallSettings {
     pub response_format: Option<LanguageModelCallSettingsResponseFormat>,
 }
 
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Serialize, Deserialize)]
 pub enum LanguageModelCallSettingsResponseFormat {
     Text,
     Json,
 }
 
-impl Default for LanguageModelCallSettings {
-    fn default() -> Self {
+/// Retries used when `max_retries` is not set.
+pub const DEFAULT_MAX_RETRIES: u32 = 2;
+
+impl LanguageModelCallSettings {
+    /// Returns these settings with every setting that is set in `overrides`
+    /// replaced by its value. Headers are combined; a header in `overrides`
+    /// replaces a header with the same name.
+    pub fn merge(&self, overrides: &LanguageModelCallSettings) -> LanguageModelCallSettings {
+        let mut headers: Vec<(String, String)> = self
+            .headers
+            .iter()
+            .filter(|(key, _)| {
+                !overrides
+                    .headers
+                    .iter()
+                    .any(|(other, _)| other.eq_ignore_ascii_case(key))
+            })
+            .cloned()
+            .collect();
+        headers.extend(overrides.headers.iter().cloned());
+
         LanguageModelCallSettings {
-            max_tokens: 2056,
-            temperature: 0.0,
-            top_p: None,
-            top_k: None,
-            presence_penalty: None,
-            frequency_penalty: None,
-            stop_sequences: None,
-            seed: None,
-            max_retries: 2,
-            headers: Vec::new(),
-            response_format: None,
+            max_tokens: overrides.max_tokens.or(self.max_tokens),
+            temperature: overrides.temperature.or(self.temperature),
+            top_p: overrides.top_p.or(self.top_p),
+            top_k: overrides.top_k.or(self.top_k),
+            presence_penalty: overrides.presence_penalty.or(self.presence_penalty),
+            frequency_penalty: overrides.frequency_penalty.or(self.frequency_penalty),
+            stop_sequences: overrides
+                .stop_sequences
+                .clone()
+                .or_else(|| self.stop_sequences.clone()),
+            seed: overrides.seed.or(self.seed),
+            max_retries: overrides.max_retries.or(self.max_retries),
+            headers,
+            response_format: overrides
+                .response_format
+                .clone()
+                .or_else(|| self.response_format.clone()),
         }
     }
-}
 
-impl LanguageModelCallSettings {
+    pub fn max_retries_or_default(&self) -> u32 {
+        self.max_retries.unwrap_or(DEFAULT_MAX_RETRIES)
+    }
+
     pub fn prepare(&mut self) -> Result<(), ModelError> {
-        if self.max_tokens < 1 {
+        if self.max_tokens.is_some_and(|max_tokens| max_tokens < 1) {
             return Err(ModelError::InvalidArgument(
                 "max_tokens must be greater than `1`".to_string(),
             ));
@@ -110,3 +140,38 @@ impl LanguageModelCallSettings {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::LanguageModelCallSettings;
+
+    #[test]
+    fn test_merge_layers() {
+        let provider = LanguageModelCallSettings {
+            max_tokens: Some(1024),
+            temperature: Some(0.2),
+            headers: vec![("X-Team".to_string(), "search".to_string())],
+            ..Default::default()
+        };
+        let model = LanguageModelCallSettings {
+            temperature: Some(0.5),
+            seed: Some(7),
+            ..Default::default()
+        };
+        let call = LanguageModelCallSettings {
+            temperature: Some(0.9),
+            headers: vec![("x-team".to_string(), "evals".to_string())],
+            ..Default::default()
+        };
+
+        let settings = provider.merge(&model).merge(&call);
+        assert_eq!(settings.max_tokens, Some(1024));
+        assert_eq!(settings.temperature, Some(0.9));
+        assert_eq!(settings.seed, Some(7));
+        assert_eq!(settings.top_p, None);
+        assert_eq!(
+            settings.headers,
+            vec![("x-team".to_string(), "evals".to_string())]
+        );
+    }
+}
@@ -1,5 +1,7 @@
 use super::tools::Tool;
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelCallWarning {
     UnsupportedSetting {
         setting: String,
@@ -13,4 +15,3 @@ pub enum LanguageModelCallWarning {
         message: String,
     },
 }
-
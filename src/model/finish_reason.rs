@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub enum LanguageModelFinishReason {
     Stop,
     Length,
@@ -5,11 +8,6 @@ pub enum LanguageModelFinishReason {
     ToolCalls,
     Error,
     Other,
+    #[default]
     Unknown,
 }
-
-impl Default for LanguageModelFinishReason {
-    fn default() -> Self {
-        Self::Unknown
-    }
-}
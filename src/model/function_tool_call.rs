@@ -1,6 +1,8 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelFunctionToolCall {
     pub tool_name: String,
     pub tool_call_id: String,
     pub args: String,
 }
-
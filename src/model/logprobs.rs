@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelLogprobs {
     pub token: String,
     pub logprob: f32,
@@ -1,20 +1,24 @@
-use std::any;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JSONValue;
 
 use crate::provider::metadata::LanguageModelProviderMetadata;
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelMessage {
     System(String),
-    User(LanguageModelUserMessage),
-    Assistant(LanguageModelAssistantMessage),
+    User(Vec<LanguageModelUserMessage>),
+    Assistant(Vec<LanguageModelAssistantMessage>),
     Tool(Vec<LanguageModelToolResultPart>),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelUserMessage {
     Text(LanguageModelTextPart),
     Image(LanguageModelImagePart),
     File(LanguageModelFilePart),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelAssistantMessage {
     Text(LanguageModelTextPart),
     Image(LanguageModelImagePart),
@@ -24,62 +28,72 @@ pub enum LanguageModelAssistantMessage {
     ToolCall(LanguageModelToolCallPart),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelToolResultPart {
     pub tool_call_id: String,
     pub tool_name: String,
-    pub result: any,
+    pub result: JSONValue,
     pub is_error: Option<bool>,
     pub content: Vec<LanguageModelToolResultPartContent>,
     pub provider_metadata: Option<LanguageModelProviderMetadata>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelToolResultPartContent {
     Text(String),
     /// Image URL and optional MIME type
     Image(String, Option<String>),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelReasoningPart {
     pub text: String,
     pub signature: Option<String>,
     pub provider_metadata: Option<LanguageModelProviderMetadata>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelRedactedReasoningPart {
     pub data: String,
     pub provider_metadata: Option<LanguageModelProviderMetadata>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelToolCallPart {
     pub tool_call_id: String,
     pub tool_name: String,
-    pub args: Vec<any>,
+    pub args: JSONValue,
     pub provider_metadata: Option<LanguageModelProviderMetadata>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelTextPart {
     pub text: String,
     pub provider_metadata: Option<LanguageModelProviderMetadata>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelImagePart {
     pub image: LanguageModelImagePartContent,
     pub mime_type: Option<String>,
     pub provider_metadata: Option<LanguageModelProviderMetadata>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelFilePart {
     pub file_content: LanguageModelFilePartContent,
     pub mime_type: Option<String>,
     pub provider_metadata: Option<LanguageModelProviderMetadata>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelImagePartContent {
     Base64(String),
     Url(String),
     Buffer(Vec<u8>),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelFilePartContent {
     Base64(String),
     Url(String),
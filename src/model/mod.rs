@@ -1,16 +1,24 @@
+use serde::{Deserialize, Serialize};
+
 pub mod call_settings;
 pub mod call_warning;
 pub mod finish_reason;
 pub mod function_tool_call;
 pub mod logprobs;
 pub mod message;
+pub mod pricing;
 pub mod request_metadata;
 pub mod response_metadata;
 pub mod source;
 pub mod step_result;
+pub mod stream_part;
 pub mod tools;
 pub mod usage;
 
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+
 use crate::{
     core::generate_text::GenerateTextOptions, errors::ModelError,
     provider::metadata::LanguageModelProviderMetadata,
@@ -24,50 +32,410 @@ use message::LanguageModelMessage;
 use request_metadata::LanguageModelRequestMetadata;
 use response_metadata::LanguageModelResponseMetadata;
 use source::LanguageModelSource;
+use stream_part::LanguageModelStreamPart;
+use tools::{LanguageModelFunctionTool, LanguageModelToolChoice};
 use usage::LanguageModelUsage;
 
 pub enum LanguageModelCall {
-    GenerateText(GenerateTextOptions),
+    GenerateText(Box<GenerateTextOptions>),
     GenerateImage(String, u32, u32),
     GenerateObject(String, serde_json::Value),
 }
 
-pub trait LanguageModel {
+#[async_trait]
+pub trait LanguageModel: Send + Sync {
+    /// Name of the provider, e.g. `openai.chat`.
+    fn provider(&self) -> String;
+    /// Id of the model as it is sent to the provider.
+    fn model_id(&self) -> String;
     fn supports_urls(&self, url: String) -> bool;
-    fn do_generate(
+    /// Whether file parts, e.g. PDFs, can be sent as `url`. Files the model
+    /// cannot fetch are downloaded and sent inline. Defaults to
+    /// [`LanguageModel::supports_urls`].
+    fn supports_file_urls(&self, url: String) -> bool {
+        self.supports_urls(url)
+    }
+
+    async fn do_generate(
         &self,
         request: LanguageModelDoGenerateRequest,
     ) -> Result<LanguageModelDoGenerateResponse, ModelError>;
+
+    /// Streams the generation. Models that only support blocking generation
+    /// keep the default, which reports the operation as not supported.
+    async fn do_stream(
+        &self,
+        _request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        Err(ModelError::NotSupported(
+            "This model does not support streaming".to_string(),
+        ))
+    }
+}
+
+/// Lets wrapped models, see `wrap_language_model`, be used like any other model.
+#[async_trait]
+impl<T: LanguageModel + ?Sized> LanguageModel for Box<T> {
+    fn provider(&self) -> String {
+        (**self).provider()
+    }
+
+    fn model_id(&self) -> String {
+        (**self).model_id()
+    }
+
+    fn supports_urls(&self, url: String) -> bool {
+        (**self).supports_urls(url)
+    }
+
+    fn supports_file_urls(&self, url: String) -> bool {
+        (**self).supports_file_urls(url)
+    }
+
+    async fn do_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        (**self).do_generate(request).await
+    }
+
+    async fn do_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        (**self).do_stream(request).await
+    }
 }
 
+/// Lets a model be shared, e.g. between tasks.
+#[async_trait]
+impl<T: LanguageModel + ?Sized> LanguageModel for Arc<T> {
+    fn provider(&self) -> String {
+        (**self).provider()
+    }
+
+    fn model_id(&self) -> String {
+        (**self).model_id()
+    }
+
+    fn supports_urls(&self, url: String) -> bool {
+        (**self).supports_urls(url)
+    }
+
+    fn supports_file_urls(&self, url: String) -> bool {
+        (**self).supports_file_urls(url)
+    }
+
+    async fn do_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        (**self).do_generate(request).await
+    }
+
+    async fn do_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        (**self).do_stream(request).await
+    }
+}
+
+pub type LanguageModelStream = BoxStream<'static, Result<LanguageModelStreamPart, ModelError>>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelDoGenerateRequest {
-    call_settings: Option<LanguageModelCallSettings>,
-    input_format: LanguageModelDoGenerateRequestInputFormat,
-    prompt: Vec<LanguageModelMessage>,
-    provider_metadata: Option<LanguageModelProviderMetadata>,
+    pub(crate) call_settings: Option<LanguageModelCallSettings>,
+    pub(crate) input_format: LanguageModelDoGenerateRequestInputFormat,
+    pub(crate) prompt: Vec<LanguageModelMessage>,
+    pub(crate) tools: Option<Vec<LanguageModelFunctionTool>>,
+    pub(crate) tool_choice: Option<LanguageModelToolChoice>,
+    pub(crate) provider_metadata: Option<LanguageModelProviderMetadata>,
+}
+
+impl LanguageModelDoGenerateRequest {
+    pub fn new(
+        input_format: LanguageModelDoGenerateRequestInputFormat,
+        prompt: Vec<LanguageModelMessage>,
+    ) -> Self {
+        LanguageModelDoGenerateRequest {
+            call_settings: None,
+            input_format,
+            prompt,
+            tools: None,
+            tool_choice: None,
+            provider_metadata: None,
+        }
+    }
+
+    pub fn with_call_settings(mut self, call_settings: LanguageModelCallSettings) -> Self {
+        self.call_settings = Some(call_settings);
+        self
+    }
+
+    pub fn with_tools(mut self, tools: Vec<LanguageModelFunctionTool>) -> Self {
+        self.tools = Some(tools);
+        self
+    }
+
+    pub fn with_tool_choice(mut self, tool_choice: LanguageModelToolChoice) -> Self {
+        self.tool_choice = Some(tool_choice);
+        self
+    }
+
+    pub fn with_provider_metadata(
+        mut self,
+        provider_metadata: LanguageModelProviderMetadata,
+    ) -> Self {
+        self.provider_metadata = Some(provider_metadata);
+        self
+    }
+
+    pub fn call_settings(&self) -> Option<&LanguageModelCallSettings> {
+        self.call_settings.as_ref()
+    }
+
+    pub fn call_settings_mut(&mut self) -> &mut Option<LanguageModelCallSettings> {
+        &mut self.call_settings
+    }
+
+    /// The settings of this call layered on top of `defaults`.
+    pub fn call_settings_with_defaults(
+        &self,
+        defaults: &LanguageModelCallSettings,
+    ) -> LanguageModelCallSettings {
+        match &self.call_settings {
+            Some(settings) => defaults.merge(settings),
+            None => defaults.clone(),
+        }
+    }
+
+    pub fn input_format(&self) -> LanguageModelDoGenerateRequestInputFormat {
+        self.input_format
+    }
+
+    pub fn input_format_mut(&mut self) -> &mut LanguageModelDoGenerateRequestInputFormat {
+        &mut self.input_format
+    }
+
+    pub fn prompt(&self) -> &[LanguageModelMessage] {
+        &self.prompt
+    }
+
+    pub fn prompt_mut(&mut self) -> &mut Vec<LanguageModelMessage> {
+        &mut self.prompt
+    }
+
+    pub fn tools(&self) -> Option<&[LanguageModelFunctionTool]> {
+        self.tools.as_deref()
+    }
+
+    pub fn tools_mut(&mut self) -> &mut Option<Vec<LanguageModelFunctionTool>> {
+        &mut self.tools
+    }
+
+    pub fn tool_choice(&self) -> Option<&LanguageModelToolChoice> {
+        self.tool_choice.as_ref()
+    }
+
+    pub fn tool_choice_mut(&mut self) -> &mut Option<LanguageModelToolChoice> {
+        &mut self.tool_choice
+    }
+
+    /// Provider specific options, keyed by provider name.
+    pub fn provider_metadata(&self) -> Option<&LanguageModelProviderMetadata> {
+        self.provider_metadata.as_ref()
+    }
+
+    pub fn provider_metadata_mut(&mut self) -> &mut Option<LanguageModelProviderMetadata> {
+        &mut self.provider_metadata
+    }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum LanguageModelDoGenerateRequestInputFormat {
     Messages,
     Prompt,
 }
 
-#[derive(Default)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct LanguageModelDoGenerateResponse {
-    text: Option<String>,
-    reasoning: Vec<LanguageModelDoGenerateResponseReasoning>,
-    files: Vec<LanguageModelDoGenerateResponseFiles>,
-    tool_calls: Vec<LanguageModelFunctionToolCall>,
-    finish_reason: LanguageModelFinishReason,
-    usage: LanguageModelUsage,
-    request_body: Option<LanguageModelRequestMetadata>,
-    response: Option<LanguageModelResponseMetadata>,
-    warnings: Vec<LanguageModelCallWarning>,
-    provider_metadata: Option<LanguageModelProviderMetadata>,
-    sources: Option<LanguageModelSource>,
-    logprobs: Option<LanguageModelLogprobs>,
+    pub(crate) text: Option<String>,
+    pub(crate) reasoning: Vec<LanguageModelDoGenerateResponseReasoning>,
+    pub(crate) files: Vec<LanguageModelDoGenerateResponseFiles>,
+    pub(crate) tool_calls: Vec<LanguageModelFunctionToolCall>,
+    pub(crate) finish_reason: LanguageModelFinishReason,
+    pub(crate) usage: LanguageModelUsage,
+    pub(crate) request_body: Option<LanguageModelRequestMetadata>,
+    pub(crate) response: Option<LanguageModelResponseMetadata>,
+    pub(crate) warnings: Vec<LanguageModelCallWarning>,
+    pub(crate) provider_metadata: Option<LanguageModelProviderMetadata>,
+    pub(crate) sources: Vec<LanguageModelSource>,
+    pub(crate) logprobs: Option<LanguageModelLogprobs>,
+}
+
+impl LanguageModelDoGenerateResponse {
+    pub fn text(&self) -> Option<&str> {
+        self.text.as_deref()
+    }
+
+    pub fn text_mut(&mut self) -> &mut Option<String> {
+        &mut self.text
+    }
+
+    pub fn reasoning(&self) -> &[LanguageModelDoGenerateResponseReasoning] {
+        &self.reasoning
+    }
+
+    pub fn reasoning_mut(&mut self) -> &mut Vec<LanguageModelDoGenerateResponseReasoning> {
+        &mut self.reasoning
+    }
+
+    pub fn files(&self) -> &[LanguageModelDoGenerateResponseFiles] {
+        &self.files
+    }
+
+    pub fn files_mut(&mut self) -> &mut Vec<LanguageModelDoGenerateResponseFiles> {
+        &mut self.files
+    }
+
+    pub fn tool_calls(&self) -> &[LanguageModelFunctionToolCall] {
+        &self.tool_calls
+    }
+
+    pub fn tool_calls_mut(&mut self) -> &mut Vec<LanguageModelFunctionToolCall> {
+        &mut self.tool_calls
+    }
+
+    pub fn finish_reason(&self) -> LanguageModelFinishReason {
+        self.finish_reason
+    }
+
+    pub fn finish_reason_mut(&mut self) -> &mut LanguageModelFinishReason {
+        &mut self.finish_reason
+    }
+
+    pub fn usage(&self) -> &LanguageModelUsage {
+        &self.usage
+    }
+
+    pub fn usage_mut(&mut self) -> &mut LanguageModelUsage {
+        &mut self.usage
+    }
+
+    pub fn request_body(&self) -> Option<&LanguageModelRequestMetadata> {
+        self.request_body.as_ref()
+    }
+
+    pub fn request_body_mut(&mut self) -> &mut Option<LanguageModelRequestMetadata> {
+        &mut self.request_body
+    }
+
+    pub fn response(&self) -> Option<&LanguageModelResponseMetadata> {
+        self.response.as_ref()
+    }
+
+    pub fn response_mut(&mut self) -> &mut Option<LanguageModelResponseMetadata> {
+        &mut self.response
+    }
+
+    pub fn warnings(&self) -> &[LanguageModelCallWarning] {
+        &self.warnings
+    }
+
+    pub fn warnings_mut(&mut self) -> &mut Vec<LanguageModelCallWarning> {
+        &mut self.warnings
+    }
+
+    pub fn provider_metadata(&self) -> Option<&LanguageModelProviderMetadata> {
+        self.provider_metadata.as_ref()
+    }
+
+    pub fn provider_metadata_mut(&mut self) -> &mut Option<LanguageModelProviderMetadata> {
+        &mut self.provider_metadata
+    }
+
+    pub fn sources(&self) -> &[LanguageModelSource] {
+        &self.sources
+    }
+
+    pub fn sources_mut(&mut self) -> &mut Vec<LanguageModelSource> {
+        &mut self.sources
+    }
+
+    pub fn logprobs(&self) -> Option<&LanguageModelLogprobs> {
+        self.logprobs.as_ref()
+    }
+
+    pub fn logprobs_mut(&mut self) -> &mut Option<LanguageModelLogprobs> {
+        &mut self.logprobs
+    }
+}
+
+pub struct LanguageModelDoStreamResponse {
+    pub(crate) stream: LanguageModelStream,
+    pub(crate) request_body: Option<LanguageModelRequestMetadata>,
+    pub(crate) response_headers: Vec<(String, String)>,
+    pub(crate) warnings: Vec<LanguageModelCallWarning>,
+}
+
+impl LanguageModelDoStreamResponse {
+    pub fn new(stream: LanguageModelStream) -> Self {
+        LanguageModelDoStreamResponse {
+            stream,
+            request_body: None,
+            response_headers: Vec::new(),
+            warnings: Vec::new(),
+        }
+    }
+
+    pub fn with_request_body(mut self, request_body: LanguageModelRequestMetadata) -> Self {
+        self.request_body = Some(request_body);
+        self
+    }
+
+    pub fn with_response_headers(mut self, response_headers: Vec<(String, String)>) -> Self {
+        self.response_headers = response_headers;
+        self
+    }
+
+    pub fn with_warnings(mut self, warnings: Vec<LanguageModelCallWarning>) -> Self {
+        self.warnings = warnings;
+        self
+    }
+
+    /// Replaces the stream with `f(stream)`, keeping the rest of the response.
+    pub fn map_stream(
+        mut self,
+        f: impl FnOnce(LanguageModelStream) -> LanguageModelStream,
+    ) -> Self {
+        let stream = std::mem::replace(&mut self.stream, futures::stream::empty().boxed());
+        self.stream = f(stream);
+        self
+    }
+
+    pub fn into_stream(self) -> LanguageModelStream {
+        self.stream
+    }
+
+    pub fn request_body(&self) -> Option<&LanguageModelRequestMetadata> {
+        self.request_body.as_ref()
+    }
+
+    pub fn response_headers(&self) -> &[(String, String)] {
+        &self.response_headers
+    }
+
+    pub fn warnings(&self) -> &[LanguageModelCallWarning] {
+        &self.warnings
+    }
+
+    pub fn warnings_mut(&mut self) -> &mut Vec<LanguageModelCallWarning> {
+        &mut self.warnings
+    }
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelDoGenerateResponseReasoning {
     Text {
         text: String,
@@ -76,11 +444,13 @@ pub enum LanguageModelDoGenerateResponseReasoning {
     Redacted(String),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelDoGenerateResponseFiles {
     pub file_content: LanguageModelDoGenerateResponseFilesContent,
     pub mime_type: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelDoGenerateResponseFilesContent {
     Base64(String),
     Buffer(Vec<u8>),
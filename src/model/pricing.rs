@@ -0,0 +1,235 @@
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, str::FromStr};
+
+use super::usage::LanguageModelUsage;
+use crate::providers::{
+    cohere::chat_model::model_id::CohereChatModelId,
+    mistral::chat_model::model_id::MistralChatModelId,
+    openai::chat_model::model_id::OpenAIChatModelId,
+};
+
+/// Token prices of a model in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPricing {
+    pub input: f64,
+    /// Price of prompt tokens read from the prompt cache. Defaults to `input`.
+    pub cached_input: Option<f64>,
+    pub output: f64,
+    /// Price of reasoning tokens. Defaults to `output`.
+    pub reasoning: Option<f64>,
+}
+
+/// An estimated cost in USD.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct CostEstimate {
+    /// Cost of the prompt tokens, including cached ones.
+    pub input: f64,
+    /// Cost of the completion tokens, including reasoning ones.
+    pub output: f64,
+}
+
+impl CostEstimate {
+    pub fn total(&self) -> f64 {
+        self.input + self.output
+    }
+}
+
+impl std::ops::Add for CostEstimate {
+    type Output = CostEstimate;
+
+    fn add(self, other: CostEstimate) -> CostEstimate {
+        CostEstimate {
+            input: self.input + other.input,
+            output: self.output + other.output,
+        }
+    }
+}
+
+impl ModelPricing {
+    pub const fn new(input: f64, output: f64) -> Self {
+        ModelPricing {
+            input,
+            cached_input: None,
+            output,
+            reasoning: None,
+        }
+    }
+
+    pub const fn with_cached_input(mut self, cached_input: f64) -> Self {
+        self.cached_input = Some(cached_input);
+        self
+    }
+
+    pub const fn with_reasoning(mut self, reasoning: f64) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    pub fn cost(&self, usage: &LanguageModelUsage) -> CostEstimate {
+        let cached = usage.cached_prompt_tokens.unwrap_or_default();
+        let reasoning = usage.reasoning_tokens.unwrap_or_default();
+        let per_token = |tokens: u32, price: f64| tokens as f64 * price / 1_000_000.0;
+
+        CostEstimate {
+            input: per_token(usage.prompt_tokens.saturating_sub(cached), self.input)
+                + per_token(cached, self.cached_input.unwrap_or(self.input)),
+            output: per_token(
+                usage.completion_tokens.saturating_sub(reasoning),
+                self.output,
+            ) + per_token(reasoning, self.reasoning.unwrap_or(self.output)),
+        }
+    }
+}
+
+/// Looks up model prices: custom prices first, then the built-in list prices
+/// of the OpenAI, Mistral and Cohere models.
+///
+/// Providers are named like their default provider name, e.g. `openai`;
+/// the `.chat` suffix of [`LanguageModel::provider`](super::LanguageModel::provider)
+/// is ignored.
+#[derive(Debug, Clone)]
+pub struct PricingCatalogue {
+    custom: HashMap<(String, String), ModelPricing>,
+    use_list_prices: bool,
+}
+
+impl Default for PricingCatalogue {
+    fn default() -> Self {
+        PricingCatalogue {
+            custom: HashMap::new(),
+            use_list_prices: true,
+        }
+    }
+}
+
+impl PricingCatalogue {
+    /// The list prices, without custom prices.
+    pub fn new() -> Self {
+        PricingCatalogue::default()
+    }
+
+    /// A catalogue with custom prices only.
+    pub fn empty() -> Self {
+        PricingCatalogue {
+            use_list_prices: false,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the price of a model, e.g. for a custom model or a negotiated rate.
+    pub fn with_price(mut self, provider: &str, model_id: &str, pricing: ModelPricing) -> Self {
+        self.custom.insert(
+            (provider_family(provider).to_string(), model_id.to_string()),
+            pricing,
+        );
+        self
+    }
+
+    pub fn with_openai_price(self, model_id: OpenAIChatModelId, pricing: ModelPricing) -> Self {
+        self.with_price("openai", &model_id.to_string(), pricing)
+    }
+
+    pub fn get(&self, provider: &str, model_id: &str) -> Option<ModelPricing> {
+        let provider = provider_family(provider);
+        if let Some(pricing) = self
+            .custom
+            .get(&(provider.to_string(), model_id.to_string()))
+        {
+            return Some(*pricing);
+        }
+        if !self.use_list_prices {
+            return None;
+        }
+        match provider {
+            "openai" => OpenAIChatModelId::from_str(model_id).ok()?.pricing(),
+            "mistral" => MistralChatModelId::from_str(model_id).ok()?.pricing(),
+            "cohere" => CohereChatModelId::from_str(model_id).ok()?.pricing(),
+            _ => None,
+        }
+    }
+
+    /// The cost of `usage`, or `None` if the model's price is unknown.
+    pub fn estimate(
+        &self,
+        provider: &str,
+        model_id: &str,
+        usage: &LanguageModelUsage,
+    ) -> Option<CostEstimate> {
+        self.get(provider, model_id)
+            .map(|pricing| pricing.cost(usage))
+    }
+}
+
+fn provider_family(provider: &str) -> &str {
+    provider.split('.').next().unwrap_or(provider)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ModelPricing, PricingCatalogue};
+    use crate::{
+        core::generate_text::{generate_text, GenerateTextOptions},
+        model::usage::LanguageModelUsage,
+        providers::openai::chat_model::model_id::OpenAIChatModelId,
+        testing::MockLanguageModel,
+    };
+
+    #[test]
+    fn test_estimate_cost() {
+        let usage = LanguageModelUsage {
+            cached_prompt_tokens: Some(400_000),
+            reasoning_tokens: Some(100_000),
+            ..LanguageModelUsage::new(1_000_000, 300_000)
+        };
+
+        // o4-mini: $1.10 input, $0.275 cached input, $4.40 output.
+        let cost = PricingCatalogue::new()
+            .estimate("openai.chat", "o4-mini", &usage)
+            .unwrap();
+        assert!((cost.input - (0.6 * 1.10 + 0.4 * 0.275)).abs() < 1e-9);
+        assert!((cost.output - 0.3 * 4.40).abs() < 1e-9);
+
+        let catalogue = PricingCatalogue::new()
+            .with_openai_price(
+                OpenAIChatModelId::Custom("ft:gpt-4o-mini:acme".to_string()),
+                ModelPricing::new(0.30, 1.20),
+            )
+            .with_price(
+                "openai",
+                "o4-mini",
+                ModelPricing::new(1.0, 4.0).with_reasoning(2.0),
+            );
+        let cost = catalogue
+            .estimate("openai.chat", "o4-mini", &usage)
+            .unwrap();
+        assert!((cost.total() - (1.0 + 0.2 * 4.0 + 0.1 * 2.0)).abs() < 1e-9);
+        assert!(catalogue
+            .estimate("openai.chat", "ft:gpt-4o-mini:acme", &usage)
+            .is_some());
+        assert!(catalogue
+            .estimate("deepseek.chat", "deepseek-chat", &usage)
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_generate_text_cost() {
+        let mut model = MockLanguageModel::new().with_text_response("Kathmandu");
+        let result = generate_text(
+            &mut model,
+            GenerateTextOptions::new()
+                .prompt("What is the capital of Nepal?".into())
+                .pricing(PricingCatalogue::empty().with_price(
+                    "mock",
+                    "mock-model",
+                    ModelPricing::new(1.0, 2.0),
+                )),
+        )
+        .await
+        .unwrap();
+
+        // 10 prompt and 20 completion tokens.
+        let cost = result.steps()[0].cost().unwrap();
+        assert!((cost.total() - 50.0 / 1_000_000.0).abs() < 1e-12);
+        assert_eq!(result.cost(), Some(cost));
+    }
+}
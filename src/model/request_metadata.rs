@@ -1,3 +1,10 @@
+use serde::{Deserialize, Serialize};
+
+use super::call_settings::LanguageModelCallSettings;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelRequestMetadata {
     pub body: Option<String>,
+    /// The settings the request was sent with, after all defaults were applied.
+    pub call_settings: Option<LanguageModelCallSettings>,
 }
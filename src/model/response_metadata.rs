@@ -1,3 +1,6 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelResponseMetadata {
     pub id: String,
     pub timestamp: u64,
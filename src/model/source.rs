@@ -1,13 +1,18 @@
 use crate::provider::metadata::LanguageModelProviderMetadata;
+use serde::{Deserialize, Serialize};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum LanguageModelSourceType {
     Url,
+    /// A document that was passed to the model, e.g. Cohere `documents`.
+    Document,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct LanguageModelSource {
-    source_type: LanguageModelSourceType,
-    id: String,
-    url: String,
-    title: Option<String>,
-    provider_metadata: LanguageModelProviderMetadata,
+    pub source_type: LanguageModelSourceType,
+    pub id: String,
+    pub url: Option<String>,
+    pub title: Option<String>,
+    pub provider_metadata: LanguageModelProviderMetadata,
 }
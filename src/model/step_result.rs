@@ -1,36 +1,68 @@
-use crate::{prompt::{CoreAssistantMessage, CoreToolMessage}, provider::metadata::LanguageModelProviderMetadata};
-use super::{call_warning::LanguageModelCallWarning, finish_reason::LanguageModelFinishReason, logprobs::LanguageModelLogprobs, request_metadata::LanguageModelRequestMetadata, response_metadata::LanguageModelResponseMetadata, source::LanguageModelSource, tools::Tool, usage::LanguageModelUsage};
-
+use super::{
+    call_warning::LanguageModelCallWarning, finish_reason::LanguageModelFinishReason,
+    function_tool_call::LanguageModelFunctionToolCall, logprobs::LanguageModelLogprobs,
+    pricing::CostEstimate, request_metadata::LanguageModelRequestMetadata,
+    response_metadata::LanguageModelResponseMetadata, source::LanguageModelSource,
+    usage::LanguageModelUsage, LanguageModelDoGenerateResponse,
+    LanguageModelDoGenerateResponseFilesContent, LanguageModelDoGenerateResponseReasoning,
+};
+use crate::{
+    generate_file::GenerateFile,
+    prompt::{
+        AssistantContent, AssistantContentParts, CoreAssistantMessage, CoreToolMessage,
+        ReasoningPart, RedactedReasoningPart, TextPart, ToolCallPart, ToolResultPart,
+    },
+    provider::metadata::LanguageModelProviderMetadata,
+};
 
+#[derive(Debug, Clone)]
 pub enum ResponseMessage {
     AssistantResponse(String, CoreAssistantMessage),
     ToolResponse(String, CoreToolMessage),
 }
 
-pub struct StepResultResponse{
-    model_response: LanguageModelResponseMetadata,
+#[derive(Debug, Clone)]
+pub struct StepResultResponse {
+    model_response: Option<LanguageModelResponseMetadata>,
     messages: Vec<ResponseMessage>,
-    body: String,
+    body: Option<String>,
 }
 
-pub enum StepType{
+impl StepResultResponse {
+    pub fn model_response(&self) -> Option<&LanguageModelResponseMetadata> {
+        self.model_response.as_ref()
+    }
+
+    /// The messages generated in this step, to be appended to the conversation.
+    pub fn messages(&self) -> &[ResponseMessage] {
+        &self.messages
+    }
+
+    pub fn body(&self) -> Option<&str> {
+        self.body.as_deref()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StepType {
     Initial,
     Continue,
     ToolResult,
 }
 
-pub struct StepResult{
+pub struct StepResult {
     text: String,
     // INFO: this maps to ai sdk's reasoning
-    reasoning_text: String,
+    reasoning_text: Option<String>,
     // INFO: this maps to ai sdk's reasoningDetails
-    reasoning: String,
-    files: Vec<GeneratedFile>,
+    reasoning: Vec<LanguageModelDoGenerateResponseReasoning>,
+    files: Vec<GenerateFile>,
     sources: Vec<LanguageModelSource>,
-    tool_calls: Vec<Tool>,
-    tool_results: Vec<ToolCallResult>
+    tool_calls: Vec<LanguageModelFunctionToolCall>,
+    tool_results: Vec<ToolResultPart>,
     finish_reason: LanguageModelFinishReason,
     usage: LanguageModelUsage,
+    cost: Option<CostEstimate>,
     warnings: Option<Vec<LanguageModelCallWarning>>,
     logprobs: Option<LanguageModelLogprobs>,
     request: LanguageModelRequestMetadata,
@@ -39,3 +71,173 @@ pub struct StepResult{
     step_type: StepType,
     is_continued: bool,
 }
+
+impl StepResult {
+    pub(crate) fn from_response(
+        response: LanguageModelDoGenerateResponse,
+        step_type: StepType,
+    ) -> Self {
+        let text = response.text.unwrap_or_default();
+        let reasoning_text = response
+            .reasoning
+            .iter()
+            .filter_map(|reasoning| match reasoning {
+                LanguageModelDoGenerateResponseReasoning::Text { text, .. } => Some(text.as_str()),
+                LanguageModelDoGenerateResponseReasoning::Redacted(_) => None,
+            })
+            .collect::<String>();
+
+        let mut parts: Vec<AssistantContentParts> = response
+            .reasoning
+            .iter()
+            .map(|reasoning| match reasoning {
+                LanguageModelDoGenerateResponseReasoning::Text { text, signature } => {
+                    AssistantContentParts::Reasoning(ReasoningPart {
+                        text: text.clone(),
+                        signature: signature.clone(),
+                    })
+                }
+                LanguageModelDoGenerateResponseReasoning::Redacted(data) => {
+                    AssistantContentParts::RedactedReasoning(RedactedReasoningPart {
+                        data: data.clone(),
+                    })
+                }
+            })
+            .collect();
+        if !text.is_empty() {
+            parts.push(AssistantContentParts::Text(TextPart { text: text.clone() }));
+        }
+        parts.extend(response.tool_calls.iter().map(|tool_call| {
+            AssistantContentParts::ToolCall(ToolCallPart {
+                tool_call_id: tool_call.tool_call_id.clone(),
+                tool_name: tool_call.tool_name.clone(),
+                args: tool_call.args.clone(),
+            })
+        }));
+        let message_id = response
+            .response
+            .as_ref()
+            .map(|metadata| metadata.id.clone())
+            .unwrap_or_default();
+
+        StepResult {
+            text,
+            reasoning_text: (!reasoning_text.is_empty()).then_some(reasoning_text),
+            reasoning: response.reasoning,
+            files: response
+                .files
+                .into_iter()
+                .map(|file| match file.file_content {
+                    LanguageModelDoGenerateResponseFilesContent::Base64(data) => {
+                        GenerateFile::with_base64(data, file.mime_type)
+                    }
+                    LanguageModelDoGenerateResponseFilesContent::Buffer(data) => {
+                        GenerateFile::with_buffer(data, file.mime_type)
+                    }
+                })
+                .collect(),
+            sources: response.sources,
+            tool_calls: response.tool_calls,
+            tool_results: Vec::new(),
+            finish_reason: response.finish_reason,
+            usage: response.usage,
+            cost: None,
+            warnings: (!response.warnings.is_empty()).then_some(response.warnings),
+            logprobs: response.logprobs,
+            request: response
+                .request_body
+                .unwrap_or(LanguageModelRequestMetadata {
+                    body: None,
+                    call_settings: None,
+                }),
+            response: StepResultResponse {
+                model_response: response.response,
+                messages: vec![ResponseMessage::AssistantResponse(
+                    message_id,
+                    CoreAssistantMessage {
+                        content: AssistantContent::Parts(parts),
+                    },
+                )],
+                body: None,
+            },
+            provider_metadata: response.provider_metadata,
+            step_type,
+            is_continued: false,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn reasoning_text(&self) -> Option<&str> {
+        self.reasoning_text.as_deref()
+    }
+
+    pub fn reasoning(&self) -> &[LanguageModelDoGenerateResponseReasoning] {
+        &self.reasoning
+    }
+
+    pub fn files(&self) -> &[GenerateFile] {
+        &self.files
+    }
+
+    pub fn sources(&self) -> &[LanguageModelSource] {
+        &self.sources
+    }
+
+    pub fn tool_calls(&self) -> &[LanguageModelFunctionToolCall] {
+        &self.tool_calls
+    }
+
+    pub fn tool_results(&self) -> &[ToolResultPart] {
+        &self.tool_results
+    }
+
+    pub fn finish_reason(&self) -> LanguageModelFinishReason {
+        self.finish_reason
+    }
+
+    pub fn usage(&self) -> &LanguageModelUsage {
+        &self.usage
+    }
+
+    /// The estimated cost of the step, if the model's price is known.
+    pub fn cost(&self) -> Option<CostEstimate> {
+        self.cost
+    }
+
+    pub(crate) fn with_cost(mut self, cost: Option<CostEstimate>) -> Self {
+        self.cost = cost;
+        self
+    }
+
+    pub fn warnings(&self) -> Option<&[LanguageModelCallWarning]> {
+        self.warnings.as_deref()
+    }
+
+    pub fn logprobs(&self) -> Option<&LanguageModelLogprobs> {
+        self.logprobs.as_ref()
+    }
+
+    /// The request as it was sent, including the effective call settings.
+    pub fn request(&self) -> &LanguageModelRequestMetadata {
+        &self.request
+    }
+
+    pub fn response(&self) -> &StepResultResponse {
+        &self.response
+    }
+
+    pub fn provider_metadata(&self) -> Option<&LanguageModelProviderMetadata> {
+        self.provider_metadata.as_ref()
+    }
+
+    pub fn step_type(&self) -> StepType {
+        self.step_type
+    }
+
+    pub fn is_continued(&self) -> bool {
+        self.is_continued
+    }
+}
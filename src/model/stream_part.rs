@@ -0,0 +1,45 @@
+use super::{
+    finish_reason::LanguageModelFinishReason, function_tool_call::LanguageModelFunctionToolCall,
+    logprobs::LanguageModelLogprobs, source::LanguageModelSource, usage::LanguageModelUsage,
+    LanguageModelDoGenerateResponseFiles,
+};
+use crate::provider::metadata::LanguageModelProviderMetadata;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A single event of a streamed generation.
+pub enum LanguageModelStreamPart {
+    /// A chunk of generated text.
+    TextDelta(String),
+    /// A chunk of reasoning text.
+    ReasoningDelta(String),
+    /// The signature of the reasoning that was streamed so far.
+    ReasoningSignature(String),
+    /// Reasoning that the provider returned in redacted form.
+    RedactedReasoning(String),
+    /// A chunk of the arguments of a tool call that is still being generated.
+    ToolCallDelta {
+        tool_call_id: String,
+        tool_name: String,
+        args_text_delta: String,
+    },
+    /// A complete tool call, emitted once all of its arguments were streamed.
+    ToolCall(LanguageModelFunctionToolCall),
+    Source(LanguageModelSource),
+    File(LanguageModelDoGenerateResponseFiles),
+    /// Metadata of the response, usually emitted with the first chunk.
+    ResponseMetadata {
+        id: Option<String>,
+        timestamp: Option<u64>,
+        model_id: Option<String>,
+    },
+    /// The last part of a successful stream.
+    Finish {
+        finish_reason: LanguageModelFinishReason,
+        usage: LanguageModelUsage,
+        provider_metadata: Option<LanguageModelProviderMetadata>,
+        logprobs: Option<LanguageModelLogprobs>,
+    },
+    /// An error reported by the provider in the middle of the stream.
+    Error(String),
+}
@@ -1,15 +1,45 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JSONValue;
 use std::collections::HashMap;
 
 use crate::prompt::CoreMessage;
 
+#[derive(Debug, Clone)]
 pub struct ToolExecutionOptions {
-    tool_call_id: String,
-    messages: Vec<CoreMessage>,
+    /// The id of the tool call being executed.
+    pub tool_call_id: String,
+    /// The messages sent to the model in the step that requested the call.
+    pub messages: Vec<CoreMessage>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Tool {
     pub description: Option<String>,
+    /// Runtime state of the tool; not serialized.
+    #[serde(skip)]
     pub execution_options: Option<ToolExecutionOptions>,
 }
 
 pub type ToolSet = HashMap<String, Tool>;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// A function tool as it is passed to a provider. `parameters` holds the JSON
+/// schema describing the arguments the model has to produce.
+pub struct LanguageModelFunctionTool {
+    pub name: String,
+    pub description: Option<String>,
+    pub parameters: JSONValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+/// How the model should choose which tool to call, if any.
+pub enum LanguageModelToolChoice {
+    /// The model decides whether and which tool to call.
+    Auto,
+    /// The model must not call any tool.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call the tool with the given name.
+    Tool(String),
+}
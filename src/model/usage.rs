@@ -1,6 +1,44 @@
-#[derive(Debug, Clone, PartialEq, Eq, Default)]
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Serialize, Deserialize)]
 pub struct LanguageModelUsage {
     pub prompt_tokens: u32,
     pub completion_tokens: u32,
     pub total_tokens: u32,
+    /// Prompt tokens read from the provider's prompt cache. Included in
+    /// `prompt_tokens`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cached_prompt_tokens: Option<u32>,
+    /// Completion tokens spent on reasoning. Included in `completion_tokens`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_tokens: Option<u32>,
+}
+
+impl LanguageModelUsage {
+    pub fn new(prompt_tokens: u32, completion_tokens: u32) -> Self {
+        LanguageModelUsage {
+            prompt_tokens,
+            completion_tokens,
+            total_tokens: prompt_tokens + completion_tokens,
+            ..Default::default()
+        }
+    }
+}
+
+impl std::ops::Add for &LanguageModelUsage {
+    type Output = LanguageModelUsage;
+
+    fn add(self, other: &LanguageModelUsage) -> LanguageModelUsage {
+        let add = |a: Option<u32>, b: Option<u32>| match (a, b) {
+            (None, None) => None,
+            (a, b) => Some(a.unwrap_or_default() + b.unwrap_or_default()),
+        };
+        LanguageModelUsage {
+            prompt_tokens: self.prompt_tokens + other.prompt_tokens,
+            completion_tokens: self.completion_tokens + other.completion_tokens,
+            total_tokens: self.total_tokens + other.total_tokens,
+            cached_prompt_tokens: add(self.cached_prompt_tokens, other.cached_prompt_tokens),
+            reasoning_tokens: add(self.reasoning_tokens, other.reasoning_tokens),
+        }
+    }
 }
@@ -1,46 +1,77 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct TextPart {
     pub text: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ImagePart {
+    #[serde(
+        default,
+        with = "base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub image: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub image_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FilePart {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub file_name: Option<String>,
+    #[serde(
+        default,
+        with = "base64_bytes",
+        skip_serializing_if = "Option::is_none"
+    )]
     pub file_content: Option<Vec<u8>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub file_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub mime_type: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum UserContentParts {
     Text(TextPart),
     Image(ImagePart),
     File(FilePart),
 }
 
+/// Serialized as a plain string or as a list of parts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
 pub enum UserContent {
     Text(String),
     Parts(Vec<UserContentParts>),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReasoningPart {
     pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub signature: Option<String>,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RedactedReasoningPart {
     pub data: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCallPart {
     pub tool_call_id: String,
     pub tool_name: String,
     pub args: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
 pub enum AssistantContentParts {
     Text(TextPart),
     File(FilePart),
@@ -49,14 +80,47 @@ pub enum AssistantContentParts {
     ToolCall(ToolCallPart),
 }
 
+/// Serialized as a plain string or as a list of parts.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(untagged)]
 pub enum AssistantContent {
     Text(String),
     Parts(Vec<AssistantContentParts>),
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolResultPart {
     pub tool_call_id: String,
     pub tool_name: String,
     pub result: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub is_error: Option<bool>,
 }
+
+/// Serializes binary content as a base64 string.
+mod base64_bytes {
+    use base64::{engine::general_purpose, Engine as _};
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        data: &Option<Vec<u8>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        match data {
+            Some(data) => serializer.serialize_str(&general_purpose::STANDARD.encode(data)),
+            None => serializer.serialize_none(),
+        }
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<Vec<u8>>, D::Error> {
+        Option::<String>::deserialize(deserializer)?
+            .map(|data| {
+                general_purpose::STANDARD
+                    .decode(data)
+                    .map_err(D::Error::custom)
+            })
+            .transpose()
+    }
+}
@@ -0,0 +1,147 @@
+use serde::{Deserialize, Serialize};
+
+use crate::errors::ModelError;
+
+use super::CoreMessage;
+
+/// Version of the JSON representation written by [`Conversation`].
+pub const CONVERSATION_VERSION: u32 = 1;
+
+/// A stored chat history.
+///
+/// Serialized as `{"version":1,"messages":[...]}`; binary content of images
+/// and files is base64 encoded. Reading a version other than
+/// [`CONVERSATION_VERSION`] fails.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(try_from = "VersionedConversation", into = "VersionedConversation")]
+pub struct Conversation {
+    pub messages: Vec<CoreMessage>,
+}
+
+impl Conversation {
+    pub fn new(messages: Vec<CoreMessage>) -> Self {
+        Conversation { messages }
+    }
+
+    pub fn to_json(&self) -> Result<String, ModelError> {
+        serde_json::to_string(self).map_err(|e| {
+            ModelError::InternalError(format!("Failed to serialize conversation: {e}"))
+        })
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, ModelError> {
+        serde_json::from_str(json)
+            .map_err(|e| ModelError::InvalidPrompt(format!("Invalid conversation JSON: {e}")))
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct VersionedConversation {
+    version: u32,
+    messages: Vec<CoreMessage>,
+}
+
+impl TryFrom<VersionedConversation> for Conversation {
+    type Error = String;
+
+    fn try_from(conversation: VersionedConversation) -> Result<Self, Self::Error> {
+        if conversation.version != CONVERSATION_VERSION {
+            return Err(format!(
+                "unsupported conversation version {}, expected {CONVERSATION_VERSION}",
+                conversation.version
+            ));
+        }
+        Ok(Conversation {
+            messages: conversation.messages,
+        })
+    }
+}
+
+impl From<Conversation> for VersionedConversation {
+    fn from(conversation: Conversation) -> Self {
+        VersionedConversation {
+            version: CONVERSATION_VERSION,
+            messages: conversation.messages,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::Conversation;
+    use crate::prompt::{
+        AssistantContent, AssistantContentParts, CoreAssistantMessage, CoreMessage,
+        CoreSystemMessage, CoreToolMessage, CoreUserMessage, ImagePart, TextPart, ToolCallPart,
+        ToolResultPart, UserContent, UserContentParts,
+    };
+
+    #[test]
+    fn test_conversation_json_round_trip() {
+        let conversation = Conversation::new(vec![
+            CoreMessage::System(CoreSystemMessage {
+                content: "You are a weather assistant.".to_string(),
+            }),
+            CoreMessage::User(CoreUserMessage {
+                content: UserContent::Parts(vec![
+                    UserContentParts::Text(TextPart {
+                        text: "Where was this taken?".to_string(),
+                    }),
+                    UserContentParts::Image(ImagePart {
+                        image: Some(b"GIF89a".to_vec()),
+                        image_url: None,
+                        mime_type: Some("image/gif".to_string()),
+                    }),
+                ]),
+            }),
+            CoreMessage::Assistant(CoreAssistantMessage {
+                content: AssistantContent::Parts(vec![AssistantContentParts::ToolCall(
+                    ToolCallPart {
+                        tool_call_id: "call_1".to_string(),
+                        tool_name: "weather".to_string(),
+                        args: r#"{"city":"Kathmandu"}"#.to_string(),
+                    },
+                )]),
+            }),
+            CoreMessage::Tool(CoreToolMessage {
+                content: vec![ToolResultPart {
+                    tool_call_id: "call_1".to_string(),
+                    tool_name: "weather".to_string(),
+                    result: "sunny".to_string(),
+                    is_error: None,
+                }],
+            }),
+            CoreMessage::Assistant(CoreAssistantMessage {
+                content: AssistantContent::Text("It is sunny in Kathmandu.".to_string()),
+            }),
+        ]);
+
+        let json: serde_json::Value =
+            serde_json::from_str(&conversation.to_json().unwrap()).unwrap();
+        assert_eq!(json["version"], 1);
+        assert_eq!(
+            json["messages"][1],
+            json!({
+                "role": "user",
+                "content": [
+                    { "type": "text", "text": "Where was this taken?" },
+                    { "type": "image", "image": "R0lGODlh", "mime_type": "image/gif" }
+                ]
+            })
+        );
+        assert_eq!(
+            json["messages"][4],
+            json!({ "role": "assistant", "content": "It is sunny in Kathmandu." })
+        );
+
+        let restored = Conversation::from_json(&json.to_string()).unwrap();
+        assert_eq!(
+            format!("{:?}", restored.messages),
+            format!("{:?}", conversation.messages)
+        );
+
+        let future = json!({ "version": 2, "messages": [] }).to_string();
+        assert!(Conversation::from_json(&future).is_err());
+    }
+}
@@ -0,0 +1,480 @@
+use base64::{engine::general_purpose, Engine as _};
+use serde_json::Value as JSONValue;
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    errors::ModelError,
+    model::{
+        message::{
+            LanguageModelAssistantMessage, LanguageModelFilePart, LanguageModelFilePartContent,
+            LanguageModelImagePart, LanguageModelImagePartContent, LanguageModelMessage,
+            LanguageModelReasoningPart, LanguageModelRedactedReasoningPart, LanguageModelTextPart,
+            LanguageModelToolCallPart, LanguageModelToolResultPart, LanguageModelUserMessage,
+        },
+        LanguageModel, LanguageModelDoGenerateRequestInputFormat,
+    },
+    transport::{DownloadSettings, HttpTransport},
+    utils,
+};
+
+use super::{
+    content_part::{
+        AssistantContent, AssistantContentParts, FilePart, ImagePart, UserContent, UserContentParts,
+    },
+    detect_mime_type::{detect_image_mime_type, detect_mime_type},
+    standarize_prompt::{StandardizedPrompt, StandardizedPromptKind},
+    CoreMessage,
+};
+
+/// Downloaded content of image and file URLs, with the mime type the server
+/// reported.
+pub(crate) type Downloads = HashMap<String, (Vec<u8>, Option<String>)>;
+
+/// Converts a standardized prompt into the messages that are sent to the provider.
+///
+/// Image and file URLs that the model cannot fetch itself, see
+/// [`LanguageModel::supports_urls`], are downloaded with `transport` within
+/// the limits of `download_settings`.
+pub async fn convert_to_language_model_prompt(
+    prompt: &StandardizedPrompt,
+    model: &dyn LanguageModel,
+    transport: &dyn HttpTransport,
+    download_settings: &DownloadSettings,
+) -> Result<Vec<LanguageModelMessage>, ModelError> {
+    let downloads = download_assets(prompt.messages(), model, transport, download_settings).await?;
+
+    let mut messages = Vec::with_capacity(prompt.messages().len() + 1);
+    if let Some(system) = prompt.system().filter(|system| !system.is_empty()) {
+        messages.push(LanguageModelMessage::System(system.to_string()));
+    }
+
+    for message in prompt.messages() {
+        messages.push(convert_to_language_model_message(message, &downloads)?);
+    }
+
+    Ok(messages)
+}
+
+pub fn input_format(prompt: &StandardizedPrompt) -> LanguageModelDoGenerateRequestInputFormat {
+    match prompt.kind() {
+        StandardizedPromptKind::Prompt => LanguageModelDoGenerateRequestInputFormat::Prompt,
+        StandardizedPromptKind::Messages => LanguageModelDoGenerateRequestInputFormat::Messages,
+    }
+}
+
+pub(crate) async fn download_assets(
+    messages: &[CoreMessage],
+    model: &dyn LanguageModel,
+    transport: &dyn HttpTransport,
+    download_settings: &DownloadSettings,
+) -> Result<Downloads, ModelError> {
+    let urls: HashSet<&str> = messages
+        .iter()
+        .flat_map(asset_urls)
+        .filter(|(url, is_file)| {
+            let supported = if *is_file {
+                model.supports_file_urls(url.to_string())
+            } else {
+                model.supports_urls(url.to_string())
+            };
+            split_data_url(url).is_none() && !supported
+        })
+        .map(|(url, _)| url)
+        .collect();
+    let downloads = futures::future::try_join_all(urls.into_iter().map(|url| async move {
+        Ok::<_, ModelError>((
+            url.to_string(),
+            utils::download(transport, url, download_settings).await?,
+        ))
+    }))
+    .await?;
+    Ok(downloads.into_iter().collect())
+}
+
+/// The image and file URLs of a message, and whether they are files.
+fn asset_urls(message: &CoreMessage) -> Vec<(&str, bool)> {
+    match message {
+        CoreMessage::User(message) => match &message.content {
+            UserContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    UserContentParts::Image(part) => Some((part.image_url.as_deref()?, false)),
+                    UserContentParts::File(part) => Some((part.file_url.as_deref()?, true)),
+                    UserContentParts::Text(_) => None,
+                })
+                .collect(),
+            UserContent::Text(_) => Vec::new(),
+        },
+        CoreMessage::Assistant(message) => match &message.content {
+            AssistantContent::Parts(parts) => parts
+                .iter()
+                .filter_map(|part| match part {
+                    AssistantContentParts::File(part) => Some((part.file_url.as_deref()?, true)),
+                    _ => None,
+                })
+                .collect(),
+            AssistantContent::Text(_) => Vec::new(),
+        },
+        _ => Vec::new(),
+    }
+}
+
+/// Converts a single message. URLs found in `downloads` are replaced by
+/// their content.
+pub(crate) fn convert_to_language_model_message(
+    message: &CoreMessage,
+    downloads: &Downloads,
+) -> Result<LanguageModelMessage, ModelError> {
+    match message {
+        CoreMessage::System(message) => Ok(LanguageModelMessage::System(message.content.clone())),
+        CoreMessage::User(message) => {
+            let parts = match &message.content {
+                UserContent::Text(text) => vec![LanguageModelUserMessage::Text(text_part(text))],
+                UserContent::Parts(parts) => parts
+                    .iter()
+                    .map(|part| match part {
+                        UserContentParts::Text(part) => {
+                            Ok(LanguageModelUserMessage::Text(text_part(&part.text)))
+                        }
+                        UserContentParts::Image(part) => Ok(LanguageModelUserMessage::Image(
+                            convert_image_part(part, downloads)?,
+                        )),
+                        UserContentParts::File(part) => Ok(LanguageModelUserMessage::File(
+                            convert_file_part(part, downloads)?,
+                        )),
+                    })
+                    .collect::<Result<_, ModelError>>()?,
+            };
+            Ok(LanguageModelMessage::User(parts))
+        }
+        CoreMessage::Assistant(message) => {
+            let parts = match &message.content {
+                AssistantContent::Text(text) => {
+                    vec![LanguageModelAssistantMessage::Text(text_part(text))]
+                }
+                AssistantContent::Parts(parts) => parts
+                    .iter()
+                    .map(|part| match part {
+                        AssistantContentParts::Text(part) => {
+                            Ok(LanguageModelAssistantMessage::Text(text_part(&part.text)))
+                        }
+                        AssistantContentParts::Reasoning(part) => Ok(
+                            LanguageModelAssistantMessage::Reasoning(LanguageModelReasoningPart {
+                                text: part.text.clone(),
+                                signature: part.signature.clone(),
+                                provider_metadata: None,
+                            }),
+                        ),
+                        AssistantContentParts::RedactedReasoning(part) => {
+                            Ok(LanguageModelAssistantMessage::RedactedReasoning(
+                                LanguageModelRedactedReasoningPart {
+                                    data: part.data.clone(),
+                                    provider_metadata: None,
+                                },
+                            ))
+                        }
+                        AssistantContentParts::ToolCall(part) => Ok(
+                            LanguageModelAssistantMessage::ToolCall(LanguageModelToolCallPart {
+                                tool_call_id: part.tool_call_id.clone(),
+                                tool_name: part.tool_name.clone(),
+                                args: parse_json_or_string(&part.args),
+                                provider_metadata: None,
+                            }),
+                        ),
+                        AssistantContentParts::File(part) => {
+                            Ok(LanguageModelAssistantMessage::File(convert_file_part(
+                                part, downloads,
+                            )?))
+                        }
+                    })
+                    .collect::<Result<_, ModelError>>()?,
+            };
+            Ok(LanguageModelMessage::Assistant(parts))
+        }
+        CoreMessage::Tool(message) => Ok(LanguageModelMessage::Tool(
+            message
+                .content
+                .iter()
+                .map(|part| LanguageModelToolResultPart {
+                    tool_call_id: part.tool_call_id.clone(),
+                    tool_name: part.tool_name.clone(),
+                    result: parse_json_or_string(&part.result),
+                    is_error: part.is_error,
+                    content: Vec::new(),
+                    provider_metadata: None,
+                })
+                .collect(),
+        )),
+    }
+}
+
+/// Images are passed as bytes, as a `data:` URL's base64 content, or as a URL
+/// the model fetches itself. The mime type, unless given, is detected from the
+/// content.
+fn convert_image_part(
+    part: &ImagePart,
+    downloads: &Downloads,
+) -> Result<LanguageModelImagePart, ModelError> {
+    let detect = |data: &[u8]| detect_image_mime_type(data).map(str::to_string);
+    let (image, mime_type) = match (&part.image, &part.image_url) {
+        (Some(data), _) => (
+            LanguageModelImagePartContent::Buffer(data.clone()),
+            detect(data),
+        ),
+        (None, Some(url)) => match (split_data_url(url), downloads.get(url)) {
+            (Some((mime_type, data)), _) => (
+                LanguageModelImagePartContent::Base64(data.to_string()),
+                mime_type,
+            ),
+            (None, Some((data, mime_type))) => (
+                LanguageModelImagePartContent::Buffer(data.clone()),
+                detect(data).or_else(|| mime_type.clone()),
+            ),
+            (None, None) => (LanguageModelImagePartContent::Url(url.clone()), None),
+        },
+        (None, None) => {
+            return Err(ModelError::InvalidPrompt(
+                "Image parts need either an image or an image URL".to_string(),
+            ))
+        }
+    };
+    Ok(LanguageModelImagePart {
+        image,
+        mime_type: part.mime_type.clone().or(mime_type),
+        provider_metadata: None,
+    })
+}
+
+/// Files are passed as base64 content or as a URL the model fetches itself.
+fn convert_file_part(
+    part: &FilePart,
+    downloads: &Downloads,
+) -> Result<LanguageModelFilePart, ModelError> {
+    let encode =
+        |data: &[u8]| LanguageModelFilePartContent::Base64(general_purpose::STANDARD.encode(data));
+    let detect = |data: &[u8]| detect_mime_type(data).map(str::to_string);
+    let (file_content, mime_type) = match (&part.file_content, &part.file_url) {
+        (Some(data), _) => (encode(data), detect(data)),
+        (None, Some(url)) => match (split_data_url(url), downloads.get(url)) {
+            (Some((mime_type, data)), _) => (
+                LanguageModelFilePartContent::Base64(data.to_string()),
+                mime_type,
+            ),
+            (None, Some((data, mime_type))) => {
+                (encode(data), detect(data).or_else(|| mime_type.clone()))
+            }
+            (None, None) => (LanguageModelFilePartContent::Url(url.clone()), None),
+        },
+        (None, None) => {
+            return Err(ModelError::InvalidPrompt(
+                "File parts need either file content or a file URL".to_string(),
+            ))
+        }
+    };
+    Ok(LanguageModelFilePart {
+        file_content,
+        mime_type: part.mime_type.clone().or(mime_type),
+        provider_metadata: None,
+    })
+}
+
+/// Splits a base64 `data:` URL into its mime type and content.
+fn split_data_url(url: &str) -> Option<(Option<String>, &str)> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    Some(((!mime_type.is_empty()).then(|| mime_type.to_string()), data))
+}
+
+fn text_part(text: &str) -> LanguageModelTextPart {
+    LanguageModelTextPart {
+        text: text.to_string(),
+        provider_metadata: None,
+    }
+}
+
+/// Tool call arguments and results are stored as strings; providers expect
+/// JSON values, so valid JSON is parsed and anything else is kept as a string.
+pub(crate) fn parse_json_or_string(value: &str) -> JSONValue {
+    serde_json::from_str(value).unwrap_or_else(|_| JSONValue::String(value.to_string()))
+}
+
+#[cfg(test)]
+mod test {
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::convert_to_language_model_prompt;
+    use crate::{
+        errors::ModelError,
+        model::message::{
+            LanguageModelFilePartContent, LanguageModelImagePartContent, LanguageModelMessage,
+            LanguageModelUserMessage,
+        },
+        prompt::{
+            standarize_prompt::StandardizedPrompt, CoreMessage, CoreUserMessage, FilePart,
+            ImagePart, Prompt, TextPart, UserContent, UserContentParts,
+        },
+        testing::MockLanguageModel,
+        transport::{default_transport, DownloadSettings},
+        utils::download,
+    };
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n\x00\x00\x00\rIHDR";
+
+    fn image_url(url: &str) -> UserContentParts {
+        UserContentParts::Image(ImagePart {
+            image: None,
+            image_url: Some(url.to_string()),
+            mime_type: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_convert_user_parts_and_download_urls() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/cat"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "application/octet-stream")
+                    .set_body_bytes(PNG),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let prompt = StandardizedPrompt::try_from(Prompt {
+            system: "Describe images.".to_string(),
+            prompt: None,
+            messages: Some(vec![CoreMessage::User(CoreUserMessage {
+                content: UserContent::Parts(vec![
+                    UserContentParts::Text(TextPart {
+                        text: "What is this?".to_string(),
+                    }),
+                    image_url(&format!("{}/cat", server.uri())),
+                    image_url("data:image/gif;base64,R0lGODlh"),
+                    UserContentParts::File(FilePart {
+                        file_name: Some("report.pdf".to_string()),
+                        file_content: Some(b"%PDF-1.7".to_vec()),
+                        file_url: None,
+                        mime_type: None,
+                    }),
+                ]),
+            })]),
+            ui_messages: None,
+        })
+        .unwrap();
+
+        let model = MockLanguageModel::new().with_supports_urls(false);
+        let messages = convert_to_language_model_prompt(
+            &prompt,
+            &model,
+            &*default_transport(),
+            &DownloadSettings::default(),
+        )
+        .await
+        .unwrap();
+        assert!(
+            matches!(&messages[0], LanguageModelMessage::System(system) if system == "Describe images.")
+        );
+        let LanguageModelMessage::User(parts) = &messages[1] else {
+            panic!("expected a user message");
+        };
+        assert!(
+            matches!(&parts[0], LanguageModelUserMessage::Text(part) if part.text == "What is this?")
+        );
+        assert!(matches!(
+            &parts[1],
+            LanguageModelUserMessage::Image(part)
+                if matches!(&part.image, LanguageModelImagePartContent::Buffer(data) if data == PNG)
+                    && part.mime_type.as_deref() == Some("image/png")
+        ));
+        assert!(matches!(
+            &parts[2],
+            LanguageModelUserMessage::Image(part)
+                if matches!(&part.image, LanguageModelImagePartContent::Base64(data) if data == "R0lGODlh")
+                    && part.mime_type.as_deref() == Some("image/gif")
+        ));
+        assert!(matches!(
+            &parts[3],
+            LanguageModelUserMessage::File(part)
+                if matches!(&part.file_content, LanguageModelFilePartContent::Base64(data) if data == "JVBERi0xLjc=")
+                    && part.mime_type.as_deref() == Some("application/pdf")
+        ));
+
+        // Models that fetch URLs themselves get the URL.
+        let model = MockLanguageModel::new();
+        let messages = convert_to_language_model_prompt(
+            &prompt,
+            &model,
+            &*default_transport(),
+            &DownloadSettings::default(),
+        )
+        .await
+        .unwrap();
+        let LanguageModelMessage::User(parts) = &messages[1] else {
+            panic!("expected a user message");
+        };
+        assert!(matches!(
+            &parts[1],
+            LanguageModelUserMessage::Image(part)
+                if matches!(&part.image, LanguageModelImagePartContent::Url(_)) && part.mime_type.is_none()
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_download_limits() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/cat"))
+            .respond_with(ResponseTemplate::new(200).set_body_bytes(PNG))
+            .mount(&server)
+            .await;
+        let transport = default_transport();
+        let url = format!("{}/cat", server.uri());
+        let rejected = |result: Result<_, ModelError>, reason: &str| matches!(result, Err(ModelError::Download { message, .. }) if message.contains(reason));
+
+        let (body, _) = download(&*transport, &url, &DownloadSettings::new())
+            .await
+            .unwrap();
+        assert_eq!(body, PNG);
+        assert!(rejected(
+            download(
+                &*transport,
+                &url,
+                &DownloadSettings::new().with_max_bytes(8)
+            )
+            .await,
+            "larger than 8 bytes"
+        ));
+        assert!(rejected(
+            download(&*transport, "file:///etc/passwd", &DownloadSettings::new()).await,
+            "only http and https"
+        ));
+        let settings = DownloadSettings::new().with_url_filter(|url| !url.contains("127.0.0.1"));
+        assert!(rejected(
+            download(&*transport, &url, &settings).await,
+            "URL filter"
+        ));
+
+        // Redirects are followed, and every hop passes the URL filter.
+        for (from, to) in [("/moved", "/cat"), ("/private-redirect", "/private")] {
+            Mock::given(method("GET"))
+                .and(path(from))
+                .respond_with(ResponseTemplate::new(302).insert_header("location", to))
+                .mount(&server)
+                .await;
+        }
+        let settings = DownloadSettings::new().with_url_filter(|url| !url.ends_with("/private"));
+        let moved = format!("{}/moved", server.uri());
+        let (body, _) = download(&*transport, &moved, &settings).await.unwrap();
+        assert_eq!(body, PNG);
+        let private = format!("{}/private-redirect", server.uri());
+        assert!(rejected(
+            download(&*transport, &private, &settings).await,
+            "/private rejected by the URL filter"
+        ));
+    }
+}
@@ -0,0 +1,56 @@
+//! Detects the mime type of images, audio and documents from their leading bytes.
+
+const IMAGE_SIGNATURES: &[(&str, &[u8])] = &[
+    ("image/gif", b"GIF8"),
+    ("image/png", b"\x89PNG\r\n\x1a\n"),
+    ("image/jpeg", b"\xff\xd8\xff"),
+    ("image/bmp", b"BM"),
+    ("image/tiff", b"II*\x00"),
+    ("image/tiff", b"MM\x00*"),
+];
+
+const AUDIO_SIGNATURES: &[(&str, &[u8])] = &[
+    ("audio/mpeg", b"ID3"),
+    ("audio/mpeg", b"\xff\xfb"),
+    ("audio/mpeg", b"\xff\xf3"),
+    ("audio/mpeg", b"\xff\xf2"),
+    ("audio/ogg", b"OggS"),
+    ("audio/flac", b"fLaC"),
+];
+
+/// Detects the mime type of an image, `None` if the format is not recognized.
+pub fn detect_image_mime_type(data: &[u8]) -> Option<&'static str> {
+    if riff_format(data) == Some(b"WEBP") {
+        return Some("image/webp");
+    }
+    // ISO base media files: `ftyp` box followed by the major brand.
+    match data.get(4..12) {
+        Some(b"ftypavif") => return Some("image/avif"),
+        Some(b"ftypheic" | b"ftypheix" | b"ftypmif1") => return Some("image/heic"),
+        _ => {}
+    }
+    find_signature(IMAGE_SIGNATURES, data)
+}
+
+/// Detects the mime type of an image, audio file or PDF document.
+pub fn detect_mime_type(data: &[u8]) -> Option<&'static str> {
+    if data.starts_with(b"%PDF") {
+        return Some("application/pdf");
+    }
+    if riff_format(data) == Some(b"WAVE") {
+        return Some("audio/wav");
+    }
+    detect_image_mime_type(data).or_else(|| find_signature(AUDIO_SIGNATURES, data))
+}
+
+/// The format of a RIFF container, e.g. `WEBP` or `WAVE`.
+fn riff_format(data: &[u8]) -> Option<&[u8]> {
+    data.starts_with(b"RIFF").then(|| data.get(8..12)).flatten()
+}
+
+fn find_signature(signatures: &[(&'static str, &[u8])], data: &[u8]) -> Option<&'static str> {
+    signatures
+        .iter()
+        .find(|(_, signature)| data.starts_with(signature))
+        .map(|(mime_type, _)| *mime_type)
+}
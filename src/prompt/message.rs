@@ -1,21 +1,30 @@
+use serde::{Deserialize, Serialize};
+
 use super::content_part::{AssistantContent, ToolResultPart, UserContent};
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreSystemMessage {
     pub content: String,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreUserMessage {
     pub content: UserContent,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreAssistantMessage {
     pub content: AssistantContent,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CoreToolMessage {
     pub content: Vec<ToolResultPart>,
 }
 
+/// Serialized with a `role` tag, e.g. `{"role":"user","content":"Hi"}`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "role", rename_all = "snake_case")]
 pub enum CoreMessage {
     System(CoreSystemMessage),
     User(CoreUserMessage),
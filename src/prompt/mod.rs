@@ -1,21 +1,32 @@
 mod content_part;
+mod conversation;
+pub mod convert_to_language_model_prompt;
+pub mod detect_mime_type;
 mod message;
-mod retry_policy;
 pub mod standarize_prompt;
+mod ui_message;
 
+pub use content_part::*;
+pub use conversation::{Conversation, CONVERSATION_VERSION};
 pub use message::*;
-pub use retry_policy::RetryPolicy;
+pub use ui_message::*;
 
 /// Prompt part of the AI function options.
-/// It contains a system message, a simple text prompt, or a list of messages.
+/// It contains a system message and either a simple text prompt, a list of
+/// messages or a list of UI messages.
 #[derive(Default)]
 pub struct Prompt {
-    /// System message to include in the prompt. Can be used with `prompt` or `messages`.
+    /// System message to include in the prompt. Can be used with any of the
+    /// other fields.
     pub system: String,
 
-    /// A simple text prompt. You can either use `prompt` or `messages` but not both.
+    /// A simple text prompt.
     pub prompt: Option<String>,
 
-    /// A list of messages. You can either use `prompt` or `messages` but not both.
+    /// A list of messages.
     pub messages: Option<Vec<CoreMessage>>,
+
+    /// Messages as received from a chat UI, converted with
+    /// [`convert_to_core_messages`].
+    pub ui_messages: Option<Vec<UIMessage>>,
 }
@@ -1,8 +1,8 @@
 use crate::errors::ModelError;
 
 use super::{
-    content_part::{AssistantContent, UserContent},
-    CoreMessage, CoreUserMessage, Prompt,
+    content_part::{AssistantContent, AssistantContentParts, UserContent, UserContentParts},
+    convert_to_core_messages, CoreMessage, CoreUserMessage, Prompt,
 };
 
 pub enum StandardizedPromptKind {
@@ -16,106 +16,378 @@ pub struct StandardizedPrompt {
     messages: Vec<CoreMessage>,
 }
 
+impl StandardizedPrompt {
+    pub fn kind(&self) -> &StandardizedPromptKind {
+        &self.kind
+    }
+
+    pub fn system(&self) -> Option<&str> {
+        self.system.as_deref()
+    }
+
+    pub fn messages(&self) -> &[CoreMessage] {
+        &self.messages
+    }
+
+    pub(crate) fn with_messages(self, messages: Vec<CoreMessage>) -> Self {
+        StandardizedPrompt { messages, ..self }
+    }
+}
+
 impl TryFrom<Prompt> for StandardizedPrompt {
     type Error = ModelError;
     fn try_from(prompt: Prompt) -> Result<Self, Self::Error> {
-        if prompt.prompt.is_none() && prompt.messages.is_none() {
-            return Err(ModelError::InvalidPrompt(
-                "Prompt must contain either a prompt or messages".to_string(),
-            ));
-        }
+        let messages = match (prompt.prompt, prompt.messages, prompt.ui_messages) {
+            (Some(text), None, None) => {
+                return Ok(StandardizedPrompt {
+                    kind: StandardizedPromptKind::Prompt,
+                    system: Some(prompt.system),
+                    messages: vec![CoreMessage::User(CoreUserMessage {
+                        content: UserContent::Text(text),
+                    })],
+                });
+            }
+            (None, Some(messages), None) => messages,
+            (None, None, Some(ui_messages)) => convert_to_core_messages(&ui_messages)?,
+            (None, None, None) => {
+                return Err(ModelError::InvalidPrompt(
+                    "Prompt must contain either a prompt or messages".to_string(),
+                ));
+            }
+            _ => {
+                return Err(ModelError::InvalidPrompt(
+                    "Prompt can only contain one of a prompt, messages and UI messages".to_string(),
+                ));
+            }
+        };
 
-        if prompt.prompt.is_some() && prompt.messages.is_some() {
+        if messages.is_empty() {
             return Err(ModelError::InvalidPrompt(
-                "Prompt cannot contain both a prompt and messages".to_string(),
+                "Messages cannot be empty".to_string(),
             ));
         }
+        validate_messages(&messages)?;
 
-        if prompt.prompt.is_some() {
-            return Ok(StandardizedPrompt {
-                kind: StandardizedPromptKind::Prompt,
-                system: Some(prompt.system),
-                messages: vec![CoreMessage::User(CoreUserMessage {
-                    content: UserContent::Text(prompt.prompt.unwrap()),
-                })],
-            });
-        }
+        Ok(StandardizedPrompt {
+            kind: StandardizedPromptKind::Messages,
+            system: Some(prompt.system),
+            messages,
+        })
+    }
+}
 
-        if prompt.messages.is_some() {
-            let messages = prompt.messages.unwrap();
+/// Checks the structure that providers reject with less helpful errors:
+/// empty content, parts without data, tool results that do not answer the
+/// tool calls of the preceding assistant message, and roles out of order.
+fn validate_messages(messages: &[CoreMessage]) -> Result<(), ModelError> {
+    let invalid = |index: usize, reason: String| {
+        ModelError::InvalidPrompt(format!("Message {index}: {reason}"))
+    };
 
-            //TODO: not sure what UI messages are, but they are not supported yet
-            // let promptType = detect_prompt_type(messages)
+    // System messages come first, followed by a conversation that starts
+    // with a user message and never has two assistant messages in a row.
+    let mut conversation_started = false;
+    let mut previous_was_assistant = false;
+    for (index, message) in messages.iter().enumerate() {
+        match message {
+            CoreMessage::System(_) if conversation_started => {
+                return Err(invalid(
+                    index,
+                    "system messages must come before the conversation".to_string(),
+                ));
+            }
+            CoreMessage::System(_) => {}
+            CoreMessage::Assistant(_) | CoreMessage::Tool(_) if !conversation_started => {
+                return Err(invalid(
+                    index,
+                    "the conversation must start with a user message".to_string(),
+                ));
+            }
+            CoreMessage::Assistant(_) if previous_was_assistant => {
+                return Err(invalid(
+                    index,
+                    "assistant message follows another assistant message".to_string(),
+                ));
+            }
+            _ => conversation_started = true,
+        }
+        previous_was_assistant = matches!(message, CoreMessage::Assistant(_));
+    }
 
-            if messages.is_empty() {
-                return Err(ModelError::InvalidPrompt(
-                    "Messages cannot be empty".to_string(),
+    // Tool calls of the last assistant message that have no result yet.
+    let mut pending_calls: Vec<&str> = Vec::new();
+    let mut calls_index = 0;
+    let mut accepts_results = false;
+    for (index, message) in messages.iter().enumerate() {
+        if let Some(call) = pending_calls.first() {
+            if !matches!(message, CoreMessage::Tool(_)) {
+                return Err(invalid(
+                    calls_index,
+                    format!("tool call `{call}` has no result"),
                 ));
             }
+        }
 
-            return Ok(StandardizedPrompt {
-                kind: StandardizedPromptKind::Messages,
-                system: Some(prompt.system),
-                messages,
-            });
+        match message {
+            CoreMessage::System(message) => {
+                if message.content.trim().is_empty() {
+                    return Err(invalid(index, "system message is empty".to_string()));
+                }
+            }
+            CoreMessage::User(message) => match &message.content {
+                UserContent::Text(text) if text.trim().is_empty() => {
+                    return Err(invalid(index, "user message is empty".to_string()));
+                }
+                UserContent::Text(_) => {}
+                UserContent::Parts(parts) => {
+                    if parts.is_empty() {
+                        return Err(invalid(index, "user message has no parts".to_string()));
+                    }
+                    for (part_index, part) in parts.iter().enumerate() {
+                        let missing = match part {
+                            UserContentParts::Text(_) => false,
+                            UserContentParts::Image(part) => {
+                                part.image.is_none() && part.image_url.is_none()
+                            }
+                            UserContentParts::File(part) => {
+                                part.file_content.is_none() && part.file_url.is_none()
+                            }
+                        };
+                        if missing {
+                            return Err(invalid(
+                                index,
+                                format!("part {part_index} has neither content nor a URL"),
+                            ));
+                        }
+                    }
+                }
+            },
+            CoreMessage::Assistant(message) => {
+                match &message.content {
+                    AssistantContent::Text(text) if text.trim().is_empty() => {
+                        return Err(invalid(index, "assistant message is empty".to_string()));
+                    }
+                    AssistantContent::Text(_) => {}
+                    AssistantContent::Parts(parts) => {
+                        if parts.is_empty() {
+                            return Err(invalid(
+                                index,
+                                "assistant message has no parts".to_string(),
+                            ));
+                        }
+                        for (part_index, part) in parts.iter().enumerate() {
+                            match part {
+                                AssistantContentParts::File(part)
+                                    if part.file_content.is_none() && part.file_url.is_none() =>
+                                {
+                                    return Err(invalid(
+                                        index,
+                                        format!("part {part_index} has neither content nor a URL"),
+                                    ));
+                                }
+                                AssistantContentParts::ToolCall(call) => {
+                                    if call.tool_call_id.is_empty() {
+                                        return Err(invalid(
+                                            index,
+                                            format!("tool call in part {part_index} has no id"),
+                                        ));
+                                    }
+                                    if pending_calls.contains(&call.tool_call_id.as_str()) {
+                                        return Err(invalid(
+                                            index,
+                                            format!(
+                                                "duplicate tool call id `{}`",
+                                                call.tool_call_id
+                                            ),
+                                        ));
+                                    }
+                                    pending_calls.push(&call.tool_call_id);
+                                }
+                                _ => {}
+                            }
+                        }
+                    }
+                }
+                calls_index = index;
+                accepts_results = !pending_calls.is_empty();
+            }
+            CoreMessage::Tool(message) => {
+                if message.content.is_empty() {
+                    return Err(invalid(index, "tool message has no results".to_string()));
+                }
+                if !accepts_results {
+                    return Err(invalid(
+                        index,
+                        "tool results must follow an assistant message with tool calls".to_string(),
+                    ));
+                }
+                for result in &message.content {
+                    let Some(position) = pending_calls
+                        .iter()
+                        .position(|call| *call == result.tool_call_id)
+                    else {
+                        return Err(invalid(
+                            index,
+                            format!(
+                                "tool result `{}` has no matching tool call",
+                                result.tool_call_id
+                            ),
+                        ));
+                    };
+                    pending_calls.remove(position);
+                }
+            }
+        }
+        if matches!(message, CoreMessage::System(_) | CoreMessage::User(_)) {
+            accepts_results = false;
         }
+    }
 
-        Err(ModelError::Other(
-            "Prompt must contain either a prompt or messages".to_string(),
-        ))
+    match pending_calls.first() {
+        Some(call) => Err(invalid(
+            calls_index,
+            format!("tool call `{call}` has no result"),
+        )),
+        None => Ok(()),
     }
 }
 
-enum PromptType {
-    UIMessages,
-    Messages,
-    Other,
-}
+#[cfg(test)]
+mod test {
+    use super::StandardizedPrompt;
+    use crate::{
+        errors::ModelError,
+        prompt::{
+            AssistantContent, AssistantContentParts, CoreAssistantMessage, CoreMessage,
+            CoreSystemMessage, CoreToolMessage, CoreUserMessage, Prompt, ToolCallPart,
+            ToolResultPart, UserContent,
+        },
+    };
 
-fn detect_prompt_type(messages: &[CoreMessage]) -> PromptType {
-    if messages.is_empty() {
-        return PromptType::Messages;
+    fn user(text: &str) -> CoreMessage {
+        CoreMessage::User(CoreUserMessage {
+            content: UserContent::Text(text.to_string()),
+        })
     }
 
-    let characterstics: Vec<PromptCharacteristics> = messages
-        .iter()
-        .map(detect_single_message_characteristics)
-        .collect();
+    fn assistant(text: &str) -> CoreMessage {
+        CoreMessage::Assistant(CoreAssistantMessage {
+            content: AssistantContent::Text(text.to_string()),
+        })
+    }
 
-    if characterstics
-        .iter()
-        .any(|c| *c == PromptCharacteristics::HasUISpecificParts)
-    {
-        return PromptType::UIMessages;
+    fn system(text: &str) -> CoreMessage {
+        CoreMessage::System(CoreSystemMessage {
+            content: text.to_string(),
+        })
     }
 
-    PromptType::Messages
-}
+    fn tool_call(id: &str) -> CoreMessage {
+        CoreMessage::Assistant(CoreAssistantMessage {
+            content: AssistantContent::Parts(vec![AssistantContentParts::ToolCall(ToolCallPart {
+                tool_call_id: id.to_string(),
+                tool_name: "weather".to_string(),
+                args: "{}".to_string(),
+            })]),
+        })
+    }
 
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-enum PromptCharacteristics {
-    HasUISpecificParts,
-    HasCoreSpecificParts,
-    Message,
-}
+    fn tool_result(id: &str) -> CoreMessage {
+        CoreMessage::Tool(CoreToolMessage {
+            content: vec![ToolResultPart {
+                tool_call_id: id.to_string(),
+                tool_name: "weather".to_string(),
+                result: "sunny".to_string(),
+                is_error: None,
+            }],
+        })
+    }
 
-fn detect_single_message_characteristics(message: &CoreMessage) -> PromptCharacteristics {
-    match message {
-        CoreMessage::System(_) => PromptCharacteristics::Message,
-        CoreMessage::User(user_message) => match user_message.content {
-            UserContent::Text(_) => PromptCharacteristics::Message,
-            UserContent::Parts(_) => PromptCharacteristics::HasCoreSpecificParts,
-        },
-        CoreMessage::Assistant(assistant_message) => match assistant_message.content {
-            AssistantContent::Text(_) => PromptCharacteristics::Message,
-            AssistantContent::Parts(_) => PromptCharacteristics::HasCoreSpecificParts,
-        },
-        CoreMessage::Tool(tool_message) => {
-            if tool_message.content.is_empty() {
-                PromptCharacteristics::Message
-            } else {
-                PromptCharacteristics::HasCoreSpecificParts
-            }
+    fn validate(messages: Vec<CoreMessage>) -> Result<(), String> {
+        let prompt = Prompt {
+            messages: Some(messages),
+            ..Default::default()
+        };
+        match StandardizedPrompt::try_from(prompt) {
+            Ok(_) => Ok(()),
+            Err(ModelError::InvalidPrompt(message)) => Err(message),
+            Err(error) => panic!("unexpected error: {error}"),
         }
     }
+
+    #[test]
+    fn test_validate_messages() {
+        assert_eq!(
+            validate(vec![
+                user("Weather?"),
+                tool_call("call_1"),
+                tool_result("call_1")
+            ]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec![user("Weather?"), tool_result("call_1")]),
+            Err("Message 1: tool results must follow an assistant message with tool calls".into())
+        );
+        assert_eq!(
+            validate(vec![
+                user("Weather?"),
+                tool_call("call_1"),
+                tool_result("call_2")
+            ]),
+            Err("Message 2: tool result `call_2` has no matching tool call".into())
+        );
+        assert_eq!(
+            validate(vec![user("Weather?"), tool_call("call_1"), user("Hello?")]),
+            Err("Message 1: tool call `call_1` has no result".into())
+        );
+        assert_eq!(
+            validate(vec![user("Weather?"), tool_call("call_1")]),
+            Err("Message 1: tool call `call_1` has no result".into())
+        );
+        assert_eq!(
+            validate(vec![user(" ")]),
+            Err("Message 0: user message is empty".into())
+        );
+        assert_eq!(
+            validate(vec![user("Weather?"), assistant(" \n")]),
+            Err("Message 1: assistant message is empty".into())
+        );
+    }
+
+    #[test]
+    fn test_validate_role_order() {
+        assert_eq!(
+            validate(vec![
+                system("Be brief."),
+                user("Weather?"),
+                assistant("Sunny.")
+            ]),
+            Ok(())
+        );
+        assert_eq!(
+            validate(vec![user("Weather?"), system("Be brief.")]),
+            Err("Message 1: system messages must come before the conversation".into())
+        );
+        assert_eq!(
+            validate(vec![
+                system("Be brief."),
+                assistant("Hello!"),
+                user("Weather?")
+            ]),
+            Err("Message 1: the conversation must start with a user message".into())
+        );
+        assert_eq!(
+            validate(vec![tool_result("call_1"), user("Weather?")]),
+            Err("Message 0: the conversation must start with a user message".into())
+        );
+        assert_eq!(
+            validate(vec![
+                user("Weather?"),
+                assistant("Sunny."),
+                assistant("Warm, too.")
+            ]),
+            Err("Message 2: assistant message follows another assistant message".into())
+        );
+    }
 }
@@ -0,0 +1,580 @@
+//! Messages as they are shown in a chat UI.
+//!
+//! Unlike [`CoreMessage`]s they keep everything the user saw, e.g. sources,
+//! tool calls in progress and custom data, and serialize to the
+//! camelCase JSON used by chat frontends.
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JSONValue};
+
+use crate::{
+    core::generate_text::GenerateTextResult,
+    errors::ModelError,
+    model::{
+        source::{LanguageModelSource, LanguageModelSourceType},
+        LanguageModelDoGenerateResponseReasoning,
+    },
+};
+
+use super::{
+    convert_to_language_model_prompt::parse_json_or_string, AssistantContent,
+    AssistantContentParts, CoreAssistantMessage, CoreMessage, CoreSystemMessage, CoreToolMessage,
+    CoreUserMessage, FilePart, ImagePart, ReasoningPart, TextPart, ToolCallPart, ToolResultPart,
+    UserContent, UserContentParts,
+};
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UIMessage {
+    pub id: String,
+    pub role: UIMessageRole,
+    #[serde(with = "ui_message_parts")]
+    pub parts: Vec<UIMessagePart>,
+    /// Application specific data, e.g. timestamps.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub metadata: Option<JSONValue>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UIMessageRole {
+    System,
+    User,
+    Assistant,
+}
+
+/// A part of a [`UIMessage`], in the shape of the AI SDK 5 UI message
+/// protocol.
+///
+/// https://ai-sdk.dev/docs/reference/ai-sdk-core/ui-message#uimessagepart-types
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum UIMessagePart {
+    Text {
+        text: String,
+    },
+    Reasoning {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    /// A call of a tool, sent with the type `tool-{tool_name}`.
+    #[serde(skip)]
+    Tool(UIToolPart),
+    /// A file or image, `url` is either a remote or a `data:` URL.
+    File {
+        media_type: String,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+    },
+    SourceUrl {
+        source_id: String,
+        url: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        title: Option<String>,
+    },
+    SourceDocument {
+        source_id: String,
+        media_type: String,
+        title: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        filename: Option<String>,
+    },
+    /// Marks the start of a step of a multi-step generation.
+    StepStart,
+    /// Custom data for the UI, sent with the type `data-{name}`. Ignored when
+    /// converting to [`CoreMessage`]s.
+    #[serde(skip)]
+    Data {
+        name: String,
+        id: Option<String>,
+        data: JSONValue,
+    },
+}
+
+impl UIMessagePart {
+    pub fn to_json(&self) -> JSONValue {
+        match self {
+            UIMessagePart::Tool(part) => {
+                let mut json = serde_json::to_value(part).expect("tool parts serialize to JSON");
+                json["type"] = json!(format!("tool-{}", part.tool_name));
+                json
+            }
+            UIMessagePart::Data { name, id, data } => {
+                let mut json = json!({ "type": format!("data-{name}"), "data": data });
+                if let Some(id) = id {
+                    json["id"] = json!(id);
+                }
+                json
+            }
+            part => serde_json::to_value(part).expect("UI message parts serialize to JSON"),
+        }
+    }
+
+    pub fn from_json(json: JSONValue) -> Result<Self, serde_json::Error> {
+        let kind = json["type"].as_str().unwrap_or_default();
+        if let Some(tool_name) = kind.strip_prefix("tool-") {
+            let tool_name = tool_name.to_string();
+            let part = serde_json::from_value(json)?;
+            return Ok(UIMessagePart::Tool(UIToolPart { tool_name, ..part }));
+        }
+        if let Some(name) = kind.strip_prefix("data-") {
+            return Ok(UIMessagePart::Data {
+                name: name.to_string(),
+                id: json["id"].as_str().map(str::to_string),
+                data: json["data"].clone(),
+            });
+        }
+        serde_json::from_value(json)
+    }
+}
+
+/// Serializes parts with [`UIMessagePart::to_json`], which the derived
+/// implementation cannot do for the tool and data parts.
+mod ui_message_parts {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+    use serde_json::Value as JSONValue;
+
+    use super::UIMessagePart;
+
+    pub fn serialize<S: Serializer>(
+        parts: &[UIMessagePart],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(parts.iter().map(UIMessagePart::to_json))
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<UIMessagePart>, D::Error> {
+        Vec::<JSONValue>::deserialize(deserializer)?
+            .into_iter()
+            .map(|json| UIMessagePart::from_json(json).map_err(D::Error::custom))
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UIToolPart {
+    /// Part of the `type` of the serialized part.
+    #[serde(skip)]
+    pub tool_name: String,
+    pub tool_call_id: String,
+    #[serde(flatten)]
+    pub state: UIToolPartState,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(
+    tag = "state",
+    rename_all = "kebab-case",
+    rename_all_fields = "camelCase"
+)]
+pub enum UIToolPartState {
+    /// The input is still being streamed and may be incomplete.
+    InputStreaming {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        input: Option<JSONValue>,
+    },
+    /// The call is complete and waiting for its output.
+    InputAvailable {
+        input: JSONValue,
+    },
+    OutputAvailable {
+        input: JSONValue,
+        output: JSONValue,
+    },
+    OutputError {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        input: Option<JSONValue>,
+        error_text: String,
+    },
+}
+
+/// Converts UI messages into the messages sent to the model.
+///
+/// Every step of an assistant message becomes an assistant message followed
+/// by a tool message with the outputs of its tool parts. Sources and data
+/// parts are dropped. Tool parts without an output are an error.
+pub fn convert_to_core_messages(messages: &[UIMessage]) -> Result<Vec<CoreMessage>, ModelError> {
+    let mut core_messages = Vec::new();
+    for (index, message) in messages.iter().enumerate() {
+        match message.role {
+            UIMessageRole::System => {
+                core_messages.push(CoreMessage::System(CoreSystemMessage {
+                    content: text_of(&message.parts),
+                }));
+            }
+            UIMessageRole::User => {
+                let parts: Vec<UserContentParts> = message
+                    .parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        UIMessagePart::Text { text } => {
+                            Some(UserContentParts::Text(TextPart { text: text.clone() }))
+                        }
+                        UIMessagePart::File {
+                            media_type,
+                            url,
+                            filename,
+                        } => Some(file_part(media_type, url, filename)),
+                        _ => None,
+                    })
+                    .collect();
+                let content = match parts.as_slice() {
+                    [UserContentParts::Text(part)] => UserContent::Text(part.text.clone()),
+                    _ => UserContent::Parts(parts),
+                };
+                core_messages.push(CoreMessage::User(CoreUserMessage { content }));
+            }
+            UIMessageRole::Assistant => {
+                for step in message
+                    .parts
+                    .split(|part| matches!(part, UIMessagePart::StepStart))
+                {
+                    convert_assistant_step(index, step, &mut core_messages)?;
+                }
+            }
+        }
+    }
+    Ok(core_messages)
+}
+
+fn convert_assistant_step(
+    index: usize,
+    parts: &[UIMessagePart],
+    core_messages: &mut Vec<CoreMessage>,
+) -> Result<(), ModelError> {
+    let mut content = Vec::new();
+    let mut results = Vec::new();
+    for part in parts {
+        match part {
+            UIMessagePart::Text { text } => {
+                content.push(AssistantContentParts::Text(TextPart { text: text.clone() }));
+            }
+            UIMessagePart::Reasoning { text, signature } => {
+                content.push(AssistantContentParts::Reasoning(ReasoningPart {
+                    text: text.clone(),
+                    signature: signature.clone(),
+                }));
+            }
+            UIMessagePart::File {
+                media_type,
+                url,
+                filename,
+            } => {
+                content.push(AssistantContentParts::File(FilePart {
+                    file_name: filename.clone(),
+                    file_content: None,
+                    file_url: Some(url.clone()),
+                    mime_type: Some(media_type.clone()),
+                }));
+            }
+            UIMessagePart::Tool(part) => {
+                let (input, result, is_error) = match &part.state {
+                    UIToolPartState::OutputAvailable { input, output } => {
+                        (input.clone(), json_to_string(output), None)
+                    }
+                    UIToolPartState::OutputError { input, error_text } => (
+                        input.clone().unwrap_or_else(|| json!({})),
+                        error_text.clone(),
+                        Some(true),
+                    ),
+                    UIToolPartState::InputStreaming { .. }
+                    | UIToolPartState::InputAvailable { .. } => {
+                        return Err(ModelError::InvalidPrompt(format!(
+                            "Message {index}: tool call `{}` has no output",
+                            part.tool_call_id
+                        )));
+                    }
+                };
+                content.push(AssistantContentParts::ToolCall(ToolCallPart {
+                    tool_call_id: part.tool_call_id.clone(),
+                    tool_name: part.tool_name.clone(),
+                    args: json_to_string(&input),
+                }));
+                results.push(ToolResultPart {
+                    tool_call_id: part.tool_call_id.clone(),
+                    tool_name: part.tool_name.clone(),
+                    result,
+                    is_error,
+                });
+            }
+            UIMessagePart::SourceUrl { .. }
+            | UIMessagePart::SourceDocument { .. }
+            | UIMessagePart::StepStart
+            | UIMessagePart::Data { .. } => {}
+        }
+    }
+
+    // Steps without tool results in between, e.g. of consecutive assistant
+    // messages, are a single assistant turn.
+    match core_messages.last_mut() {
+        Some(CoreMessage::Assistant(CoreAssistantMessage {
+            content: AssistantContent::Parts(parts),
+        })) => parts.extend(content),
+        _ if !content.is_empty() => {
+            core_messages.push(CoreMessage::Assistant(CoreAssistantMessage {
+                content: AssistantContent::Parts(content),
+            }))
+        }
+        _ => {}
+    }
+    if !results.is_empty() {
+        core_messages.push(CoreMessage::Tool(CoreToolMessage { content: results }));
+    }
+    Ok(())
+}
+
+/// Builds the assistant UI message shown for a generate result, with a step
+/// start before every step.
+pub fn convert_to_ui_message(id: impl Into<String>, result: &GenerateTextResult) -> UIMessage {
+    let mut parts = Vec::new();
+    for step in result.steps() {
+        parts.push(UIMessagePart::StepStart);
+        for reasoning in step.reasoning() {
+            if let LanguageModelDoGenerateResponseReasoning::Text { text, signature } = reasoning {
+                parts.push(UIMessagePart::Reasoning {
+                    text: text.clone(),
+                    signature: signature.clone(),
+                });
+            }
+        }
+        if !step.text().is_empty() {
+            parts.push(UIMessagePart::Text {
+                text: step.text().to_string(),
+            });
+        }
+        parts.extend(step.files().iter().map(|file| UIMessagePart::File {
+            media_type: file.mime_type().to_string(),
+            url: file.to_data_url(),
+            filename: None,
+        }));
+        parts.extend(step.sources().iter().map(source_part));
+        for call in step.tool_calls() {
+            let result = step
+                .tool_results()
+                .iter()
+                .find(|result| result.tool_call_id == call.tool_call_id);
+            let input = parse_json_or_string(&call.args);
+            let state = match result {
+                Some(result) if result.is_error == Some(true) => UIToolPartState::OutputError {
+                    input: Some(input),
+                    error_text: result.result.clone(),
+                },
+                Some(result) => UIToolPartState::OutputAvailable {
+                    input,
+                    output: parse_json_or_string(&result.result),
+                },
+                None => UIToolPartState::InputAvailable { input },
+            };
+            parts.push(UIMessagePart::Tool(UIToolPart {
+                tool_name: call.tool_name.clone(),
+                tool_call_id: call.tool_call_id.clone(),
+                state,
+            }));
+        }
+    }
+
+    UIMessage {
+        id: id.into(),
+        role: UIMessageRole::Assistant,
+        parts,
+        metadata: None,
+    }
+}
+
+/// Documents have no media type in model responses and are shown as text.
+fn source_part(source: &LanguageModelSource) -> UIMessagePart {
+    match (&source.source_type, &source.url) {
+        (LanguageModelSourceType::Url, Some(url)) => UIMessagePart::SourceUrl {
+            source_id: source.id.clone(),
+            url: url.clone(),
+            title: source.title.clone(),
+        },
+        _ => UIMessagePart::SourceDocument {
+            source_id: source.id.clone(),
+            media_type: "text/plain".to_string(),
+            title: source.title.clone().unwrap_or_else(|| source.id.clone()),
+            filename: None,
+        },
+    }
+}
+
+fn text_of(parts: &[UIMessagePart]) -> String {
+    parts
+        .iter()
+        .filter_map(|part| match part {
+            UIMessagePart::Text { text } => Some(text.as_str()),
+            _ => None,
+        })
+        .collect()
+}
+
+fn file_part(media_type: &str, url: &str, filename: &Option<String>) -> UserContentParts {
+    if media_type.starts_with("image/") {
+        UserContentParts::Image(ImagePart {
+            image: None,
+            image_url: Some(url.to_string()),
+            mime_type: Some(media_type.to_string()),
+        })
+    } else {
+        UserContentParts::File(FilePart {
+            file_name: filename.clone(),
+            file_content: None,
+            file_url: Some(url.to_string()),
+            mime_type: Some(media_type.to_string()),
+        })
+    }
+}
+
+/// Tool arguments and results are stored as strings in [`CoreMessage`]s.
+fn json_to_string(value: &JSONValue) -> String {
+    match value {
+        JSONValue::String(text) => text.clone(),
+        value => value.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{convert_to_core_messages, convert_to_ui_message, UIMessage, UIMessagePart};
+    use crate::{
+        core::generate_text::{generate_text, GenerateTextOptions},
+        prompt::{
+            standarize_prompt::StandardizedPrompt, AssistantContent, CoreAssistantMessage,
+            CoreMessage, Prompt,
+        },
+        testing::MockLanguageModel,
+    };
+
+    #[tokio::test]
+    async fn test_ui_message_conversions() {
+        let mut model = MockLanguageModel::new().with_text_response("It is sunny.");
+        let result = generate_text(
+            &mut model,
+            GenerateTextOptions::new().prompt("Weather?".to_string()),
+        )
+        .await
+        .unwrap();
+        let message = convert_to_ui_message("msg_2", &result);
+        assert_eq!(
+            serde_json::to_value(&message).unwrap(),
+            json!({
+                "id": "msg_2",
+                "role": "assistant",
+                "parts": [
+                    { "type": "step-start" },
+                    { "type": "text", "text": "It is sunny." }
+                ]
+            })
+        );
+
+        let messages: Vec<UIMessage> = serde_json::from_value(json!([
+            { "id": "msg_1", "role": "user", "parts": [{ "type": "text", "text": "Weather in Kathmandu?" }] },
+            {
+                "id": "msg_2",
+                "role": "assistant",
+                "parts": [
+                    { "type": "step-start" },
+                    {
+                        "type": "tool-weather",
+                        "toolCallId": "call_1",
+                        "state": "output-available",
+                        "input": { "city": "Kathmandu" },
+                        "output": { "temperature": 24 }
+                    },
+                    { "type": "source-url", "sourceId": "src_1", "url": "https://weather.example/ktm" },
+                    { "type": "data-forecast-card", "id": "card_1", "data": { "city": "Kathmandu" } },
+                    { "type": "step-start" },
+                    { "type": "text", "text": "It is 24 degrees." }
+                ]
+            }
+        ]))
+        .unwrap();
+        assert!(matches!(
+            &messages[1].parts[1],
+            UIMessagePart::Tool(part) if part.tool_name == "weather"
+        ));
+        assert!(matches!(
+            &messages[1].parts[3],
+            UIMessagePart::Data { name, id, .. } if name == "forecast-card" && id.as_deref() == Some("card_1")
+        ));
+        assert_eq!(
+            serde_json::to_value(&messages[1]).unwrap()["parts"][1],
+            json!({
+                "type": "tool-weather",
+                "toolCallId": "call_1",
+                "state": "output-available",
+                "input": { "city": "Kathmandu" },
+                "output": { "temperature": 24 }
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&messages[1]).unwrap()["parts"][3],
+            json!({ "type": "data-forecast-card", "id": "card_1", "data": { "city": "Kathmandu" } })
+        );
+        let core_messages = convert_to_core_messages(&messages).unwrap();
+        assert_eq!(core_messages.len(), 4);
+        assert!(matches!(&core_messages[1], CoreMessage::Assistant(_)));
+        assert!(matches!(
+            &core_messages[2],
+            CoreMessage::Tool(message) if message.content[0].result == r#"{"temperature":24}"#
+        ));
+        let prompt = StandardizedPrompt::try_from(Prompt {
+            ui_messages: Some(messages),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(prompt.messages().len(), core_messages.len());
+
+        // Text-only steps and consecutive assistant messages are one turn.
+        let messages: Vec<UIMessage> = serde_json::from_value(json!([
+            { "id": "msg_1", "role": "user", "parts": [{ "type": "text", "text": "Hi" }] },
+            {
+                "id": "msg_2",
+                "role": "assistant",
+                "parts": [
+                    { "type": "step-start" },
+                    { "type": "text", "text": "Hello!" },
+                    { "type": "step-start" },
+                    { "type": "text", "text": "How can I help?" }
+                ]
+            },
+            { "id": "msg_3", "role": "assistant", "parts": [{ "type": "text", "text": "Ask me anything." }] }
+        ]))
+        .unwrap();
+        let prompt = StandardizedPrompt::try_from(Prompt {
+            ui_messages: Some(messages),
+            ..Default::default()
+        })
+        .unwrap();
+        assert_eq!(prompt.messages().len(), 2);
+        assert!(matches!(
+            &prompt.messages()[1],
+            CoreMessage::Assistant(CoreAssistantMessage { content: AssistantContent::Parts(parts) })
+                if parts.len() == 3
+        ));
+
+        let pending: Vec<UIMessage> = serde_json::from_value(json!([{
+            "id": "msg_3",
+            "role": "assistant",
+            "parts": [{
+                "type": "tool-weather",
+                "toolCallId": "call_2",
+                "state": "input-available",
+                "input": {}
+            }]
+        }]))
+        .unwrap();
+        assert!(convert_to_core_messages(&pending).is_err());
+    }
+}
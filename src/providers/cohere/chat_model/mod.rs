@@ -0,0 +1,599 @@
+pub mod model_id;
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value as JSONValue};
+use std::{collections::HashMap, sync::Arc};
+
+use crate::{
+    errors::{ApiErrorPayload, ModelError},
+    model::{
+        call_settings::{LanguageModelCallSettings, LanguageModelCallSettingsResponseFormat},
+        call_warning::LanguageModelCallWarning,
+        finish_reason::LanguageModelFinishReason,
+        function_tool_call::LanguageModelFunctionToolCall,
+        message::{LanguageModelAssistantMessage, LanguageModelMessage, LanguageModelUserMessage},
+        request_metadata::LanguageModelRequestMetadata,
+        response_metadata::LanguageModelResponseMetadata,
+        source::{LanguageModelSource, LanguageModelSourceType},
+        tools::LanguageModelToolChoice,
+        usage::LanguageModelUsage,
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateResponse,
+        LanguageModelDoGenerateResponseReasoning,
+    },
+    transport::HttpTransport,
+    utils,
+};
+use model_id::CohereChatModelId;
+
+/// Key under which Cohere specific options are read from the request's
+/// `provider_metadata`, e.g. `{"cohere": {"documents": [...]}}`.
+const COHERE_METADATA_KEY: &str = "cohere";
+
+/// Connection details handed to the chat model by the provider.
+pub struct CohereChatConfig {
+    pub provider: String,
+    pub base_url: String,
+    pub headers: Vec<(String, String)>,
+    pub transport: Arc<dyn HttpTransport>,
+    pub default_settings: LanguageModelCallSettings,
+}
+
+pub struct CohereChatModel {
+    pub model_id: CohereChatModelId,
+    pub config: CohereChatConfig,
+}
+
+impl CohereChatModel {
+    pub fn new(model_id: CohereChatModelId, config: CohereChatConfig) -> Self {
+        CohereChatModel { model_id, config }
+    }
+
+    fn get_args(
+        &self,
+        request: &LanguageModelDoGenerateRequest,
+    ) -> Result<(JSONValue, Vec<LanguageModelCallWarning>), ModelError> {
+        let mut warnings = Vec::new();
+        let mut body = Map::new();
+        body.insert("model".into(), json!(self.model_id.to_string()));
+        body.insert(
+            "messages".into(),
+            convert_to_cohere_messages(&request.prompt)?,
+        );
+
+        let settings = request.call_settings_with_defaults(&self.config.default_settings);
+        if let Some(max_tokens) = settings.max_tokens {
+            body.insert("max_tokens".into(), json!(max_tokens));
+        }
+        if let Some(temperature) = settings.temperature {
+            body.insert("temperature".into(), json!(temperature));
+        }
+        if let Some(top_p) = settings.top_p {
+            body.insert("p".into(), json!(top_p));
+        }
+        if let Some(top_k) = settings.top_k {
+            body.insert("k".into(), json!(top_k));
+        }
+        if let Some(presence_penalty) = settings.presence_penalty {
+            body.insert("presence_penalty".into(), json!(presence_penalty));
+        }
+        if let Some(frequency_penalty) = settings.frequency_penalty {
+            body.insert("frequency_penalty".into(), json!(frequency_penalty));
+        }
+        if let Some(stop_sequences) = &settings.stop_sequences {
+            body.insert("stop_sequences".into(), json!(stop_sequences));
+        }
+        if let Some(seed) = settings.seed {
+            body.insert("seed".into(), json!(seed));
+        }
+        if let Some(LanguageModelCallSettingsResponseFormat::Json) = settings.response_format {
+            body.insert("response_format".into(), json!({ "type": "json_object" }));
+        }
+
+        if let Some(tools) = request.tools.as_ref().filter(|tools| !tools.is_empty()) {
+            // Cohere cannot force a specific tool, so only that tool is sent and
+            // a tool call is required instead.
+            let forced_tool = match &request.tool_choice {
+                Some(LanguageModelToolChoice::Tool(name)) => Some(name),
+                _ => None,
+            };
+            let tools: Vec<JSONValue> = tools
+                .iter()
+                .filter(|tool| forced_tool.is_none_or(|name| *name == tool.name))
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        }
+                    })
+                })
+                .collect();
+            body.insert("tools".into(), JSONValue::Array(tools));
+
+            match &request.tool_choice {
+                Some(LanguageModelToolChoice::None) => {
+                    body.insert("tool_choice".into(), json!("NONE"));
+                }
+                Some(LanguageModelToolChoice::Required)
+                | Some(LanguageModelToolChoice::Tool(_)) => {
+                    body.insert("tool_choice".into(), json!("REQUIRED"));
+                }
+                Some(LanguageModelToolChoice::Auto) | None => {}
+            }
+        }
+
+        if let Some(options) = request
+            .provider_metadata
+            .as_ref()
+            .and_then(|metadata| metadata.get(COHERE_METADATA_KEY))
+        {
+            for (key, value) in options {
+                match key.as_str() {
+                    "documents" | "citation_options" => {
+                        body.insert(key.clone(), value.clone());
+                    }
+                    _ => warnings.push(LanguageModelCallWarning::Other {
+                        message: format!("Unsupported Cohere option `{key}` was ignored"),
+                    }),
+                }
+            }
+        }
+
+        Ok((JSONValue::Object(body), warnings))
+    }
+}
+
+#[async_trait]
+impl LanguageModel for CohereChatModel {
+    fn provider(&self) -> String {
+        self.config.provider.clone()
+    }
+
+    fn model_id(&self) -> String {
+        self.model_id.to_string()
+    }
+
+    fn supports_urls(&self, url: String) -> bool {
+        url.starts_with("https://")
+    }
+
+    async fn do_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let (body, warnings) = self.get_args(&request)?;
+        let url = format!("{}/chat", self.config.base_url);
+        let settings = request.call_settings_with_defaults(&self.config.default_settings);
+        let mut headers = self.config.headers.clone();
+        headers.extend(settings.headers.iter().cloned());
+
+        let (response, response_headers) = utils::post_json_to_api(
+            self.config.transport.as_ref(),
+            &url,
+            &headers,
+            &body,
+            parse_cohere_error_payload,
+        )
+        .await?;
+        let message = &response["message"];
+        if !message.is_object() {
+            return Err(ModelError::NoContent(
+                "Cohere response did not contain a message".to_string(),
+            ));
+        }
+
+        let text = message["content"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .filter(|part| part["type"] == "text")
+            .filter_map(|part| part["text"].as_str())
+            .collect::<String>();
+
+        let reasoning = message["tool_plan"]
+            .as_str()
+            .map(|tool_plan| LanguageModelDoGenerateResponseReasoning::Text {
+                text: tool_plan.to_string(),
+                signature: None,
+            })
+            .into_iter()
+            .collect();
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|tool_call| LanguageModelFunctionToolCall {
+                tool_call_id: tool_call["id"].as_str().unwrap_or_default().to_string(),
+                tool_name: tool_call["function"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                args: match &tool_call["function"]["arguments"] {
+                    JSONValue::String(args) => args.clone(),
+                    args => args.to_string(),
+                },
+            })
+            .collect();
+
+        let sources = message["citations"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .flat_map(citation_to_sources)
+            .collect();
+
+        let tokens = if response["usage"]["tokens"].is_object() {
+            &response["usage"]["tokens"]
+        } else {
+            &response["usage"]["billed_units"]
+        };
+        let prompt_tokens = tokens["input_tokens"].as_u64().unwrap_or_default() as u32;
+        let completion_tokens = tokens["output_tokens"].as_u64().unwrap_or_default() as u32;
+
+        Ok(LanguageModelDoGenerateResponse {
+            text: (!text.is_empty()).then_some(text),
+            reasoning,
+            tool_calls,
+            finish_reason: map_cohere_finish_reason(response["finish_reason"].as_str()),
+            usage: LanguageModelUsage::new(prompt_tokens, completion_tokens),
+            response: Some(LanguageModelResponseMetadata {
+                id: response["id"].as_str().unwrap_or_default().to_string(),
+                timestamp: utils::current_timestamp(),
+                model_id: self.model_id.to_string(),
+                headers: response_headers,
+            }),
+            request_body: Some(LanguageModelRequestMetadata {
+                body: Some(body.to_string()),
+                call_settings: Some(settings),
+            }),
+            warnings,
+            sources,
+            ..Default::default()
+        })
+    }
+}
+
+/// Cohere errors are `{"id", "message"}`, without an error code.
+fn parse_cohere_error_payload(body: &JSONValue) -> ApiErrorPayload {
+    ApiErrorPayload {
+        code: None,
+        message: body["message"].as_str().map(str::to_string),
+    }
+}
+
+/// Turns a Cohere citation into one source per cited document or tool result.
+/// The cited span of the generated text is kept in the source's provider metadata.
+fn citation_to_sources(citation: &JSONValue) -> Vec<LanguageModelSource> {
+    citation["sources"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .map(|source| {
+            let document = if source["document"].is_object() {
+                &source["document"]
+            } else {
+                &source["tool_output"]
+            };
+            let url = document["url"].as_str().map(str::to_string);
+            let mut metadata = HashMap::new();
+            metadata.insert("start".to_string(), citation["start"].clone());
+            metadata.insert("end".to_string(), citation["end"].clone());
+            metadata.insert("text".to_string(), citation["text"].clone());
+            metadata.insert("type".to_string(), source["type"].clone());
+            metadata.insert("document".to_string(), document.clone());
+
+            LanguageModelSource {
+                source_type: if url.is_some() {
+                    LanguageModelSourceType::Url
+                } else {
+                    LanguageModelSourceType::Document
+                },
+                id: source["id"].as_str().unwrap_or_default().to_string(),
+                url,
+                title: document["title"].as_str().map(str::to_string),
+                provider_metadata: HashMap::from([(COHERE_METADATA_KEY.to_string(), metadata)]),
+            }
+        })
+        .collect()
+}
+
+fn map_cohere_finish_reason(finish_reason: Option<&str>) -> LanguageModelFinishReason {
+    match finish_reason {
+        Some("COMPLETE") | Some("STOP_SEQUENCE") => LanguageModelFinishReason::Stop,
+        Some("MAX_TOKENS") => LanguageModelFinishReason::Length,
+        Some("TOOL_CALL") => LanguageModelFinishReason::ToolCalls,
+        Some("ERROR") => LanguageModelFinishReason::Error,
+        Some(_) => LanguageModelFinishReason::Other,
+        None => LanguageModelFinishReason::Unknown,
+    }
+}
+
+fn convert_to_cohere_messages(prompt: &[LanguageModelMessage]) -> Result<JSONValue, ModelError> {
+    let mut messages = Vec::new();
+    for message in prompt {
+        match message {
+            LanguageModelMessage::System(content) => {
+                messages.push(json!({ "role": "system", "content": content }));
+            }
+            LanguageModelMessage::User(parts) => {
+                let content = parts
+                    .iter()
+                    .map(|part| match part {
+                        LanguageModelUserMessage::Text(part) => {
+                            Ok(json!({ "type": "text", "text": part.text }))
+                        }
+                        LanguageModelUserMessage::Image(part) => Ok(json!({
+                            "type": "image_url",
+                            "image_url": { "url": utils::image_part_to_url(part) }
+                        })),
+                        LanguageModelUserMessage::File(_) => Err(ModelError::NotSupported(
+                            "Cohere does not support file parts, pass them as `documents` instead"
+                                .to_string(),
+                        )),
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                messages.push(json!({ "role": "user", "content": content }));
+            }
+            LanguageModelMessage::Assistant(parts) => {
+                let mut text = String::new();
+                let mut tool_calls = Vec::new();
+                for part in parts {
+                    match part {
+                        LanguageModelAssistantMessage::Text(part) => text.push_str(&part.text),
+                        LanguageModelAssistantMessage::ToolCall(part) => tool_calls.push(json!({
+                            "id": part.tool_call_id,
+                            "type": "function",
+                            "function": {
+                                "name": part.tool_name,
+                                "arguments": part.args.to_string(),
+                            }
+                        })),
+                        _ => {}
+                    }
+                }
+                // Cohere expects the text preceding tool calls as `tool_plan`.
+                if tool_calls.is_empty() {
+                    messages.push(json!({ "role": "assistant", "content": text }));
+                } else {
+                    messages.push(json!({
+                        "role": "assistant",
+                        "tool_plan": text,
+                        "tool_calls": tool_calls,
+                    }));
+                }
+            }
+            LanguageModelMessage::Tool(results) => {
+                for result in results {
+                    messages.push(json!({
+                        "role": "tool",
+                        "tool_call_id": result.tool_call_id,
+                        "content": [{
+                            "type": "document",
+                            "document": { "data": result.result.to_string() }
+                        }],
+                    }));
+                }
+            }
+        }
+    }
+    Ok(JSONValue::Array(messages))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use std::collections::HashMap;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        model::{
+            call_settings::LanguageModelCallSettings,
+            finish_reason::LanguageModelFinishReason,
+            message::{
+                LanguageModelAssistantMessage, LanguageModelMessage, LanguageModelTextPart,
+                LanguageModelToolCallPart, LanguageModelToolResultPart, LanguageModelUserMessage,
+            },
+            source::LanguageModelSourceType,
+            tools::{LanguageModelFunctionTool, LanguageModelToolChoice},
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat,
+        },
+        provider::LanguageModelProvider,
+        providers::cohere::{provider_settings::CohereProviderSettings, CohereProvider},
+    };
+
+    fn user_message(text: &str) -> LanguageModelMessage {
+        LanguageModelMessage::User(vec![LanguageModelUserMessage::Text(
+            LanguageModelTextPart {
+                text: text.to_string(),
+                provider_metadata: None,
+            },
+        )])
+    }
+
+    #[tokio::test]
+    async fn test_cohere_documents_and_citations() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "command-r-plus",
+                "messages": [
+                    { "role": "system", "content": "Answer using the documents." },
+                    {
+                        "role": "user",
+                        "content": [{ "type": "text", "text": "What is the capital of Nepal?" }]
+                    }
+                ],
+                "documents": [{ "id": "doc-1", "data": { "title": "Nepal", "text": "Kathmandu is the capital of Nepal." } }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp-1",
+                "finish_reason": "COMPLETE",
+                "message": {
+                    "role": "assistant",
+                    "content": [{ "type": "text", "text": "The capital is Kathmandu." }],
+                    "citations": [{
+                        "start": 15,
+                        "end": 24,
+                        "text": "Kathmandu",
+                        "sources": [{
+                            "type": "document",
+                            "id": "doc-1",
+                            "document": { "id": "doc-1", "title": "Nepal", "text": "Kathmandu is the capital of Nepal." }
+                        }]
+                    }]
+                },
+                "usage": {
+                    "billed_units": { "input_tokens": 30, "output_tokens": 6 },
+                    "tokens": { "input_tokens": 210, "output_tokens": 6 }
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = CohereProvider::new(
+            CohereProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let model = provider.language_model("command-r-plus").unwrap();
+
+        let documents = json!([{ "id": "doc-1", "data": { "title": "Nepal", "text": "Kathmandu is the capital of Nepal." } }]);
+        let response = model
+            .do_generate(LanguageModelDoGenerateRequest {
+                call_settings: Some(LanguageModelCallSettings::default()),
+                input_format: LanguageModelDoGenerateRequestInputFormat::Messages,
+                prompt: vec![
+                    LanguageModelMessage::System("Answer using the documents.".to_string()),
+                    user_message("What is the capital of Nepal?"),
+                ],
+                tools: None,
+                tool_choice: None,
+                provider_metadata: Some(HashMap::from([(
+                    "cohere".to_string(),
+                    HashMap::from([("documents".to_string(), documents)]),
+                )])),
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.text.as_deref(), Some("The capital is Kathmandu."));
+        assert_eq!(response.finish_reason, LanguageModelFinishReason::Stop);
+        assert_eq!(response.usage.prompt_tokens, 210);
+        assert_eq!(response.sources.len(), 1);
+        let source = &response.sources[0];
+        assert!(matches!(
+            source.source_type,
+            LanguageModelSourceType::Document
+        ));
+        assert_eq!(source.id, "doc-1");
+        assert_eq!(source.title.as_deref(), Some("Nepal"));
+        assert_eq!(source.provider_metadata["cohere"]["text"], "Kathmandu");
+        assert_eq!(source.provider_metadata["cohere"]["start"], 15);
+    }
+
+    #[tokio::test]
+    async fn test_cohere_tool_calls() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat"))
+            .and(body_partial_json(json!({
+                "tool_choice": "REQUIRED",
+                "tools": [{ "type": "function", "function": { "name": "weather" } }],
+                "messages": [
+                    { "role": "user" },
+                    {
+                        "role": "assistant",
+                        "tool_plan": "I will look up the weather.",
+                        "tool_calls": [{
+                            "id": "call_0",
+                            "type": "function",
+                            "function": { "name": "weather", "arguments": "{\"city\":\"Pokhara\"}" }
+                        }]
+                    },
+                    {
+                        "role": "tool",
+                        "tool_call_id": "call_0",
+                        "content": [{ "type": "document", "document": { "data": "{\"temperature\":21}" } }]
+                    },
+                    { "role": "user" }
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "resp-2",
+                "finish_reason": "TOOL_CALL",
+                "message": {
+                    "role": "assistant",
+                    "tool_plan": "I will look up the weather in Kathmandu.",
+                    "tool_calls": [{
+                        "id": "call_1",
+                        "type": "function",
+                        "function": { "name": "weather", "arguments": "{\"city\":\"Kathmandu\"}" }
+                    }]
+                },
+                "usage": { "billed_units": { "input_tokens": 40, "output_tokens": 12 } }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = CohereProvider::new(
+            CohereProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let model = provider.language_model("command-r-plus").unwrap();
+
+        let response = model
+            .do_generate(LanguageModelDoGenerateRequest {
+                call_settings: Some(LanguageModelCallSettings::default()),
+                input_format: LanguageModelDoGenerateRequestInputFormat::Messages,
+                prompt: vec![
+                    user_message("What is the weather in Pokhara?"),
+                    LanguageModelMessage::Assistant(vec![
+                        LanguageModelAssistantMessage::Text(LanguageModelTextPart {
+                            text: "I will look up the weather.".to_string(),
+                            provider_metadata: None,
+                        }),
+                        LanguageModelAssistantMessage::ToolCall(LanguageModelToolCallPart {
+                            tool_call_id: "call_0".to_string(),
+                            tool_name: "weather".to_string(),
+                            args: json!({ "city": "Pokhara" }),
+                            provider_metadata: None,
+                        }),
+                    ]),
+                    LanguageModelMessage::Tool(vec![LanguageModelToolResultPart {
+                        tool_call_id: "call_0".to_string(),
+                        tool_name: "weather".to_string(),
+                        result: json!({ "temperature": 21 }),
+                        is_error: None,
+                        content: Vec::new(),
+                        provider_metadata: None,
+                    }]),
+                    user_message("And in Kathmandu?"),
+                ],
+                tools: Some(vec![LanguageModelFunctionTool {
+                    name: "weather".to_string(),
+                    description: Some("Get the weather for a city".to_string()),
+                    parameters: json!({ "type": "object" }),
+                }]),
+                tool_choice: Some(LanguageModelToolChoice::Required),
+                provider_metadata: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, None);
+        assert_eq!(response.finish_reason, LanguageModelFinishReason::ToolCalls);
+        assert_eq!(response.usage.total_tokens, 52);
+        assert_eq!(response.reasoning.len(), 1);
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].tool_call_id, "call_1");
+        assert_eq!(response.tool_calls[0].args, "{\"city\":\"Kathmandu\"}");
+    }
+}
@@ -0,0 +1,96 @@
+use core::fmt;
+use std::str::FromStr;
+
+use crate::model::pricing::ModelPricing;
+
+/// https://docs.cohere.com/docs/models
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CohereChatModelId {
+    // Command A models
+    CommandA03_2025,
+
+    // Command R models
+    CommandR7b12_2024,
+    CommandRPlus04_2024,
+    CommandRPlus08_2024,
+    CommandRPlus,
+    CommandR03_2024,
+    CommandR08_2024,
+    CommandR,
+
+    // Command models
+    Command,
+    CommandNightly,
+    CommandLight,
+    CommandLightNightly,
+
+    Custom(String),
+}
+
+impl CohereChatModelId {
+    /// List price of the model, `None` for nightly and custom models.
+    ///
+    /// https://cohere.com/pricing
+    pub fn pricing(&self) -> Option<ModelPricing> {
+        let pricing = match self {
+            Self::CommandA03_2025 | Self::CommandRPlus08_2024 => ModelPricing::new(2.5, 10.0),
+            Self::CommandR7b12_2024 => ModelPricing::new(0.0375, 0.15),
+            Self::CommandRPlus04_2024 | Self::CommandRPlus => ModelPricing::new(3.0, 15.0),
+            Self::CommandR08_2024 => ModelPricing::new(0.15, 0.6),
+            Self::CommandR03_2024 | Self::CommandR => ModelPricing::new(0.5, 1.5),
+            Self::Command => ModelPricing::new(1.0, 2.0),
+            Self::CommandLight => ModelPricing::new(0.3, 0.6),
+            Self::CommandNightly | Self::CommandLightNightly | Self::Custom(_) => return None,
+        };
+        Some(pricing)
+    }
+}
+
+impl FromStr for CohereChatModelId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "command-a-03-2025" => Ok(Self::CommandA03_2025),
+
+            "command-r7b-12-2024" => Ok(Self::CommandR7b12_2024),
+            "command-r-plus-04-2024" => Ok(Self::CommandRPlus04_2024),
+            "command-r-plus-08-2024" => Ok(Self::CommandRPlus08_2024),
+            "command-r-plus" => Ok(Self::CommandRPlus),
+            "command-r-03-2024" => Ok(Self::CommandR03_2024),
+            "command-r-08-2024" => Ok(Self::CommandR08_2024),
+            "command-r" => Ok(Self::CommandR),
+
+            "command" => Ok(Self::Command),
+            "command-nightly" => Ok(Self::CommandNightly),
+            "command-light" => Ok(Self::CommandLight),
+            "command-light-nightly" => Ok(Self::CommandLightNightly),
+
+            // Handle custom model IDs
+            _ => Ok(Self::Custom(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for CohereChatModelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CommandA03_2025 => write!(f, "command-a-03-2025"),
+
+            Self::CommandR7b12_2024 => write!(f, "command-r7b-12-2024"),
+            Self::CommandRPlus04_2024 => write!(f, "command-r-plus-04-2024"),
+            Self::CommandRPlus08_2024 => write!(f, "command-r-plus-08-2024"),
+            Self::CommandRPlus => write!(f, "command-r-plus"),
+            Self::CommandR03_2024 => write!(f, "command-r-03-2024"),
+            Self::CommandR08_2024 => write!(f, "command-r-08-2024"),
+            Self::CommandR => write!(f, "command-r"),
+
+            Self::Command => write!(f, "command"),
+            Self::CommandNightly => write!(f, "command-nightly"),
+            Self::CommandLight => write!(f, "command-light"),
+            Self::CommandLightNightly => write!(f, "command-light-nightly"),
+
+            Self::Custom(s) => write!(f, "{}", s),
+        }
+    }
+}
@@ -0,0 +1,72 @@
+pub mod chat_model;
+pub mod provider_settings;
+
+use crate::{errors::ProviderError, provider::LanguageModelProvider};
+use chat_model::{model_id::CohereChatModelId, CohereChatConfig, CohereChatModel};
+use provider_settings::CohereProviderSettings;
+use std::str::FromStr;
+
+pub struct CohereProvider {
+    pub settings: CohereProviderSettings,
+}
+
+impl CohereProvider {
+    pub fn new(settings: CohereProviderSettings) -> Self {
+        CohereProvider { settings }
+    }
+
+    pub fn create_chat_model(
+        &self,
+        model_id: CohereChatModelId,
+    ) -> Result<CohereChatModel, ProviderError> {
+        Ok(CohereChatModel::new(
+            model_id,
+            CohereChatConfig {
+                provider: format!("{}.chat", self.settings.name),
+                base_url: self.settings.base_url.clone(),
+                headers: self.get_headers()?,
+                transport: self.settings.transport.clone(),
+                default_settings: self.settings.default_settings.clone(),
+            },
+        ))
+    }
+}
+
+impl Default for CohereProvider {
+    fn default() -> Self {
+        CohereProvider::new(CohereProviderSettings::default())
+    }
+}
+
+impl LanguageModelProvider for CohereProvider {
+    type Model = CohereChatModel;
+    fn language_model(&self, model_id: &str) -> Result<Self::Model, ProviderError> {
+        let model_id = model_id.trim();
+        if model_id.is_empty() {
+            return Err(ProviderError::InvalidModelId(
+                "Provided an empty Cohere model id".to_string(),
+            ));
+        }
+        let cohere_model_id = CohereChatModelId::from_str(model_id).map_err(|_| {
+            ProviderError::InvalidModelId(format!(
+                "Provided an invalid Cohere model id, {model_id}"
+            ))
+        })?;
+
+        self.create_chat_model(cohere_model_id)
+    }
+
+    fn get_headers(&self) -> Result<Vec<(String, String)>, ProviderError> {
+        let mut headers = vec![
+            (
+                "Authorization".to_string(),
+                format!("Bearer {}", self.settings.api_key),
+            ),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ];
+        if let Some(extra) = &self.settings.headers {
+            headers.extend(extra.iter().cloned());
+        }
+        Ok(headers)
+    }
+}
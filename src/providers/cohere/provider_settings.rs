@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use crate::{
+    model::call_settings::LanguageModelCallSettings,
+    transport::{default_transport, HttpTransport},
+    utils,
+};
+
+const COHERE_DEFAULT_BASE_URL: &str = "https://api.cohere.com/v2";
+
+pub struct CohereProviderSettings {
+    /// Base URL for the Cohere API calls.
+    pub base_url: String,
+    /// API key for authenticating requests to the Cohere API.
+    pub api_key: String,
+    /// Optional headers to include in the requests.
+    pub headers: Option<Vec<(String, String)>>,
+    /// Provider name. Overrides the `cohere` default name for 3rd party providers.
+    pub name: String,
+    /// Transport used to send the HTTP requests, e.g. to record or replay them.
+    pub transport: Arc<dyn HttpTransport>,
+    /// Call settings applied to every call of the provider's models, unless
+    /// a model default or the call itself overrides them.
+    pub default_settings: LanguageModelCallSettings,
+}
+
+impl Default for CohereProviderSettings {
+    fn default() -> Self {
+        CohereProviderSettings {
+            base_url: COHERE_DEFAULT_BASE_URL.to_string(),
+            api_key: String::new(),
+            headers: None,
+            name: "cohere".to_string(),
+            transport: default_transport(),
+            default_settings: LanguageModelCallSettings::default(),
+        }
+    }
+}
+
+impl CohereProviderSettings {
+    /// Creates a new instance of `CohereProviderSettings` with the provided API key.
+    pub fn new(api_key: String) -> Self {
+        CohereProviderSettings {
+            api_key,
+            ..Default::default()
+        }
+    }
+
+    /// Sets the base URL for the Cohere provider settings.
+    pub fn base_url(mut self, base_url: &str) -> Self {
+        self.base_url = utils::without_trailing_slash(base_url);
+        self
+    }
+
+    /// Sets the headers for the Cohere provider settings.
+    pub fn headers(mut self, headers: Vec<(String, String)>) -> Self {
+        self.headers = Some(headers);
+        self
+    }
+
+    /// Sets the name for the Cohere provider settings.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// Sets the HTTP transport for the Cohere provider settings.
+    pub fn transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Sets the default call settings for the Cohere provider settings.
+    pub fn default_settings(mut self, default_settings: LanguageModelCallSettings) -> Self {
+        self.default_settings = default_settings;
+        self
+    }
+}
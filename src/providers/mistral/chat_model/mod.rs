@@ -0,0 +1,535 @@
+pub mod model_id;
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value as JSONValue};
+use std::sync::Arc;
+
+use crate::{
+    errors::{ApiErrorPayload, ModelError},
+    model::{
+        call_settings::{LanguageModelCallSettings, LanguageModelCallSettingsResponseFormat},
+        call_warning::LanguageModelCallWarning,
+        finish_reason::LanguageModelFinishReason,
+        function_tool_call::LanguageModelFunctionToolCall,
+        message::{
+            LanguageModelAssistantMessage, LanguageModelFilePartContent, LanguageModelMessage,
+            LanguageModelUserMessage,
+        },
+        request_metadata::LanguageModelRequestMetadata,
+        response_metadata::LanguageModelResponseMetadata,
+        tools::LanguageModelToolChoice,
+        usage::LanguageModelUsage,
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateResponse,
+        LanguageModelDoGenerateResponseReasoning,
+    },
+    transport::HttpTransport,
+    utils,
+};
+use model_id::MistralChatModelId;
+
+/// Connection details handed to the chat model by the provider.
+pub struct MistralChatConfig {
+    pub provider: String,
+    pub base_url: String,
+    pub headers: Vec<(String, String)>,
+    pub transport: Arc<dyn HttpTransport>,
+    pub default_settings: LanguageModelCallSettings,
+}
+
+pub struct MistralChatModel {
+    pub model_id: MistralChatModelId,
+    pub config: MistralChatConfig,
+    /// Whether to inject a safety prompt before all conversations.
+    ///
+    /// Defaults to `false`.
+    pub safe_prompt: bool,
+}
+
+impl MistralChatModel {
+    pub fn new(model_id: MistralChatModelId, config: MistralChatConfig) -> Self {
+        MistralChatModel {
+            model_id,
+            config,
+            safe_prompt: false,
+        }
+    }
+
+    pub fn with_safe_prompt(mut self, safe_prompt: bool) -> Self {
+        self.safe_prompt = safe_prompt;
+        self
+    }
+
+    fn get_args(
+        &self,
+        request: &LanguageModelDoGenerateRequest,
+    ) -> Result<(JSONValue, Vec<LanguageModelCallWarning>), ModelError> {
+        let mut warnings = Vec::new();
+        let mut body = Map::new();
+        body.insert("model".into(), json!(self.model_id.to_string()));
+        body.insert(
+            "messages".into(),
+            convert_to_mistral_messages(&request.prompt)?,
+        );
+        if self.safe_prompt {
+            body.insert("safe_prompt".into(), json!(true));
+        }
+
+        let settings = request.call_settings_with_defaults(&self.config.default_settings);
+        if let Some(max_tokens) = settings.max_tokens {
+            body.insert("max_tokens".into(), json!(max_tokens));
+        }
+        if let Some(temperature) = settings.temperature {
+            body.insert("temperature".into(), json!(temperature));
+        }
+        if let Some(top_p) = settings.top_p {
+            body.insert("top_p".into(), json!(top_p));
+        }
+        if settings.top_k.is_some() {
+            warnings.push(LanguageModelCallWarning::UnsupportedSetting {
+                setting: "top_k".to_string(),
+                details: None,
+            });
+        }
+        if let Some(presence_penalty) = settings.presence_penalty {
+            body.insert("presence_penalty".into(), json!(presence_penalty));
+        }
+        if let Some(frequency_penalty) = settings.frequency_penalty {
+            body.insert("frequency_penalty".into(), json!(frequency_penalty));
+        }
+        if let Some(stop_sequences) = &settings.stop_sequences {
+            body.insert("stop".into(), json!(stop_sequences));
+        }
+        if let Some(seed) = settings.seed {
+            body.insert("random_seed".into(), json!(seed));
+        }
+        if let Some(LanguageModelCallSettingsResponseFormat::Json) = settings.response_format {
+            body.insert("response_format".into(), json!({ "type": "json_object" }));
+        }
+
+        if let Some(tools) = request.tools.as_ref().filter(|tools| !tools.is_empty()) {
+            let tools: Vec<JSONValue> = tools
+                .iter()
+                .map(|tool| {
+                    json!({
+                        "type": "function",
+                        "function": {
+                            "name": tool.name,
+                            "description": tool.description,
+                            "parameters": tool.parameters,
+                        }
+                    })
+                })
+                .collect();
+            body.insert("tools".into(), JSONValue::Array(tools));
+
+            if let Some(tool_choice) = &request.tool_choice {
+                let tool_choice = match tool_choice {
+                    LanguageModelToolChoice::Auto => json!("auto"),
+                    LanguageModelToolChoice::None => json!("none"),
+                    LanguageModelToolChoice::Required => json!("any"),
+                    LanguageModelToolChoice::Tool(name) => {
+                        json!({ "type": "function", "function": { "name": name } })
+                    }
+                };
+                body.insert("tool_choice".into(), tool_choice);
+            }
+        }
+
+        Ok((JSONValue::Object(body), warnings))
+    }
+}
+
+#[async_trait]
+impl LanguageModel for MistralChatModel {
+    fn provider(&self) -> String {
+        self.config.provider.clone()
+    }
+
+    fn model_id(&self) -> String {
+        self.model_id.to_string()
+    }
+
+    fn supports_urls(&self, url: String) -> bool {
+        url.starts_with("https://")
+    }
+
+    async fn do_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let (body, warnings) = self.get_args(&request)?;
+        let url = format!("{}/chat/completions", self.config.base_url);
+        let settings = request.call_settings_with_defaults(&self.config.default_settings);
+        let mut headers = self.config.headers.clone();
+        headers.extend(settings.headers.iter().cloned());
+
+        let (response, response_headers) = utils::post_json_to_api(
+            self.config.transport.as_ref(),
+            &url,
+            &headers,
+            &body,
+            parse_mistral_error_payload,
+        )
+        .await?;
+
+        let choice = response["choices"].get(0).ok_or_else(|| {
+            ModelError::NoContent("Mistral response did not contain any choices".to_string())
+        })?;
+        let message = &choice["message"];
+
+        let mut text = String::new();
+        let mut reasoning = Vec::new();
+        match &message["content"] {
+            JSONValue::String(content) => text.push_str(content),
+            JSONValue::Array(chunks) => {
+                for chunk in chunks {
+                    match chunk["type"].as_str() {
+                        Some("text") => text.push_str(chunk["text"].as_str().unwrap_or_default()),
+                        Some("thinking") => {
+                            let thinking = chunk["thinking"]
+                                .as_array()
+                                .into_iter()
+                                .flatten()
+                                .filter_map(|part| part["text"].as_str())
+                                .collect::<String>();
+                            reasoning.push(LanguageModelDoGenerateResponseReasoning::Text {
+                                text: thinking,
+                                signature: None,
+                            });
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|tool_call| LanguageModelFunctionToolCall {
+                tool_call_id: tool_call["id"].as_str().unwrap_or_default().to_string(),
+                tool_name: tool_call["function"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                args: match &tool_call["function"]["arguments"] {
+                    JSONValue::String(args) => args.clone(),
+                    args => args.to_string(),
+                },
+            })
+            .collect();
+
+        let usage = &response["usage"];
+        let prompt_tokens = usage["prompt_tokens"].as_u64().unwrap_or_default() as u32;
+        let completion_tokens = usage["completion_tokens"].as_u64().unwrap_or_default() as u32;
+
+        Ok(LanguageModelDoGenerateResponse {
+            text: (!text.is_empty()).then_some(text),
+            reasoning,
+            tool_calls,
+            finish_reason: map_mistral_finish_reason(choice["finish_reason"].as_str()),
+            usage: LanguageModelUsage::new(prompt_tokens, completion_tokens),
+            response: Some(LanguageModelResponseMetadata {
+                id: response["id"].as_str().unwrap_or_default().to_string(),
+                timestamp: response["created"]
+                    .as_u64()
+                    .unwrap_or_else(utils::current_timestamp),
+                model_id: response["model"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| self.model_id.to_string()),
+                headers: response_headers,
+            }),
+            request_body: Some(LanguageModelRequestMetadata {
+                body: Some(body.to_string()),
+                call_settings: Some(settings),
+            }),
+            warnings,
+            ..Default::default()
+        })
+    }
+}
+
+/// Mistral errors are `{"object": "error", "message", "type", "code"}`, where
+/// validation errors carry a `{"detail": [{"msg"}]}` object as the message.
+fn parse_mistral_error_payload(body: &JSONValue) -> ApiErrorPayload {
+    let message = match &body["message"] {
+        JSONValue::String(message) => Some(message.clone()),
+        JSONValue::Object(message) => message.get("detail").map(|detail| match detail {
+            JSONValue::Array(details) => details
+                .iter()
+                .map(|detail| detail["msg"].as_str().unwrap_or_default())
+                .collect::<Vec<_>>()
+                .join("; "),
+            detail => detail.to_string(),
+        }),
+        _ => body["detail"].as_str().map(str::to_string),
+    };
+    let code = match &body["code"] {
+        JSONValue::String(code) => Some(code.clone()),
+        JSONValue::Number(code) => Some(code.to_string()),
+        _ => body["type"].as_str().map(str::to_string),
+    };
+    ApiErrorPayload { code, message }
+}
+
+fn map_mistral_finish_reason(finish_reason: Option<&str>) -> LanguageModelFinishReason {
+    match finish_reason {
+        Some("stop") => LanguageModelFinishReason::Stop,
+        Some("length") | Some("model_length") => LanguageModelFinishReason::Length,
+        Some("tool_calls") => LanguageModelFinishReason::ToolCalls,
+        Some("error") => LanguageModelFinishReason::Error,
+        Some(_) => LanguageModelFinishReason::Other,
+        None => LanguageModelFinishReason::Unknown,
+    }
+}
+
+fn convert_to_mistral_messages(prompt: &[LanguageModelMessage]) -> Result<JSONValue, ModelError> {
+    let mut messages = Vec::new();
+    for message in prompt {
+        match message {
+            LanguageModelMessage::System(content) => {
+                messages.push(json!({ "role": "system", "content": content }));
+            }
+            LanguageModelMessage::User(parts) => {
+                let content = parts
+                    .iter()
+                    .map(|part| match part {
+                        LanguageModelUserMessage::Text(part) => {
+                            Ok(json!({ "type": "text", "text": part.text }))
+                        }
+                        LanguageModelUserMessage::Image(part) => Ok(
+                            json!({ "type": "image_url", "image_url": utils::image_part_to_url(part) }),
+                        ),
+                        LanguageModelUserMessage::File(part) => {
+                            if part.mime_type.as_deref() != Some("application/pdf") {
+                                return Err(ModelError::NotSupported(format!(
+                                    "Mistral only supports PDF files, got {}",
+                                    part.mime_type.as_deref().unwrap_or("unknown mime type")
+                                )));
+                            }
+                            let document_url = match &part.file_content {
+                                LanguageModelFilePartContent::Url(url) => url.clone(),
+                                LanguageModelFilePartContent::Base64(data) => {
+                                    format!("data:application/pdf;base64,{data}")
+                                }
+                            };
+                            Ok(json!({ "type": "document_url", "document_url": document_url }))
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                messages.push(json!({ "role": "user", "content": content }));
+            }
+            LanguageModelMessage::Assistant(parts) => {
+                let mut text = String::new();
+                let mut tool_calls = Vec::new();
+                for part in parts {
+                    match part {
+                        LanguageModelAssistantMessage::Text(part) => text.push_str(&part.text),
+                        LanguageModelAssistantMessage::ToolCall(part) => tool_calls.push(json!({
+                            "id": part.tool_call_id,
+                            "type": "function",
+                            "function": {
+                                "name": part.tool_name,
+                                "arguments": part.args.to_string(),
+                            }
+                        })),
+                        _ => {}
+                    }
+                }
+                let mut assistant = json!({ "role": "assistant", "content": text });
+                if !tool_calls.is_empty() {
+                    assistant["tool_calls"] = JSONValue::Array(tool_calls);
+                }
+                messages.push(assistant);
+            }
+            LanguageModelMessage::Tool(results) => {
+                for result in results {
+                    messages.push(json!({
+                        "role": "tool",
+                        "name": result.tool_name,
+                        "tool_call_id": result.tool_call_id,
+                        "content": result.result.to_string(),
+                    }));
+                }
+            }
+        }
+    }
+    Ok(JSONValue::Array(messages))
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        model::{
+            call_settings::LanguageModelCallSettings,
+            finish_reason::LanguageModelFinishReason,
+            message::{LanguageModelMessage, LanguageModelTextPart, LanguageModelUserMessage},
+            tools::{LanguageModelFunctionTool, LanguageModelToolChoice},
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat,
+        },
+        provider::LanguageModelProvider,
+        providers::mistral::{provider_settings::MistralProviderSettings, MistralProvider},
+    };
+
+    fn request(tools: Option<Vec<LanguageModelFunctionTool>>) -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest {
+            call_settings: Some(LanguageModelCallSettings::default()),
+            input_format: LanguageModelDoGenerateRequestInputFormat::Messages,
+            prompt: vec![
+                LanguageModelMessage::System("You are a helpful assistant.".to_string()),
+                LanguageModelMessage::User(vec![LanguageModelUserMessage::Text(
+                    LanguageModelTextPart {
+                        text: "What is the weather in Kathmandu?".to_string(),
+                        provider_metadata: None,
+                    },
+                )]),
+            ],
+            tool_choice: tools.as_ref().map(|_| LanguageModelToolChoice::Required),
+            tools,
+            provider_metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_mistral_generate_text() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "mistral-small-latest",
+                "safe_prompt": true,
+                "max_tokens": 1024,
+                "temperature": 0.5,
+                "messages": [
+                    { "role": "system", "content": "You are a helpful assistant." },
+                    {
+                        "role": "user",
+                        "content": [{ "type": "text", "text": "What is the weather in Kathmandu?" }]
+                    }
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "cmpl-1",
+                "object": "chat.completion",
+                "created": 1717000000,
+                "model": "mistral-small-latest",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "It is sunny." },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 4, "total_tokens": 16 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = MistralProvider::new(
+            MistralProviderSettings::new("test-key".to_string())
+                .base_url(&server.uri())
+                .default_settings(LanguageModelCallSettings {
+                    max_tokens: Some(1024),
+                    temperature: Some(0.2),
+                    ..Default::default()
+                }),
+        );
+        let model = provider
+            .language_model("mistral-small-latest")
+            .unwrap()
+            .with_safe_prompt(true);
+
+        let mut request = request(None);
+        *request.call_settings_mut() = Some(LanguageModelCallSettings {
+            temperature: Some(0.5),
+            ..Default::default()
+        });
+        let response = model.do_generate(request).await.unwrap();
+        let settings = response
+            .request_body
+            .as_ref()
+            .and_then(|request| request.call_settings.as_ref())
+            .unwrap();
+        assert_eq!(settings.max_tokens, Some(1024));
+        assert_eq!(settings.temperature, Some(0.5));
+        assert_eq!(response.text.as_deref(), Some("It is sunny."));
+        assert_eq!(response.finish_reason, LanguageModelFinishReason::Stop);
+        assert_eq!(response.usage.total_tokens, 16);
+        let metadata = response.response.unwrap();
+        assert_eq!(metadata.id, "cmpl-1");
+        assert_eq!(metadata.timestamp, 1717000000);
+    }
+
+    #[tokio::test]
+    async fn test_mistral_tool_calls() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({
+                "tool_choice": "any",
+                "tools": [{
+                    "type": "function",
+                    "function": {
+                        "name": "weather",
+                        "description": "Get the weather for a city",
+                        "parameters": { "type": "object", "properties": { "city": { "type": "string" } } }
+                    }
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "cmpl-2",
+                "created": 1717000000,
+                "model": "mistral-small-latest",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "",
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "function": { "name": "weather", "arguments": "{\"city\":\"Kathmandu\"}" }
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }],
+                "usage": { "prompt_tokens": 20, "completion_tokens": 10, "total_tokens": 30 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = MistralProvider::new(
+            MistralProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let model = provider.language_model("mistral-small-latest").unwrap();
+
+        let response = model
+            .do_generate(request(Some(vec![LanguageModelFunctionTool {
+                name: "weather".to_string(),
+                description: Some("Get the weather for a city".to_string()),
+                parameters: json!({
+                    "type": "object",
+                    "properties": { "city": { "type": "string" } }
+                }),
+            }])))
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, None);
+        assert_eq!(response.finish_reason, LanguageModelFinishReason::ToolCalls);
+        assert_eq!(response.tool_calls.len(), 1);
+        assert_eq!(response.tool_calls[0].tool_call_id, "call_1");
+        assert_eq!(response.tool_calls[0].tool_name, "weather");
+        assert_eq!(response.tool_calls[0].args, "{\"city\":\"Kathmandu\"}");
+    }
+}
@@ -0,0 +1,95 @@
+use core::fmt;
+use std::str::FromStr;
+
+use crate::model::pricing::ModelPricing;
+
+/// https://docs.mistral.ai/getting-started/models/models_overview/
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MistralChatModelId {
+    // Premier models
+    MistralLargeLatest,
+    MistralMediumLatest,
+    MistralSmallLatest,
+    PixtralLargeLatest,
+    CodestralLatest,
+    MagistralMediumLatest,
+    MagistralSmallLatest,
+
+    // Edge models
+    Ministral3bLatest,
+    Ministral8bLatest,
+
+    // Open models
+    OpenMistralNemo,
+    PixtralTwelveB2409,
+
+    Custom(String),
+}
+
+impl MistralChatModelId {
+    /// List price of the model, `None` for custom models.
+    ///
+    /// https://mistral.ai/pricing#api-pricing
+    pub fn pricing(&self) -> Option<ModelPricing> {
+        let pricing = match self {
+            Self::MistralLargeLatest | Self::PixtralLargeLatest => ModelPricing::new(2.0, 6.0),
+            Self::MistralMediumLatest => ModelPricing::new(0.4, 2.0),
+            Self::MistralSmallLatest => ModelPricing::new(0.1, 0.3),
+            Self::CodestralLatest => ModelPricing::new(0.3, 0.9),
+            Self::MagistralMediumLatest => ModelPricing::new(2.0, 5.0),
+            Self::MagistralSmallLatest => ModelPricing::new(0.5, 1.5),
+            Self::Ministral3bLatest => ModelPricing::new(0.04, 0.04),
+            Self::Ministral8bLatest => ModelPricing::new(0.1, 0.1),
+            Self::OpenMistralNemo | Self::PixtralTwelveB2409 => ModelPricing::new(0.15, 0.15),
+            Self::Custom(_) => return None,
+        };
+        Some(pricing)
+    }
+}
+
+impl FromStr for MistralChatModelId {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "mistral-large-latest" => Ok(Self::MistralLargeLatest),
+            "mistral-medium-latest" => Ok(Self::MistralMediumLatest),
+            "mistral-small-latest" => Ok(Self::MistralSmallLatest),
+            "pixtral-large-latest" => Ok(Self::PixtralLargeLatest),
+            "codestral-latest" => Ok(Self::CodestralLatest),
+            "magistral-medium-latest" => Ok(Self::MagistralMediumLatest),
+            "magistral-small-latest" => Ok(Self::MagistralSmallLatest),
+
+            "ministral-3b-latest" => Ok(Self::Ministral3bLatest),
+            "ministral-8b-latest" => Ok(Self::Ministral8bLatest),
+
+            "open-mistral-nemo" => Ok(Self::OpenMistralNemo),
+            "pixtral-12b-2409" => Ok(Self::PixtralTwelveB2409),
+
+            // Handle custom model IDs
+            _ => Ok(Self::Custom(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for MistralChatModelId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MistralLargeLatest => write!(f, "mistral-large-latest"),
+            Self::MistralMediumLatest => write!(f, "mistral-medium-latest"),
+            Self::MistralSmallLatest => write!(f, "mistral-small-latest"),
+            Self::PixtralLargeLatest => write!(f, "pixtral-large-latest"),
+            Self::CodestralLatest => write!(f, "codestral-latest"),
+            Self::MagistralMediumLatest => write!(f, "magistral-medium-latest"),
+            Self::MagistralSmallLatest => write!(f, "magistral-small-latest"),
+
+            Self::Ministral3bLatest => write!(f, "ministral-3b-latest"),
+            Self::Ministral8bLatest => write!(f, "ministral-8b-latest"),
+
+            Self::OpenMistralNemo => write!(f, "open-mistral-nemo"),
+            Self::PixtralTwelveB2409 => write!(f, "pixtral-12b-2409"),
+
+            Self::Custom(s) => write!(f, "{}", s),
+        }
+    }
+}
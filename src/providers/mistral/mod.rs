@@ -0,0 +1,72 @@
+pub mod chat_model;
+pub mod provider_settings;
+
+use crate::{errors::ProviderError, provider::LanguageModelProvider};
+use chat_model::{model_id::MistralChatModelId, MistralChatConfig, MistralChatModel};
+use provider_settings::MistralProviderSettings;
+use std::str::FromStr;
+
+pub struct MistralProvider {
+    pub settings: MistralProviderSettings,
+}
+
+impl MistralProvider {
+    pub fn new(settings: MistralProviderSettings) -> Self {
+        MistralProvider { settings }
+    }
+
+    pub fn create_chat_model(
+        &self,
+        model_id: MistralChatModelId,
+    ) -> Result<MistralChatModel, ProviderError> {
+        Ok(MistralChatModel::new(
+            model_id,
+            MistralChatConfig {
+                provider: format!("{}.chat", self.settings.name),
+                base_url: self.settings.base_url.clone(),
+                headers: self.get_headers()?,
+                transport: self.settings.transport.clone(),
+                default_settings: self.settings.default_settings.clone(),
+            },
+        ))
+    }
+}
+
+impl Default for MistralProvider {
+    fn default() -> Self {
+        MistralProvider::new(MistralProviderSettings::default())
+    }
+}
+
+impl LanguageModelProvider for MistralProvider {
+    type Model = MistralChatModel;
+    fn language_model(&self, model_id: &str) -> Result<Self::Model, ProviderError> {
+        let model_id = model_id.trim();
+        if model_id.is_empty() {
+            return Err(ProviderError::InvalidModelId(
+                "Provided an empty Mistral model id".to_string(),
+            ));
+        }
+        let mistral_model_id = MistralChatModelId::from_str(model_id).map_err(|_| {
+            ProviderError::InvalidModelId(format!(
+                "Provided an invalid Mistral model id, {model_id}"
+            ))
+        })?;
+
+        self.create_chat_model(mistral_model_id)
+    }
+
+    fn get_headers(&self) -> Result<Vec<(String, String)>, ProviderError> {
+        let mut headers = vec![
+            (
+                "Authorization".to_string(),
+                format!("Bearer {}", self.settings.api_key),
+            ),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ];
+        if let Some(extra) = &self.settings.headers {
+            headers.extend(extra.iter().cloned());
+        }
+        Ok(headers)
+    }
+}
@@ -1 +1,4 @@
+pub mod cohere;
+pub mod mistral;
 pub mod openai;
+pub mod openrouter;
@@ -1,12 +1,38 @@
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use serde_json::{json, Map, Value as JSONValue};
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::Arc,
+};
+
 use crate::{
+    errors::parse_openai_error_payload,
     model::{
-        LanguageModel, LanguageModelCall, LanguageModelDoGenerateRequest,
-        LanguageModelDoGenerateResponse,
+        call_settings::{LanguageModelCallSettings, LanguageModelCallSettingsResponseFormat},
+        call_warning::LanguageModelCallWarning,
+        finish_reason::LanguageModelFinishReason,
+        function_tool_call::LanguageModelFunctionToolCall,
+        message::{
+            LanguageModelAssistantMessage, LanguageModelFilePartContent, LanguageModelMessage,
+            LanguageModelUserMessage,
+        },
+        request_metadata::LanguageModelRequestMetadata,
+        response_metadata::LanguageModelResponseMetadata,
+        stream_part::LanguageModelStreamPart,
+        tools::LanguageModelToolChoice,
+        usage::LanguageModelUsage,
+        LanguageModel, LanguageModelDoGenerateRequest, LanguageModelDoGenerateResponse,
+        LanguageModelDoGenerateResponseReasoning, LanguageModelDoStreamResponse,
     },
-    providers::openai::ModelError,
+    providers::openai::{provider_settings::OpenAIProviderSettingsCompatibility, ModelError},
+    transport::HttpTransport,
+    utils,
 };
+use model_id::{OpenAIChatModelId, OpenAISystemMessageMode};
 pub mod model_id;
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpenAIChatSettingsReasoningEffort {
     /// No reasoning effort, the model will not perform any reasoning.
     None,
@@ -21,7 +47,30 @@ pub enum OpenAIChatSettingsReasoningEffort {
     High,
 }
 
+impl OpenAIChatSettingsReasoningEffort {
+    fn as_str(&self) -> &'static str {
+        match self {
+            Self::None => "none",
+            Self::Low => "low",
+            Self::Medium => "medium",
+            Self::High => "high",
+        }
+    }
+}
+
+/// Connection details handed to the chat model by the provider.
+pub struct OpenAIChatConfig {
+    pub provider: String,
+    pub base_url: String,
+    pub headers: Vec<(String, String)>,
+    pub compatibility: OpenAIProviderSettingsCompatibility,
+    pub transport: Arc<dyn HttpTransport>,
+    pub default_settings: LanguageModelCallSettings,
+}
+
 pub struct OpenAIChatModel {
+    pub model_id: OpenAIChatModelId,
+    pub config: OpenAIChatConfig,
     /// Modify the likelihood of specified tokens appearing in the completion.
     ///
     /// Accepts a JSON object that maps tokens (specified by their token ID in
@@ -61,28 +110,33 @@ pub struct OpenAIChatModel {
     ///
     /// Defaults to `false`.
     pub download_images: bool,
-    /// Reasoning effort for reasoning models. Defaults to `medium`.
-    pub reasoning_effort: OpenAIChatSettingsReasoningEffort,
+    /// Reasoning effort for reasoning models. Defaults to `medium` for models
+    /// that accept it. An explicit effort is dropped with a warning for models
+    /// that do not.
+    pub reasoning_effort: Option<OpenAIChatSettingsReasoningEffort>,
+    /// Send reasoning from previous assistant turns back as `reasoning_content`.
+    /// Some OpenAI compatible reasoning servers require it, e.g. during tool
+    /// calls, while others reject it.
+    ///
+    /// Defaults to `false`.
+    pub send_reasoning: bool,
 }
 
-impl Default for OpenAIChatModel {
-    fn default() -> Self {
+impl OpenAIChatModel {
+    pub fn new(model_id: OpenAIChatModelId, config: OpenAIChatConfig) -> Self {
         OpenAIChatModel {
+            model_id,
+            config,
             logit_bias: None,
             log_probs: None,
             parallel_calls: true,
             structured_output: false,
             user: None,
             download_images: false,
-            reasoning_effort: OpenAIChatSettingsReasoningEffort::Medium,
+            reasoning_effort: None,
+            send_reasoning: false,
         }
     }
-}
-
-impl OpenAIChatModel {
-    pub fn new() -> Self {
-        OpenAIChatModel::default()
-    }
 
     pub fn generate() -> Result<(), ModelError> {
         Err(ModelError::NotSupported(
@@ -124,20 +178,1226 @@ impl OpenAIChatModel {
         mut self,
         reasoning_effort: OpenAIChatSettingsReasoningEffort,
     ) -> Self {
-        self.reasoning_effort = reasoning_effort;
+        self.reasoning_effort = Some(reasoning_effort);
         self
     }
+
+    pub fn with_send_reasoning(mut self, send_reasoning: bool) -> Self {
+        self.send_reasoning = send_reasoning;
+        self
+    }
+
+    fn is_compatible(&self) -> bool {
+        self.config.compatibility == OpenAIProviderSettingsCompatibility::COMPATIBLE
+    }
+
+    /// Builds the chat completions request body. Shared with providers that
+    /// speak the OpenAI chat protocol, such as OpenRouter.
+    pub(crate) fn get_args(
+        &self,
+        request: &LanguageModelDoGenerateRequest,
+    ) -> Result<(Map<String, JSONValue>, Vec<LanguageModelCallWarning>), ModelError> {
+        let capabilities = self.model_id.capabilities();
+        let mut warnings = Vec::new();
+        let mut body = Map::new();
+        body.insert("model".into(), json!(self.model_id.to_string()));
+        body.insert(
+            "messages".into(),
+            convert_to_openai_chat_messages(
+                &request.prompt,
+                self.send_reasoning,
+                capabilities.system_message_mode,
+                &mut warnings,
+            )?,
+        );
+
+        if let Some(logit_bias) = &self.logit_bias {
+            let logit_bias: Map<String, JSONValue> = logit_bias
+                .iter()
+                .map(|(token, bias)| (token.clone(), json!(bias)))
+                .collect();
+            body.insert("logit_bias".into(), JSONValue::Object(logit_bias));
+        }
+        if let Some(log_probs) = self.log_probs {
+            body.insert("logprobs".into(), json!(true));
+            if log_probs > 0 {
+                body.insert("top_logprobs".into(), json!(log_probs));
+            }
+        }
+        if let Some(user) = &self.user {
+            body.insert("user".into(), json!(user));
+        }
+        if capabilities.supports_reasoning_effort {
+            let reasoning_effort = self
+                .reasoning_effort
+                .unwrap_or(OpenAIChatSettingsReasoningEffort::Medium);
+            body.insert("reasoning_effort".into(), json!(reasoning_effort.as_str()));
+        } else if self.reasoning_effort.is_some() {
+            warnings.push(LanguageModelCallWarning::UnsupportedSetting {
+                setting: "reasoning_effort".to_string(),
+                details: Some(format!(
+                    "reasoning_effort is not supported by {}",
+                    self.model_id
+                )),
+            });
+        }
+
+        let settings = request.call_settings_with_defaults(&self.config.default_settings);
+        if let Some(max_tokens) = settings.max_tokens {
+            // Reasoning models count reasoning tokens against the limit and
+            // reject `max_tokens`.
+            let key = if capabilities.supports_reasoning {
+                "max_completion_tokens"
+            } else {
+                "max_tokens"
+            };
+            body.insert(key.into(), json!(max_tokens));
+        }
+        let sampling_settings = [
+            ("temperature", settings.temperature),
+            ("top_p", settings.top_p),
+            ("presence_penalty", settings.presence_penalty),
+            ("frequency_penalty", settings.frequency_penalty),
+        ];
+        for (setting, value) in sampling_settings {
+            let Some(value) = value else { continue };
+            if capabilities.supports_temperature {
+                body.insert(setting.into(), json!(value));
+            } else {
+                warnings.push(LanguageModelCallWarning::UnsupportedSetting {
+                    setting: setting.to_string(),
+                    details: Some(format!("{setting} is not supported by {}", self.model_id)),
+                });
+            }
+        }
+        if settings.top_k.is_some() {
+            warnings.push(LanguageModelCallWarning::UnsupportedSetting {
+                setting: "top_k".to_string(),
+                details: None,
+            });
+        }
+        if let Some(stop_sequences) = &settings.stop_sequences {
+            body.insert("stop".into(), json!(stop_sequences));
+        }
+        if let Some(seed) = settings.seed {
+            body.insert("seed".into(), json!(seed));
+        }
+        if let Some(LanguageModelCallSettingsResponseFormat::Json) = settings.response_format {
+            body.insert("response_format".into(), json!({ "type": "json_object" }));
+        }
+
+        let tools = request.tools.as_ref().filter(|tools| !tools.is_empty());
+        if tools.is_some() && !capabilities.supports_tools {
+            warnings.push(LanguageModelCallWarning::UnsupportedSetting {
+                setting: "tools".to_string(),
+                details: Some(format!("tools are not supported by {}", self.model_id)),
+            });
+        }
+        if let Some(tools) = tools.filter(|_| capabilities.supports_tools) {
+            let tools: Vec<JSONValue> = tools
+                .iter()
+                .map(|tool| {
+                    let mut function = json!({
+                        "name": tool.name,
+                        "description": tool.description,
+                        "parameters": tool.parameters,
+                    });
+                    if self.structured_output {
+                        function["strict"] = json!(true);
+                    }
+                    json!({ "type": "function", "function": function })
+                })
+                .collect();
+            body.insert("tools".into(), JSONValue::Array(tools));
+            if !self.parallel_calls {
+                body.insert("parallel_tool_calls".into(), json!(false));
+            }
+
+            if let Some(tool_choice) = &request.tool_choice {
+                let tool_choice = match tool_choice {
+                    LanguageModelToolChoice::Auto => json!("auto"),
+                    LanguageModelToolChoice::None => json!("none"),
+                    LanguageModelToolChoice::Required => json!("required"),
+                    LanguageModelToolChoice::Tool(name) => {
+                        json!({ "type": "function", "function": { "name": name } })
+                    }
+                };
+                body.insert("tool_choice".into(), tool_choice);
+            }
+        }
+
+        Ok((body, warnings))
+    }
+
+    /// Sends a chat completions request body to the configured endpoint.
+    pub(crate) async fn post_chat_completion(
+        &self,
+        request: &LanguageModelDoGenerateRequest,
+        body: &JSONValue,
+    ) -> Result<(JSONValue, Vec<(String, String)>), ModelError> {
+        let url = format!("{}/chat/completions", self.config.base_url);
+        let mut headers = self.config.headers.clone();
+        headers.extend(
+            request
+                .call_settings_with_defaults(&self.config.default_settings)
+                .headers,
+        );
+        utils::post_json_to_api(
+            self.config.transport.as_ref(),
+            &url,
+            &headers,
+            body,
+            parse_openai_error_payload,
+        )
+        .await
+    }
+
+    /// The sent body together with the effective call settings.
+    pub(crate) fn request_metadata(
+        &self,
+        request: &LanguageModelDoGenerateRequest,
+        body: &JSONValue,
+    ) -> LanguageModelRequestMetadata {
+        LanguageModelRequestMetadata {
+            body: Some(body.to_string()),
+            call_settings: Some(request.call_settings_with_defaults(&self.config.default_settings)),
+        }
+    }
+
+    /// Maps a chat completions response body into a generate response.
+    pub(crate) fn parse_response(
+        &self,
+        response: &JSONValue,
+        response_headers: Vec<(String, String)>,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let choice = response["choices"].get(0).ok_or_else(|| {
+            ModelError::NoContent(format!(
+                "{} response did not contain any choices",
+                self.config.provider
+            ))
+        })?;
+        let message = &choice["message"];
+
+        let tool_calls = message["tool_calls"]
+            .as_array()
+            .into_iter()
+            .flatten()
+            .map(|tool_call| LanguageModelFunctionToolCall {
+                tool_call_id: tool_call["id"].as_str().unwrap_or_default().to_string(),
+                tool_name: tool_call["function"]["name"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+                args: tool_call["function"]["arguments"]
+                    .as_str()
+                    .unwrap_or_default()
+                    .to_string(),
+            })
+            .collect();
+
+        let reasoning = if self.is_compatible() {
+            reasoning_text(message)
+                .map(|text| LanguageModelDoGenerateResponseReasoning::Text {
+                    text: text.to_string(),
+                    signature: None,
+                })
+                .into_iter()
+                .collect()
+        } else {
+            Vec::new()
+        };
+
+        Ok(LanguageModelDoGenerateResponse {
+            text: message["content"]
+                .as_str()
+                .filter(|text| !text.is_empty())
+                .map(str::to_string),
+            reasoning,
+            tool_calls,
+            finish_reason: map_openai_finish_reason(choice["finish_reason"].as_str()),
+            usage: parse_openai_usage(&response["usage"]),
+            response: Some(LanguageModelResponseMetadata {
+                id: response["id"].as_str().unwrap_or_default().to_string(),
+                timestamp: response["created"]
+                    .as_u64()
+                    .unwrap_or_else(utils::current_timestamp),
+                model_id: response["model"]
+                    .as_str()
+                    .map(str::to_string)
+                    .unwrap_or_else(|| self.model_id.to_string()),
+                headers: response_headers,
+            }),
+            ..Default::default()
+        })
+    }
+
+    /// Sends a chat completions request body with streaming enabled and maps
+    /// the returned chunks into stream parts.
+    pub(crate) async fn stream_chat_completion(
+        &self,
+        request: &LanguageModelDoGenerateRequest,
+        mut body: Map<String, JSONValue>,
+        warnings: Vec<LanguageModelCallWarning>,
+        metadata: Option<OpenAIChatStreamMetadata>,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        body.insert("stream".into(), json!(true));
+        // Only OpenAI itself is known to accept `stream_options`.
+        if !self.is_compatible() {
+            body.insert("stream_options".into(), json!({ "include_usage": true }));
+        }
+        let body = JSONValue::Object(body);
+
+        let url = format!("{}/chat/completions", self.config.base_url);
+        let mut headers = self.config.headers.clone();
+        headers.extend(
+            request
+                .call_settings_with_defaults(&self.config.default_settings)
+                .headers,
+        );
+        let (events, response_headers) = utils::post_json_to_api_stream(
+            self.config.transport.as_ref(),
+            &url,
+            &headers,
+            &body,
+            parse_openai_error_payload,
+        )
+        .await?;
+
+        let state = OpenAIChatStreamState::new(self.is_compatible(), metadata);
+        let stream = stream::unfold(
+            (events, state, false),
+            |(mut events, mut state, finished)| async move {
+                if finished {
+                    return None;
+                }
+                match events.next().await {
+                    Some(Ok(chunk)) => {
+                        let parts = state.process_chunk(&chunk);
+                        Some((parts, (events, state, false)))
+                    }
+                    Some(Err(e)) => Some((vec![Err(e)], (events, state, true))),
+                    None => Some((state.finish(), (events, state, true))),
+                }
+            },
+        )
+        .flat_map(stream::iter)
+        .boxed();
+
+        Ok(LanguageModelDoStreamResponse {
+            stream,
+            request_body: Some(self.request_metadata(request, &body)),
+            response_headers,
+            warnings,
+        })
+    }
 }
 
+#[async_trait]
 impl LanguageModel for OpenAIChatModel {
-    fn do_generate(
+    fn provider(&self) -> String {
+        self.config.provider.clone()
+    }
+
+    fn model_id(&self) -> String {
+        self.model_id.to_string()
+    }
+
+    async fn do_generate(
         &self,
-        model_call: LanguageModelDoGenerateRequest,
+        request: LanguageModelDoGenerateRequest,
     ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
-        todo!()
+        let (body, warnings) = self.get_args(&request)?;
+        let body = JSONValue::Object(body);
+        let (response, response_headers) = self.post_chat_completion(&request, &body).await?;
+
+        let mut result = self.parse_response(&response, response_headers)?;
+        result.warnings = warnings;
+        result.request_body = Some(self.request_metadata(&request, &body));
+        Ok(result)
+    }
+
+    async fn do_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let (body, warnings) = self.get_args(&request)?;
+        self.stream_chat_completion(&request, body, warnings, None)
+            .await
     }
 
     fn supports_urls(&self, url: String) -> bool {
-        todo!()
+        !self.download_images && (url.starts_with("http://") || url.starts_with("https://"))
+    }
+
+    /// The chat API only accepts inline file data.
+    fn supports_file_urls(&self, _url: String) -> bool {
+        false
+    }
+}
+
+/// Reads provider specific details from every raw chunk of a stream. They are
+/// reported in the `provider_metadata` of the `Finish` part, under `key`.
+pub(crate) struct OpenAIChatStreamMetadata {
+    pub(crate) key: &'static str,
+    pub(crate) collect: fn(&JSONValue, &mut HashMap<String, JSONValue>),
+}
+
+/// Tool call whose arguments are still being streamed.
+struct OpenAIChatStreamToolCall {
+    id: String,
+    name: String,
+    args: String,
+}
+
+struct OpenAIChatStreamState {
+    compatible: bool,
+    is_first_chunk: bool,
+    /// Keyed by the index the server assigned, which need not start at 0.
+    tool_calls: BTreeMap<usize, OpenAIChatStreamToolCall>,
+    finish_reason: LanguageModelFinishReason,
+    usage: LanguageModelUsage,
+    metadata: Option<(OpenAIChatStreamMetadata, HashMap<String, JSONValue>)>,
+}
+
+impl OpenAIChatStreamState {
+    fn new(compatible: bool, metadata: Option<OpenAIChatStreamMetadata>) -> Self {
+        OpenAIChatStreamState {
+            compatible,
+            is_first_chunk: true,
+            tool_calls: BTreeMap::new(),
+            finish_reason: LanguageModelFinishReason::Unknown,
+            usage: LanguageModelUsage::default(),
+            metadata: metadata.map(|metadata| (metadata, HashMap::new())),
+        }
+    }
+
+    fn process_chunk(
+        &mut self,
+        chunk: &JSONValue,
+    ) -> Vec<Result<LanguageModelStreamPart, ModelError>> {
+        let mut parts = Vec::new();
+        if let Some(error) = chunk.get("error") {
+            self.finish_reason = LanguageModelFinishReason::Error;
+            let message = error["message"].as_str().map(str::to_string);
+            parts.push(Ok(LanguageModelStreamPart::Error(
+                message.unwrap_or_else(|| error.to_string()),
+            )));
+            return parts;
+        }
+
+        if self.is_first_chunk {
+            self.is_first_chunk = false;
+            parts.push(Ok(LanguageModelStreamPart::ResponseMetadata {
+                id: chunk["id"].as_str().map(str::to_string),
+                timestamp: chunk["created"].as_u64(),
+                model_id: chunk["model"].as_str().map(str::to_string),
+            }));
+        }
+
+        if chunk["usage"].is_object() {
+            self.usage = parse_openai_usage(&chunk["usage"]);
+        }
+        if let Some((metadata, collected)) = &mut self.metadata {
+            (metadata.collect)(chunk, collected);
+        }
+
+        let Some(choice) = chunk["choices"].get(0) else {
+            return parts;
+        };
+        if let Some(finish_reason) = choice["finish_reason"].as_str() {
+            self.finish_reason = map_openai_finish_reason(Some(finish_reason));
+        }
+
+        let delta = &choice["delta"];
+        if self.compatible {
+            if let Some(reasoning) = reasoning_text(delta) {
+                parts.push(Ok(LanguageModelStreamPart::ReasoningDelta(
+                    reasoning.to_string(),
+                )));
+            }
+        }
+        if let Some(text) = delta["content"].as_str().filter(|text| !text.is_empty()) {
+            parts.push(Ok(LanguageModelStreamPart::TextDelta(text.to_string())));
+        }
+
+        for tool_call_delta in delta["tool_calls"].as_array().into_iter().flatten() {
+            let id = tool_call_delta["id"].as_str();
+            let index = match tool_call_delta["index"].as_u64() {
+                Some(index) => index as usize,
+                // Without an index, a delta continues the last call unless it
+                // starts one with a new id.
+                None => match self.tool_calls.last_key_value() {
+                    Some((index, tool_call)) if id.is_none_or(|id| id == tool_call.id) => *index,
+                    last => last.map_or(0, |(index, _)| index + 1),
+                },
+            };
+            let tool_call =
+                self.tool_calls
+                    .entry(index)
+                    .or_insert_with(|| OpenAIChatStreamToolCall {
+                        id: id.unwrap_or_default().to_string(),
+                        name: tool_call_delta["function"]["name"]
+                            .as_str()
+                            .unwrap_or_default()
+                            .to_string(),
+                        args: String::new(),
+                    });
+            let args_text_delta = tool_call_delta["function"]["arguments"]
+                .as_str()
+                .unwrap_or_default();
+            if !args_text_delta.is_empty() {
+                tool_call.args.push_str(args_text_delta);
+                parts.push(Ok(LanguageModelStreamPart::ToolCallDelta {
+                    tool_call_id: tool_call.id.clone(),
+                    tool_name: tool_call.name.clone(),
+                    args_text_delta: args_text_delta.to_string(),
+                }));
+            }
+        }
+
+        parts
+    }
+
+    fn finish(&mut self) -> Vec<Result<LanguageModelStreamPart, ModelError>> {
+        let mut parts: Vec<_> = std::mem::take(&mut self.tool_calls)
+            .into_values()
+            .map(|tool_call| {
+                Ok(LanguageModelStreamPart::ToolCall(
+                    LanguageModelFunctionToolCall {
+                        tool_call_id: tool_call.id,
+                        tool_name: tool_call.name,
+                        args: tool_call.args,
+                    },
+                ))
+            })
+            .collect();
+        let provider_metadata = self
+            .metadata
+            .take()
+            .filter(|(_, collected)| !collected.is_empty())
+            .map(|(metadata, collected)| HashMap::from([(metadata.key.to_string(), collected)]));
+        parts.push(Ok(LanguageModelStreamPart::Finish {
+            finish_reason: self.finish_reason,
+            usage: self.usage.clone(),
+            provider_metadata,
+            logprobs: None,
+        }));
+        parts
+    }
+}
+
+/// Reasoning returned by OpenAI compatible reasoning servers: DeepSeek and
+/// vLLM use `reasoning_content`, others such as OpenRouter use `reasoning`.
+fn reasoning_text(message: &JSONValue) -> Option<&str> {
+    ["reasoning_content", "reasoning"]
+        .into_iter()
+        .filter_map(|key| message[key].as_str())
+        .find(|text| !text.is_empty())
+}
+
+fn parse_openai_usage(usage: &JSONValue) -> LanguageModelUsage {
+    let prompt_tokens = usage["prompt_tokens"].as_u64().unwrap_or_default() as u32;
+    let completion_tokens = usage["completion_tokens"].as_u64().unwrap_or_default() as u32;
+    let as_tokens = |value: &JSONValue| value.as_u64().map(|tokens| tokens as u32);
+    LanguageModelUsage {
+        cached_prompt_tokens: as_tokens(&usage["prompt_tokens_details"]["cached_tokens"]),
+        reasoning_tokens: as_tokens(&usage["completion_tokens_details"]["reasoning_tokens"]),
+        ..LanguageModelUsage::new(prompt_tokens, completion_tokens)
+    }
+}
+
+fn map_openai_finish_reason(finish_reason: Option<&str>) -> LanguageModelFinishReason {
+    match finish_reason {
+        Some("stop") => LanguageModelFinishReason::Stop,
+        Some("length") => LanguageModelFinishReason::Length,
+        Some("content_filter") => LanguageModelFinishReason::ContentFilter,
+        Some("function_call") | Some("tool_calls") => LanguageModelFinishReason::ToolCalls,
+        Some(_) => LanguageModelFinishReason::Other,
+        None => LanguageModelFinishReason::Unknown,
+    }
+}
+
+fn convert_to_openai_chat_messages(
+    prompt: &[LanguageModelMessage],
+    send_reasoning: bool,
+    system_message_mode: OpenAISystemMessageMode,
+    warnings: &mut Vec<LanguageModelCallWarning>,
+) -> Result<JSONValue, ModelError> {
+    let mut messages = Vec::new();
+    for message in prompt {
+        match message {
+            LanguageModelMessage::System(content) => match system_message_mode {
+                OpenAISystemMessageMode::System => {
+                    messages.push(json!({ "role": "system", "content": content }));
+                }
+                OpenAISystemMessageMode::Developer => {
+                    messages.push(json!({ "role": "developer", "content": content }));
+                }
+                OpenAISystemMessageMode::Remove => {
+                    warnings.push(LanguageModelCallWarning::Other {
+                        message: "system messages are removed for this model".to_string(),
+                    });
+                }
+            },
+            LanguageModelMessage::User(parts) => {
+                if let [LanguageModelUserMessage::Text(part)] = parts.as_slice() {
+                    messages.push(json!({ "role": "user", "content": part.text }));
+                    continue;
+                }
+                let content = parts
+                    .iter()
+                    .map(|part| match part {
+                        LanguageModelUserMessage::Text(part) => {
+                            Ok(json!({ "type": "text", "text": part.text }))
+                        }
+                        LanguageModelUserMessage::Image(part) => Ok(json!({
+                            "type": "image_url",
+                            "image_url": { "url": utils::image_part_to_url(part) }
+                        })),
+                        LanguageModelUserMessage::File(part) => {
+                            let mime_type = part.mime_type.as_deref().unwrap_or_default();
+                            let LanguageModelFilePartContent::Base64(data) = &part.file_content
+                            else {
+                                return Err(ModelError::NotSupported(
+                                    "File URLs are not supported by the OpenAI chat API"
+                                        .to_string(),
+                                ));
+                            };
+                            match mime_type {
+                                "audio/wav" | "audio/mpeg" | "audio/mp3" => Ok(json!({
+                                    "type": "input_audio",
+                                    "input_audio": {
+                                        "data": data,
+                                        "format": if mime_type == "audio/wav" { "wav" } else { "mp3" },
+                                    }
+                                })),
+                                "application/pdf" => Ok(json!({
+                                    "type": "file",
+                                    "file": {
+                                        "filename": "document.pdf",
+                                        "file_data": format!("data:application/pdf;base64,{data}"),
+                                    }
+                                })),
+                                _ => Err(ModelError::NotSupported(format!(
+                                    "File parts with mime type `{mime_type}` are not supported by the OpenAI chat API"
+                                ))),
+                            }
+                        }
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                messages.push(json!({ "role": "user", "content": content }));
+            }
+            LanguageModelMessage::Assistant(parts) => {
+                let mut text = String::new();
+                let mut reasoning = String::new();
+                let mut tool_calls = Vec::new();
+                for part in parts {
+                    match part {
+                        LanguageModelAssistantMessage::Text(part) => text.push_str(&part.text),
+                        LanguageModelAssistantMessage::Reasoning(part) => {
+                            reasoning.push_str(&part.text)
+                        }
+                        LanguageModelAssistantMessage::ToolCall(part) => tool_calls.push(json!({
+                            "id": part.tool_call_id,
+                            "type": "function",
+                            "function": {
+                                "name": part.tool_name,
+                                "arguments": part.args.to_string(),
+                            }
+                        })),
+                        _ => {}
+                    }
+                }
+                let mut assistant = json!({ "role": "assistant", "content": text });
+                if send_reasoning && !reasoning.is_empty() {
+                    assistant["reasoning_content"] = json!(reasoning);
+                }
+                if !tool_calls.is_empty() {
+                    assistant["tool_calls"] = JSONValue::Array(tool_calls);
+                }
+                messages.push(assistant);
+            }
+            LanguageModelMessage::Tool(results) => {
+                for result in results {
+                    messages.push(json!({
+                        "role": "tool",
+                        "tool_call_id": result.tool_call_id,
+                        "content": result.result.to_string(),
+                    }));
+                }
+            }
+        }
+    }
+    Ok(JSONValue::Array(messages))
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use crate::{
+        core::generate_text::{generate_text, GenerateTextOptions},
+        errors::ModelError,
+        model::{
+            call_settings::LanguageModelCallSettings,
+            call_warning::LanguageModelCallWarning,
+            finish_reason::LanguageModelFinishReason,
+            message::{
+                LanguageModelAssistantMessage, LanguageModelMessage, LanguageModelReasoningPart,
+                LanguageModelTextPart, LanguageModelUserMessage,
+            },
+            stream_part::LanguageModelStreamPart,
+            tools::{LanguageModelFunctionTool, LanguageModelToolChoice},
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat, LanguageModelDoGenerateResponseReasoning,
+        },
+        prompt::{CoreMessage, CoreUserMessage, FilePart, TextPart, UserContent, UserContentParts},
+        provider::LanguageModelProvider,
+        providers::openai::{
+            chat_model::{
+                model_id::{OpenAIChatModelId, OpenAISystemMessageMode},
+                OpenAIChatSettingsReasoningEffort, OpenAIChatStreamState,
+            },
+            provider_settings::{OpenAIProviderSettings, OpenAIProviderSettingsCompatibility},
+            OpenAIProvider,
+        },
+    };
+
+    fn text_request(prompt: Vec<LanguageModelMessage>) -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest {
+            call_settings: Some(LanguageModelCallSettings::default()),
+            input_format: LanguageModelDoGenerateRequestInputFormat::Messages,
+            prompt,
+            tools: None,
+            tool_choice: None,
+            provider_metadata: None,
+        }
+    }
+
+    fn user_message(text: &str) -> LanguageModelMessage {
+        LanguageModelMessage::User(vec![LanguageModelUserMessage::Text(
+            LanguageModelTextPart {
+                text: text.to_string(),
+                provider_metadata: None,
+            },
+        )])
+    }
+
+    fn compatible_provider(server: &MockServer) -> OpenAIProvider {
+        OpenAIProvider::new(
+            OpenAIProviderSettings::new("test-key".to_string())
+                .base_url(&server.uri())
+                .compatibility(OpenAIProviderSettingsCompatibility::COMPATIBLE),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_openai_chat_tool_calls() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "gpt-4o",
+                "messages": [
+                    { "role": "system", "content": "You are a helpful assistant." },
+                    { "role": "user", "content": "What is the weather in Kathmandu?" }
+                ],
+                "tool_choice": "required",
+                "tools": [{ "type": "function", "function": { "name": "weather" } }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "created": 1717000000,
+                "model": "gpt-4o-2024-08-06",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": null,
+                        "tool_calls": [{
+                            "id": "call_1",
+                            "type": "function",
+                            "function": { "name": "weather", "arguments": "{\"city\":\"Kathmandu\"}" }
+                        }]
+                    },
+                    "finish_reason": "tool_calls"
+                }],
+                "usage": { "prompt_tokens": 25, "completion_tokens": 7, "total_tokens": 32 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAIProvider::new(
+            OpenAIProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let model = provider.language_model("gpt-4o").unwrap();
+
+        let response = model
+            .do_generate(LanguageModelDoGenerateRequest {
+                call_settings: Some(LanguageModelCallSettings::default()),
+                input_format: LanguageModelDoGenerateRequestInputFormat::Messages,
+                prompt: vec![
+                    LanguageModelMessage::System("You are a helpful assistant.".to_string()),
+                    LanguageModelMessage::User(vec![LanguageModelUserMessage::Text(
+                        LanguageModelTextPart {
+                            text: "What is the weather in Kathmandu?".to_string(),
+                            provider_metadata: None,
+                        },
+                    )]),
+                ],
+                tools: Some(vec![LanguageModelFunctionTool {
+                    name: "weather".to_string(),
+                    description: None,
+                    parameters: json!({ "type": "object" }),
+                }]),
+                tool_choice: Some(LanguageModelToolChoice::Required),
+                provider_metadata: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.text, None);
+        assert_eq!(response.finish_reason, LanguageModelFinishReason::ToolCalls);
+        assert_eq!(response.usage.total_tokens, 32);
+        assert_eq!(response.tool_calls[0].tool_name, "weather");
+        assert_eq!(response.tool_calls[0].args, "{\"city\":\"Kathmandu\"}");
+        assert_eq!(response.response.unwrap().model_id, "gpt-4o-2024-08-06");
+    }
+
+    #[tokio::test]
+    async fn test_compatible_reasoning_content_round_trip() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({
+                "messages": [
+                    { "role": "user", "content": "Hi" },
+                    {
+                        "role": "assistant",
+                        "content": "Hello!",
+                        "reasoning_content": "The user greets me."
+                    },
+                    { "role": "user", "content": "What is 2 + 2?" }
+                ]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-2",
+                "created": 1717000000,
+                "model": "deepseek-reasoner",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "4",
+                        "reasoning_content": "2 + 2 equals 4."
+                    },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 20, "completion_tokens": 10, "total_tokens": 30 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = compatible_provider(&server)
+            .language_model("deepseek-reasoner")
+            .unwrap()
+            .with_send_reasoning(true);
+
+        let response = model
+            .do_generate(text_request(vec![
+                user_message("Hi"),
+                LanguageModelMessage::Assistant(vec![
+                    LanguageModelAssistantMessage::Reasoning(LanguageModelReasoningPart {
+                        text: "The user greets me.".to_string(),
+                        signature: None,
+                        provider_metadata: None,
+                    }),
+                    LanguageModelAssistantMessage::Text(LanguageModelTextPart {
+                        text: "Hello!".to_string(),
+                        provider_metadata: None,
+                    }),
+                ]),
+                user_message("What is 2 + 2?"),
+            ]))
+            .await
+            .unwrap();
+
+        assert_eq!(response.text.as_deref(), Some("4"));
+        assert!(matches!(
+            &response.reasoning[..],
+            [LanguageModelDoGenerateResponseReasoning::Text { text, .. }] if text == "2 + 2 equals 4."
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_compatible_stream_reasoning_deltas() {
+        let chunks = [
+            json!({ "id": "chatcmpl-3", "created": 1717000000, "model": "qwq-32b",
+                "choices": [{ "index": 0, "delta": { "role": "assistant", "reasoning_content": "Think" } }] }),
+            json!({ "id": "chatcmpl-3", "choices": [{ "index": 0, "delta": { "reasoning_content": "ing." } }] }),
+            json!({ "id": "chatcmpl-3", "choices": [{ "index": 0, "delta": { "content": "Kath" } }] }),
+            json!({ "id": "chatcmpl-3", "choices": [{ "index": 0, "delta": { "content": "mandu" } }] }),
+            json!({ "id": "chatcmpl-3", "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }],
+                "usage": { "prompt_tokens": 8, "completion_tokens": 5, "total_tokens": 13 } }),
+        ];
+        let body = chunks
+            .iter()
+            .map(|chunk| format!("data: {chunk}\n\n"))
+            .chain(["data: [DONE]\n\n".to_string()])
+            .collect::<String>();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "stream": true })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let model = compatible_provider(&server)
+            .language_model("qwq-32b")
+            .unwrap();
+        let response = model
+            .do_stream(text_request(vec![user_message(
+                "What is the capital of Nepal?",
+            )]))
+            .await
+            .unwrap();
+        assert!(!response
+            .request_body
+            .and_then(|request| request.body)
+            .unwrap()
+            .contains("stream_options"));
+
+        let parts: Vec<_> = response.stream.map(|part| part.unwrap()).collect().await;
+
+        let reasoning: String = parts
+            .iter()
+            .filter_map(|part| match part {
+                LanguageModelStreamPart::ReasoningDelta(delta) => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        let text: String = parts
+            .iter()
+            .filter_map(|part| match part {
+                LanguageModelStreamPart::TextDelta(delta) => Some(delta.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(reasoning, "Thinking.");
+        assert_eq!(text, "Kathmandu");
+        assert!(matches!(
+            parts.first(),
+            Some(LanguageModelStreamPart::ResponseMetadata { model_id: Some(model_id), .. })
+                if model_id == "qwq-32b"
+        ));
+        assert!(matches!(
+            parts.last(),
+            Some(LanguageModelStreamPart::Finish {
+                finish_reason: LanguageModelFinishReason::Stop,
+                usage,
+                ..
+            }) if usage.total_tokens == 13
+        ));
+    }
+
+    #[test]
+    fn test_stream_tool_call_indices() {
+        let mut state = OpenAIChatStreamState::new(true, None);
+        let tool_call = |index: Option<u64>, id: Option<&str>, arguments: &str| {
+            let mut delta = json!({
+                "id": id,
+                "function": { "name": id.map(|_| "weather"), "arguments": arguments }
+            });
+            if let Some(index) = index {
+                delta["index"] = json!(index);
+            }
+            json!({ "choices": [{ "index": 0, "delta": { "tool_calls": [delta] } }] })
+        };
+        let chunks = [
+            // Indices starting at 1 and sparse indices.
+            tool_call(Some(1), Some("call_1"), "{\"city\":"),
+            tool_call(Some(3), Some("call_2"), "{\"city\":"),
+            tool_call(Some(1), None, "\"Kathmandu\"}"),
+            tool_call(Some(3), None, "\"Thimphu\"}"),
+            // Without indices.
+            tool_call(None, Some("call_3"), "{\"city\":"),
+            tool_call(None, None, "\"Pokhara\"}"),
+        ];
+        for chunk in &chunks {
+            state.process_chunk(chunk);
+        }
+
+        let calls: Vec<_> = state
+            .finish()
+            .into_iter()
+            .filter_map(|part| match part {
+                Ok(LanguageModelStreamPart::ToolCall(call)) => Some((call.tool_call_id, call.args)),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            calls,
+            [
+                ("call_1".to_string(), r#"{"city":"Kathmandu"}"#.to_string()),
+                ("call_2".to_string(), r#"{"city":"Thimphu"}"#.to_string()),
+                ("call_3".to_string(), r#"{"city":"Pokhara"}"#.to_string()),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_openai_error_payloads() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "model": "gpt-4o" })))
+            .respond_with(ResponseTemplate::new(400).set_body_json(json!({
+                "error": {
+                    "message": "This model's maximum context length is 128000 tokens.",
+                    "type": "invalid_request_error",
+                    "param": "messages",
+                    "code": "context_length_exceeded"
+                }
+            })))
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({ "model": "gpt-4o-mini" })))
+            .respond_with(
+                ResponseTemplate::new(429)
+                    .insert_header("retry-after", "20")
+                    .set_body_json(json!({
+                        "error": {
+                            "message": "You exceeded your current quota.",
+                            "type": "insufficient_quota",
+                            "param": null,
+                            "code": "insufficient_quota"
+                        }
+                    })),
+            )
+            .mount(&server)
+            .await;
+
+        let provider = OpenAIProvider::new(
+            OpenAIProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let error = provider
+            .language_model("gpt-4o")
+            .unwrap()
+            .do_generate(text_request(vec![user_message("Hello")]))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            ModelError::ContextLengthExceeded(error)
+                if error.status == Some(400)
+                    && error.code.as_deref() == Some("context_length_exceeded")
+        ));
+
+        let error = provider
+            .language_model("gpt-4o-mini")
+            .unwrap()
+            .do_generate(text_request(vec![user_message("Hello")]))
+            .await
+            .unwrap_err();
+        assert!(!error.is_retryable());
+        let error = error.api_call_error().unwrap();
+        assert_eq!(error.status, Some(429));
+        assert_eq!(error.code.as_deref(), Some("insufficient_quota"));
+        assert_eq!(error.header("Retry-After"), Some("20"));
+        assert!(error
+            .message
+            .starts_with("You exceeded your current quota."));
+    }
+
+    #[tokio::test]
+    async fn test_reasoning_model_request_adaptation() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({
+                "model": "o3-mini",
+                "messages": [
+                    { "role": "developer", "content": "Be brief." },
+                    { "role": "user", "content": "Hi" }
+                ],
+                "max_completion_tokens": 100,
+                "reasoning_effort": "medium"
+            })))
+            .and(|request: &wiremock::Request| {
+                let body: serde_json::Value = request.body_json().unwrap();
+                body.get("temperature").is_none() && body.get("max_tokens").is_none()
+            })
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "created": 1717000000,
+                "model": "o3-mini-2025-01-31",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "Hello!" },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 12, "completion_tokens": 3, "total_tokens": 15 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAIProvider::new(
+            OpenAIProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let mut request = text_request(vec![
+            LanguageModelMessage::System("Be brief.".to_string()),
+            user_message("Hi"),
+        ]);
+        request.call_settings = Some(LanguageModelCallSettings {
+            max_tokens: Some(100),
+            temperature: Some(0.2),
+            ..Default::default()
+        });
+        let response = provider
+            .language_model("o3-mini")
+            .unwrap()
+            .do_generate(request)
+            .await
+            .unwrap();
+
+        assert_eq!(response.text.as_deref(), Some("Hello!"));
+        assert!(matches!(
+            response.warnings.as_slice(),
+            [LanguageModelCallWarning::UnsupportedSetting { setting, .. }] if setting == "temperature"
+        ));
+
+        let capabilities = OpenAIChatModelId::O1Mini.capabilities();
+        assert!(!capabilities.supports_tools);
+        assert!(!capabilities.supports_reasoning_effort);
+        assert_eq!(
+            capabilities.system_message_mode,
+            OpenAISystemMessageMode::Remove
+        );
+        assert_eq!(
+            OpenAIChatModelId::GPT41.capabilities().context_window,
+            1_047_576
+        );
+    }
+
+    #[tokio::test]
+    async fn test_file_urls_are_sent_inline() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/report.pdf"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .insert_header("content-type", "application/pdf")
+                    .set_body_bytes(b"%PDF-1.7".to_vec()),
+            )
+            .expect(1)
+            .mount(&server)
+            .await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({
+                "messages": [{
+                    "role": "user",
+                    "content": [
+                        { "type": "text", "text": "Summarize this." },
+                        {
+                            "type": "file",
+                            "file": {
+                                "filename": "document.pdf",
+                                "file_data": "data:application/pdf;base64,JVBERi0xLjc="
+                            }
+                        }
+                    ]
+                }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "chatcmpl-1",
+                "created": 1717000000,
+                "model": "gpt-4o-2024-08-06",
+                "choices": [{
+                    "index": 0,
+                    "message": { "role": "assistant", "content": "A report." },
+                    "finish_reason": "stop"
+                }],
+                "usage": { "prompt_tokens": 30, "completion_tokens": 3, "total_tokens": 33 }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenAIProvider::new(
+            OpenAIProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let mut model = provider.language_model("gpt-4o").unwrap();
+        let message = CoreMessage::User(CoreUserMessage {
+            content: UserContent::Parts(vec![
+                UserContentParts::Text(TextPart {
+                    text: "Summarize this.".to_string(),
+                }),
+                UserContentParts::File(FilePart {
+                    file_name: None,
+                    file_content: None,
+                    file_url: Some(format!("{}/report.pdf", server.uri())),
+                    mime_type: None,
+                }),
+            ]),
+        });
+        let result = generate_text(
+            &mut model,
+            GenerateTextOptions::default().messages(vec![message]),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.text(), "A report.");
+    }
+
+    #[test]
+    fn test_unsupported_reasoning_effort_is_dropped() {
+        let provider = OpenAIProvider::new(OpenAIProviderSettings::new("test-key".to_string()));
+        let request = text_request(vec![user_message("Hi")]);
+
+        let (body, warnings) = provider
+            .language_model("o1-mini")
+            .unwrap()
+            .get_args(&request)
+            .unwrap();
+        assert!(body.get("reasoning_effort").is_none());
+        assert!(warnings.is_empty());
+
+        for model_id in ["o1-mini", "gpt-4o"] {
+            let (body, warnings) = provider
+                .language_model(model_id)
+                .unwrap()
+                .with_reasoning_effort(OpenAIChatSettingsReasoningEffort::High)
+                .get_args(&request)
+                .unwrap();
+            assert!(body.get("reasoning_effort").is_none());
+            assert!(matches!(
+                warnings.as_slice(),
+                [LanguageModelCallWarning::UnsupportedSetting { setting, .. }]
+                    if setting == "reasoning_effort"
+            ));
+        }
+
+        let (body, _) = provider
+            .language_model("o3-mini")
+            .unwrap()
+            .with_reasoning_effort(OpenAIChatSettingsReasoningEffort::High)
+            .get_args(&request)
+            .unwrap();
+        assert_eq!(body["reasoning_effort"], json!("high"));
     }
 }
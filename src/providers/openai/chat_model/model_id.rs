@@ -1,6 +1,8 @@
 use core::fmt;
 use std::str::FromStr;
 
+use crate::model::pricing::ModelPricing;
+
 /// https://platform.openai.com/docs/models
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum OpenAIChatModelId {
@@ -67,6 +69,232 @@ pub enum OpenAIChatModelId {
     Custom(String),
 }
 
+/// How a model accepts system messages.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OpenAISystemMessageMode {
+    /// Sent with the `system` role.
+    System,
+    /// Sent with the `developer` role, as required by newer reasoning models.
+    Developer,
+    /// Not supported, system messages are dropped.
+    Remove,
+}
+
+/// What a chat model supports, used to adapt requests before they are sent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpenAIChatModelCapabilities {
+    /// Maximum number of input and output tokens.
+    pub context_window: u32,
+    pub max_output_tokens: u32,
+    pub supports_tools: bool,
+    pub supports_vision: bool,
+    pub supports_audio: bool,
+    pub supports_structured_outputs: bool,
+    pub supports_reasoning: bool,
+    /// Whether a `reasoning_effort` is accepted. The first o1 previews reason
+    /// at a fixed effort and reject it.
+    pub supports_reasoning_effort: bool,
+    /// Whether sampling settings such as `temperature` and `top_p` are accepted.
+    pub supports_temperature: bool,
+    pub system_message_mode: OpenAISystemMessageMode,
+}
+
+impl OpenAIChatModelCapabilities {
+    fn new(context_window: u32, max_output_tokens: u32) -> Self {
+        OpenAIChatModelCapabilities {
+            context_window,
+            max_output_tokens,
+            supports_tools: true,
+            supports_vision: false,
+            supports_audio: false,
+            supports_structured_outputs: false,
+            supports_reasoning: false,
+            supports_reasoning_effort: false,
+            supports_temperature: true,
+            system_message_mode: OpenAISystemMessageMode::System,
+        }
+    }
+
+    fn vision(mut self) -> Self {
+        self.supports_vision = true;
+        self
+    }
+
+    fn structured_outputs(mut self) -> Self {
+        self.supports_structured_outputs = true;
+        self
+    }
+
+    fn reasoning(mut self, system_message_mode: OpenAISystemMessageMode) -> Self {
+        self.supports_reasoning = true;
+        self.supports_reasoning_effort = true;
+        self.supports_temperature = false;
+        self.system_message_mode = system_message_mode;
+        self
+    }
+
+    fn without_reasoning_effort(mut self) -> Self {
+        self.supports_reasoning_effort = false;
+        self
+    }
+
+    fn without_tools(mut self) -> Self {
+        self.supports_tools = false;
+        self
+    }
+}
+
+impl OpenAIChatModelId {
+    /// Whether the model belongs to the o-series reasoning models.
+    pub fn is_reasoning_model(&self) -> bool {
+        let id = self.to_string();
+        id.starts_with("o1") || id.starts_with("o3") || id.starts_with("o4")
+    }
+
+    /// Context window and feature support of the model.
+    ///
+    /// Custom models are assumed to support everything, with a 128k context
+    /// window, unless their id names an o-series reasoning model.
+    ///
+    /// https://platform.openai.com/docs/models
+    pub fn capabilities(&self) -> OpenAIChatModelCapabilities {
+        use OpenAIChatModelCapabilities as Caps;
+        use OpenAISystemMessageMode::{Developer, Remove};
+
+        match self {
+            Self::O1 | Self::O12024_12_17 => Caps::new(200_000, 100_000)
+                .vision()
+                .structured_outputs()
+                .reasoning(Developer),
+            Self::O1Mini | Self::O1Mini2024_09_12 => Caps::new(128_000, 65_536)
+                .without_tools()
+                .reasoning(Remove)
+                .without_reasoning_effort(),
+            Self::O1Preview | Self::O1Preview2024_09_12 => Caps::new(128_000, 32_768)
+                .without_tools()
+                .reasoning(Remove)
+                .without_reasoning_effort(),
+            Self::O3Mini | Self::O3Mini2025_01_31 => Caps::new(200_000, 100_000)
+                .structured_outputs()
+                .reasoning(Developer),
+            Self::O3 | Self::O32025_04_16 | Self::O4Mini | Self::O4Mini2025_04_16 => {
+                Caps::new(200_000, 100_000)
+                    .vision()
+                    .structured_outputs()
+                    .reasoning(Developer)
+            }
+            Self::GPT41
+            | Self::GPT412025_04_14
+            | Self::GPT41Mini
+            | Self::GPT41Mini2025_04_14
+            | Self::GPT41Nano
+            | Self::GPT41Nano2025_04_14 => {
+                Caps::new(1_047_576, 32_768).vision().structured_outputs()
+            }
+            Self::GPT4o
+            | Self::GPT4o2024_08_06
+            | Self::GPT4o2024_11_20
+            | Self::GPT4oMini
+            | Self::GPT4oMini2024_07_18
+            | Self::GPT45Preview
+            | Self::GPT45Preview2025_02_27 => {
+                Caps::new(128_000, 16_384).vision().structured_outputs()
+            }
+            Self::GPT4o2024_05_13 => Caps::new(128_000, 4_096).vision(),
+            Self::ChatGPT4oLatest => Caps::new(128_000, 16_384).vision().without_tools(),
+            Self::GPT4oAudioPreview
+            | Self::GPT4oAudioPreview2024_10_01
+            | Self::GPT4oAudioPreview2024_12_17 => Caps {
+                supports_audio: true,
+                ..Caps::new(128_000, 16_384)
+            },
+            Self::GPT4oSearchPreview
+            | Self::GPT4oSearchPreview2025_03_11
+            | Self::GPT4oMiniSearchPreview
+            | Self::GPT4oMiniSearchPreview2025_03_11 => Caps {
+                supports_temperature: false,
+                ..Caps::new(128_000, 16_384)
+                    .without_tools()
+                    .structured_outputs()
+            },
+            Self::GPT4Turbo | Self::GPT4Turbo2024_04_09 => Caps::new(128_000, 4_096).vision(),
+            Self::GPT4TurboPreview | Self::GPT40125Preview | Self::GPT41106Preview => {
+                Caps::new(128_000, 4_096)
+            }
+            Self::GPT4 | Self::GPT40613 => Caps::new(8_192, 8_192),
+            Self::GPT35Turbo0125 | Self::GPT35Turbo | Self::GPT35Turbo1106 => {
+                Caps::new(16_385, 4_096)
+            }
+            Self::Custom(_) => {
+                let capabilities = Caps {
+                    supports_audio: true,
+                    ..Caps::new(128_000, 16_384).vision().structured_outputs()
+                };
+                if self.is_reasoning_model() {
+                    capabilities.reasoning(Developer)
+                } else {
+                    capabilities
+                }
+            }
+        }
+    }
+
+    /// List price of the model, `None` for custom models.
+    ///
+    /// https://openai.com/api/pricing
+    pub fn pricing(&self) -> Option<ModelPricing> {
+        let pricing = match self {
+            Self::O1 | Self::O12024_12_17 | Self::O1Preview | Self::O1Preview2024_09_12 => {
+                ModelPricing::new(15.0, 60.0).with_cached_input(7.5)
+            }
+            Self::O1Mini | Self::O1Mini2024_09_12 | Self::O3Mini | Self::O3Mini2025_01_31 => {
+                ModelPricing::new(1.1, 4.4).with_cached_input(0.55)
+            }
+            Self::O3 | Self::O32025_04_16 => ModelPricing::new(2.0, 8.0).with_cached_input(0.5),
+            Self::O4Mini | Self::O4Mini2025_04_16 => {
+                ModelPricing::new(1.1, 4.4).with_cached_input(0.275)
+            }
+            Self::GPT41 | Self::GPT412025_04_14 => {
+                ModelPricing::new(2.0, 8.0).with_cached_input(0.5)
+            }
+            Self::GPT41Mini | Self::GPT41Mini2025_04_14 => {
+                ModelPricing::new(0.4, 1.6).with_cached_input(0.1)
+            }
+            Self::GPT41Nano | Self::GPT41Nano2025_04_14 => {
+                ModelPricing::new(0.1, 0.4).with_cached_input(0.025)
+            }
+            Self::GPT4o | Self::GPT4o2024_08_06 | Self::GPT4o2024_11_20 => {
+                ModelPricing::new(2.5, 10.0).with_cached_input(1.25)
+            }
+            Self::GPT4o2024_05_13 | Self::ChatGPT4oLatest => ModelPricing::new(5.0, 15.0),
+            Self::GPT4oAudioPreview
+            | Self::GPT4oAudioPreview2024_10_01
+            | Self::GPT4oAudioPreview2024_12_17
+            | Self::GPT4oSearchPreview
+            | Self::GPT4oSearchPreview2025_03_11 => ModelPricing::new(2.5, 10.0),
+            Self::GPT4oMiniSearchPreview | Self::GPT4oMiniSearchPreview2025_03_11 => {
+                ModelPricing::new(0.15, 0.6)
+            }
+            Self::GPT4oMini | Self::GPT4oMini2024_07_18 => {
+                ModelPricing::new(0.15, 0.6).with_cached_input(0.075)
+            }
+            Self::GPT4Turbo
+            | Self::GPT4Turbo2024_04_09
+            | Self::GPT4TurboPreview
+            | Self::GPT40125Preview
+            | Self::GPT41106Preview => ModelPricing::new(10.0, 30.0),
+            Self::GPT4 | Self::GPT40613 => ModelPricing::new(30.0, 60.0),
+            Self::GPT45Preview | Self::GPT45Preview2025_02_27 => {
+                ModelPricing::new(75.0, 150.0).with_cached_input(37.5)
+            }
+            Self::GPT35Turbo0125 | Self::GPT35Turbo => ModelPricing::new(0.5, 1.5),
+            Self::GPT35Turbo1106 => ModelPricing::new(1.0, 2.0),
+            Self::Custom(_) => return None,
+        };
+        Some(pricing)
+    }
+}
+
 impl FromStr for OpenAIChatModelId {
     type Err = String;
 
@@ -5,10 +5,11 @@ use crate::{
     errors::{ModelError, ProviderError},
     provider::LanguageModelProvider,
 };
-use chat_model::{model_id::OpenAIChatModelId, OpenAIChatModel};
+use chat_model::{model_id::OpenAIChatModelId, OpenAIChatConfig, OpenAIChatModel};
 use provider_settings::OpenAIProviderSettings;
 use std::str::FromStr;
 
+#[derive(Default)]
 pub struct OpenAIProvider {
     pub settings: OpenAIProviderSettings,
 }
@@ -18,20 +19,21 @@ impl OpenAIProvider {
         OpenAIProvider { settings }
     }
 
-    pub fn default() -> Self {
-        OpenAIProvider {
-            settings: OpenAIProviderSettings::default(),
-        }
-    }
-
     pub fn create_chat_model(
         &self,
         model_id: OpenAIChatModelId,
-    ) -> Result<OpenAIChatModel, ModelError> {
-        unimplemented!(
-            "OpenAIProvider::create_chat_model is not implemented yet. Model ID: {}",
-            model_id
-        );
+    ) -> Result<OpenAIChatModel, ProviderError> {
+        Ok(OpenAIChatModel::new(
+            model_id,
+            OpenAIChatConfig {
+                provider: format!("{}.chat", self.settings.name),
+                base_url: self.settings.base_url.clone(),
+                headers: self.get_headers()?,
+                transport: self.settings.transport.clone(),
+                default_settings: self.settings.default_settings.clone(),
+                compatibility: self.settings.compatibility,
+            },
+        ))
     }
 }
 
@@ -39,9 +41,9 @@ impl LanguageModelProvider for OpenAIProvider {
     type Model = OpenAIChatModel;
     fn language_model(&self, model_id: &str) -> Result<Self::Model, ProviderError> {
         if model_id.is_empty() {
-            return Err(ProviderError::InvalidModelId(format!(
-                "Provided an empty OpenAI model id"
-            )));
+            return Err(ProviderError::InvalidModelId(
+                "Provided an empty OpenAI model id".to_string(),
+            ));
         }
         let model_id = model_id.trim();
         let openai_model_id = OpenAIChatModelId::from_str(model_id).map_err(|_| {
@@ -51,7 +53,6 @@ impl LanguageModelProvider for OpenAIProvider {
         })?;
 
         self.create_chat_model(openai_model_id)
-            .map_err(|e| ProviderError::ModelError(e))
     }
 
     fn get_headers(&self) -> Result<Vec<(String, String)>, ProviderError> {
@@ -67,6 +68,9 @@ impl LanguageModelProvider for OpenAIProvider {
         if let Some(project_id) = &self.settings.project_id {
             headers.push(("OpenAI-Project".to_string(), project_id.clone()));
         }
+        if let Some(extra) = &self.settings.headers {
+            headers.extend(extra.iter().cloned());
+        }
         Ok(headers)
     }
 }
@@ -1,4 +1,10 @@
-use crate::utils;
+use std::sync::Arc;
+
+use crate::{
+    model::call_settings::LanguageModelCallSettings,
+    transport::{default_transport, HttpTransport},
+    utils,
+};
 
 const OPNEAI_DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 
@@ -6,6 +12,7 @@ const OPNEAI_DEFAULT_BASE_URL: &str = "https://api.openai.com/v1";
 /// and `compatible` when using 3rd party providers. In `compatible` mode, newer
 /// information such as streamOptions are not being sent. Defaults to `strict`.
 ///
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum OpenAIProviderSettingsCompatibility {
     STRICT,
     COMPATIBLE,
@@ -26,6 +33,11 @@ pub struct OpenAIProviderSettings {
     pub compatibility: OpenAIProviderSettingsCompatibility,
     /// Provider name. Overrides the `openai` default name for 3rd party providers.
     pub name: String,
+    /// Transport used to send the HTTP requests, e.g. to record or replay them.
+    pub transport: Arc<dyn HttpTransport>,
+    /// Call settings applied to every call of the provider's models, unless
+    /// a model default or the call itself overrides them.
+    pub default_settings: LanguageModelCallSettings,
 }
 
 impl Default for OpenAIProviderSettings {
@@ -38,6 +50,8 @@ impl Default for OpenAIProviderSettings {
             headers: None,
             compatibility: OpenAIProviderSettingsCompatibility::STRICT,
             name: "openai".to_string(),
+            transport: default_transport(),
+            default_settings: LanguageModelCallSettings::default(),
         }
     }
 }
@@ -86,4 +100,16 @@ impl OpenAIProviderSettings {
         self.name = name.to_string();
         self
     }
+
+    /// Sets the HTTP transport for the OpenAI provider settings.
+    pub fn transport(mut self, transport: Arc<dyn HttpTransport>) -> Self {
+        self.transport = transport;
+        self
+    }
+
+    /// Sets the default call settings for the OpenAI provider settings.
+    pub fn default_settings(mut self, default_settings: LanguageModelCallSettings) -> Self {
+        self.default_settings = default_settings;
+        self
+    }
 }
@@ -0,0 +1,406 @@
+use async_trait::async_trait;
+use serde::Serialize;
+use serde_json::{json, Map, Value as JSONValue};
+use std::collections::HashMap;
+
+use crate::{
+    errors::ModelError,
+    model::{
+        call_warning::LanguageModelCallWarning, LanguageModel, LanguageModelDoGenerateRequest,
+        LanguageModelDoGenerateResponse, LanguageModelDoStreamResponse,
+    },
+    providers::openai::chat_model::{OpenAIChatModel, OpenAIChatStreamMetadata},
+};
+
+/// Key under which OpenRouter details are reported in `provider_metadata`.
+const OPENROUTER_METADATA_KEY: &str = "openrouter";
+
+/// Provider routing preferences.
+///
+/// https://openrouter.ai/docs/features/provider-routing
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OpenRouterProviderPreferences {
+    /// Provider slugs to try in order, e.g. `["anthropic", "openai"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<Vec<String>>,
+    /// Whether backup providers may be used when the preferred ones are unavailable.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allow_fallbacks: Option<bool>,
+    /// Only use providers that support every parameter in the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub require_parameters: Option<bool>,
+    /// Whether providers that may store or train on data are allowed (`allow` or `deny`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data_collection: Option<String>,
+    /// Provider slugs that are allowed to serve the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub only: Option<Vec<String>>,
+    /// Provider slugs that must not serve the request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore: Option<Vec<String>>,
+    /// Quantization levels to filter providers by, e.g. `["fp8"]`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantizations: Option<Vec<String>>,
+    /// Sort providers by `price`, `throughput` or `latency`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
+/// Reasoning token configuration.
+///
+/// https://openrouter.ai/docs/use-cases/reasoning-tokens
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct OpenRouterReasoning {
+    /// `high`, `medium` or `low`. Cannot be combined with `max_tokens`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub effort: Option<String>,
+    /// Maximum number of tokens to spend on reasoning.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tokens: Option<u32>,
+    /// Let the model reason but leave the reasoning out of the response.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude: Option<bool>,
+}
+
+pub struct OpenRouterChatModel {
+    /// The OpenAI compatible chat model that builds and parses the requests.
+    pub chat_model: OpenAIChatModel,
+    /// Models to fall back to, in order, if the primary model is unavailable.
+    pub models: Option<Vec<String>>,
+    /// Provider routing preferences.
+    pub provider: Option<OpenRouterProviderPreferences>,
+    /// Prompt transforms to apply, e.g. `["middle-out"]`.
+    pub transforms: Option<Vec<String>>,
+    /// Reasoning token configuration.
+    pub reasoning: Option<OpenRouterReasoning>,
+    /// Ask OpenRouter to include usage accounting, which reports the cost of
+    /// the request. Defaults to `true`.
+    pub include_usage: bool,
+}
+
+impl OpenRouterChatModel {
+    pub fn new(chat_model: OpenAIChatModel) -> Self {
+        OpenRouterChatModel {
+            chat_model,
+            models: None,
+            provider: None,
+            transforms: None,
+            reasoning: None,
+            include_usage: true,
+        }
+    }
+
+    pub fn with_models(mut self, models: Vec<String>) -> Self {
+        self.models = Some(models);
+        self
+    }
+
+    pub fn with_provider(mut self, provider: OpenRouterProviderPreferences) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    pub fn with_transforms(mut self, transforms: Vec<String>) -> Self {
+        self.transforms = Some(transforms);
+        self
+    }
+
+    pub fn with_reasoning(mut self, reasoning: OpenRouterReasoning) -> Self {
+        self.reasoning = Some(reasoning);
+        self
+    }
+
+    pub fn with_include_usage(mut self, include_usage: bool) -> Self {
+        self.include_usage = include_usage;
+        self
+    }
+
+    fn get_args(
+        &self,
+        request: &LanguageModelDoGenerateRequest,
+    ) -> Result<(Map<String, JSONValue>, Vec<LanguageModelCallWarning>), ModelError> {
+        let (mut body, warnings) = self.chat_model.get_args(request)?;
+        if let Some(models) = &self.models {
+            body.insert("models".into(), json!(models));
+        }
+        if let Some(provider) = &self.provider {
+            body.insert("provider".into(), json!(provider));
+        }
+        if let Some(transforms) = &self.transforms {
+            body.insert("transforms".into(), json!(transforms));
+        }
+        if let Some(reasoning) = &self.reasoning {
+            body.insert("reasoning".into(), json!(reasoning));
+        }
+        if self.include_usage {
+            body.insert("usage".into(), json!({ "include": true }));
+        }
+        Ok((body, warnings))
+    }
+}
+
+#[async_trait]
+impl LanguageModel for OpenRouterChatModel {
+    fn provider(&self) -> String {
+        self.chat_model.provider()
+    }
+
+    fn model_id(&self) -> String {
+        self.chat_model.model_id()
+    }
+
+    fn supports_urls(&self, url: String) -> bool {
+        self.chat_model.supports_urls(url)
+    }
+
+    fn supports_file_urls(&self, url: String) -> bool {
+        self.chat_model.supports_file_urls(url)
+    }
+
+    async fn do_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let (body, warnings) = self.get_args(&request)?;
+        let body = JSONValue::Object(body);
+        let (response, response_headers) = self
+            .chat_model
+            .post_chat_completion(&request, &body)
+            .await?;
+
+        let mut result = self
+            .chat_model
+            .parse_response(&response, response_headers)?;
+        result.warnings = warnings;
+        result.request_body = Some(self.chat_model.request_metadata(&request, &body));
+
+        let mut metadata = HashMap::new();
+        collect_openrouter_metadata(&response, &mut metadata);
+        result.provider_metadata = Some(HashMap::from([(
+            OPENROUTER_METADATA_KEY.to_string(),
+            metadata,
+        )]));
+
+        Ok(result)
+    }
+
+    async fn do_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let (body, warnings) = self.get_args(&request)?;
+        self.chat_model
+            .stream_chat_completion(
+                &request,
+                body,
+                warnings,
+                Some(OpenAIChatStreamMetadata {
+                    key: OPENROUTER_METADATA_KEY,
+                    collect: collect_openrouter_metadata,
+                }),
+            )
+            .await
+    }
+}
+
+/// `model` is the model that actually served the request, which may be one of
+/// the fallbacks, and `provider` the upstream that hosted it. When usage
+/// accounting is included, streams report `usage` and `cost` in the last
+/// chunk only.
+fn collect_openrouter_metadata(response: &JSONValue, metadata: &mut HashMap<String, JSONValue>) {
+    for key in ["provider", "model"] {
+        if let Some(value) = response.get(key) {
+            metadata.insert(key.to_string(), value.clone());
+        }
+    }
+    if let Some(cost) = response["usage"].get("cost") {
+        metadata.insert("cost".to_string(), cost.clone());
+    }
+    if response["usage"].is_object() {
+        metadata.insert("usage".to_string(), response["usage"].clone());
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use serde_json::json;
+    use wiremock::{
+        matchers::{body_partial_json, header, method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{OpenRouterProviderPreferences, OpenRouterReasoning};
+    use crate::{
+        model::{
+            call_settings::LanguageModelCallSettings,
+            message::{LanguageModelMessage, LanguageModelTextPart, LanguageModelUserMessage},
+            stream_part::LanguageModelStreamPart,
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat, LanguageModelDoGenerateResponseReasoning,
+        },
+        provider::LanguageModelProvider,
+        providers::openrouter::{
+            provider_settings::OpenRouterProviderSettings, OpenRouterProvider,
+        },
+    };
+
+    #[tokio::test]
+    async fn test_openrouter_routing_and_metadata() {
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(header("Authorization", "Bearer test-key"))
+            .and(body_partial_json(json!({
+                "model": "deepseek/deepseek-r1",
+                "models": ["anthropic/claude-3.5-sonnet"],
+                "provider": { "order": ["deepinfra", "together"], "allow_fallbacks": false },
+                "transforms": ["middle-out"],
+                "reasoning": { "effort": "high" },
+                "usage": { "include": true },
+                "messages": [{ "role": "user", "content": "What is the capital of Nepal?" }]
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+                "id": "gen-1",
+                "created": 1717000000,
+                "model": "anthropic/claude-3.5-sonnet",
+                "provider": "Anthropic",
+                "choices": [{
+                    "index": 0,
+                    "message": {
+                        "role": "assistant",
+                        "content": "Kathmandu.",
+                        "reasoning": "The user asks about Nepal."
+                    },
+                    "finish_reason": "stop"
+                }],
+                "usage": {
+                    "prompt_tokens": 10,
+                    "completion_tokens": 20,
+                    "total_tokens": 30,
+                    "cost": 0.00042
+                }
+            })))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenRouterProvider::new(
+            OpenRouterProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let model = provider
+            .language_model("deepseek/deepseek-r1")
+            .unwrap()
+            .with_models(vec!["anthropic/claude-3.5-sonnet".to_string()])
+            .with_provider(OpenRouterProviderPreferences {
+                order: Some(vec!["deepinfra".to_string(), "together".to_string()]),
+                allow_fallbacks: Some(false),
+                ..Default::default()
+            })
+            .with_transforms(vec!["middle-out".to_string()])
+            .with_reasoning(OpenRouterReasoning {
+                effort: Some("high".to_string()),
+                ..Default::default()
+            });
+
+        let response = model
+            .do_generate(LanguageModelDoGenerateRequest {
+                call_settings: Some(LanguageModelCallSettings::default()),
+                input_format: LanguageModelDoGenerateRequestInputFormat::Messages,
+                prompt: vec![LanguageModelMessage::User(vec![
+                    LanguageModelUserMessage::Text(LanguageModelTextPart {
+                        text: "What is the capital of Nepal?".to_string(),
+                        provider_metadata: None,
+                    }),
+                ])],
+                tools: None,
+                tool_choice: None,
+                provider_metadata: None,
+            })
+            .await
+            .unwrap();
+
+        assert_eq!(response.text.as_deref(), Some("Kathmandu."));
+        assert!(matches!(
+            &response.reasoning[..],
+            [LanguageModelDoGenerateResponseReasoning::Text { text, .. }]
+                if text == "The user asks about Nepal."
+        ));
+        assert_eq!(
+            response.response.unwrap().model_id,
+            "anthropic/claude-3.5-sonnet"
+        );
+        let metadata = &response.provider_metadata.unwrap()["openrouter"];
+        assert_eq!(metadata["model"], "anthropic/claude-3.5-sonnet");
+        assert_eq!(metadata["provider"], "Anthropic");
+        assert_eq!(metadata["cost"], 0.00042);
+    }
+
+    #[tokio::test]
+    async fn test_openrouter_stream_metadata() {
+        let chunks = [
+            json!({ "id": "gen-2", "created": 1717000000, "model": "anthropic/claude-3.5-sonnet",
+                "provider": "Anthropic",
+                "choices": [{ "index": 0, "delta": { "role": "assistant", "content": "Kathmandu." } }] }),
+            json!({ "id": "gen-2", "model": "anthropic/claude-3.5-sonnet", "provider": "Anthropic",
+                "choices": [{ "index": 0, "delta": {}, "finish_reason": "stop" }] }),
+            json!({ "id": "gen-2", "model": "anthropic/claude-3.5-sonnet", "provider": "Anthropic",
+                "choices": [],
+                "usage": { "prompt_tokens": 10, "completion_tokens": 3, "total_tokens": 13, "cost": 0.0001 } }),
+        ];
+        let body = chunks
+            .iter()
+            .map(|chunk| format!("data: {chunk}\n\n"))
+            .chain(["data: [DONE]\n\n".to_string()])
+            .collect::<String>();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .and(body_partial_json(json!({
+                "stream": true,
+                "models": ["anthropic/claude-3.5-sonnet"],
+                "usage": { "include": true }
+            })))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&server)
+            .await;
+
+        let provider = OpenRouterProvider::new(
+            OpenRouterProviderSettings::new("test-key".to_string()).base_url(&server.uri()),
+        );
+        let model = provider
+            .language_model("deepseek/deepseek-r1")
+            .unwrap()
+            .with_models(vec!["anthropic/claude-3.5-sonnet".to_string()]);
+        let response = model
+            .do_stream(LanguageModelDoGenerateRequest::new(
+                LanguageModelDoGenerateRequestInputFormat::Messages,
+                vec![LanguageModelMessage::User(vec![
+                    LanguageModelUserMessage::Text(LanguageModelTextPart {
+                        text: "What is the capital of Nepal?".to_string(),
+                        provider_metadata: None,
+                    }),
+                ])],
+            ))
+            .await
+            .unwrap();
+
+        let parts: Vec<_> = response.stream.map(|part| part.unwrap()).collect().await;
+        let Some(LanguageModelStreamPart::Finish {
+            usage,
+            provider_metadata: Some(provider_metadata),
+            ..
+        }) = parts.last()
+        else {
+            panic!("expected a finish part with provider metadata, got {parts:?}");
+        };
+        assert_eq!(usage.total_tokens, 13);
+        let metadata = &provider_metadata["openrouter"];
+        assert_eq!(metadata["model"], "anthropic/claude-3.5-sonnet");
+        assert_eq!(metadata["provider"], "Anthropic");
+        assert_eq!(metadata["cost"], 0.0001);
+        assert_eq!(metadata["usage"]["completion_tokens"], 3);
+    }
+}
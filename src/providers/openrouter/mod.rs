@@ -0,0 +1,75 @@
+pub mod chat_model;
+pub mod provider_settings;
+
+use crate::{
+    errors::ProviderError,
+    provider::LanguageModelProvider,
+    providers::openai::{
+        chat_model::{model_id::OpenAIChatModelId, OpenAIChatConfig, OpenAIChatModel},
+        provider_settings::OpenAIProviderSettingsCompatibility,
+    },
+};
+use chat_model::OpenRouterChatModel;
+use provider_settings::OpenRouterProviderSettings;
+
+/// OpenRouter speaks the OpenAI chat protocol, so its chat models are built on
+/// top of [`OpenAIChatModel`] running in compatible mode.
+pub struct OpenRouterProvider {
+    pub settings: OpenRouterProviderSettings,
+}
+
+impl OpenRouterProvider {
+    pub fn new(settings: OpenRouterProviderSettings) -> Self {
+        OpenRouterProvider { settings }
+    }
+
+    /// Creates a chat model for an OpenRouter model slug such as
+    /// `anthropic/claude-3.5-sonnet` or `openai/gpt-4o`.
+    pub fn create_chat_model(&self, model_id: &str) -> Result<OpenRouterChatModel, ProviderError> {
+        let chat_model = OpenAIChatModel::new(
+            OpenAIChatModelId::Custom(model_id.to_string()),
+            OpenAIChatConfig {
+                provider: format!("{}.chat", self.settings.name),
+                base_url: self.settings.base_url.clone(),
+                headers: self.get_headers()?,
+                transport: self.settings.transport.clone(),
+                default_settings: self.settings.default_settings.clone(),
+                compatibility: OpenAIProviderSettingsCompatibility::COMPATIBLE,
+            },
+        );
+        Ok(OpenRouterChatModel::new(chat_model))
+    }
+}
+
+impl Default for OpenRouterProvider {
+    fn default() -> Self {
+        OpenRouterProvider::new(OpenRouterProviderSettings::default())
+    }
+}
+
+impl LanguageModelProvider for OpenRouterProvider {
+    type Model = OpenRouterChatModel;
+    fn language_model(&self, model_id: &str) -> Result<Self::Model, ProviderError> {
+        let model_id = model_id.trim();
+        if model_id.is_empty() {
+            return Err(ProviderError::InvalidModelId(
+                "Provided an empty OpenRouter model id".to_string(),
+            ));
+        }
+        self.create_chat_model(model_id)
+    }
+
+    fn get_headers(&self) -> Result<Vec<(String, String)>, ProviderError> {
+        let mut headers = vec![
+            (
+                "Authorization".to_string(),
+                format!("Bearer {}", self.settings.api_key),
+            ),
+            ("Content-Type".to_string(), "application/json".to_string()),
+        ];
+        if let Some(extra) = &self.settings.headers {
+            headers.extend(extra.iter().cloned());
+        }
+        Ok(headers)
+    }
+}
@@ -0,0 +1,118 @@
+use async_trait::async_trait;
+use std::{
+    io::ErrorKind,
+    path::{Path, PathBuf},
+};
+use tokio::{fs, sync::Mutex};
+
+use super::{generate_conversation_id, validate_conversation_id, ConversationStore};
+use crate::{
+    errors::StoreError,
+    prompt::{Conversation, CoreMessage},
+};
+
+/// Keeps every conversation as `{id}.json` in a directory, in the format of
+/// [`Conversation::to_json`].
+///
+/// Files are replaced through a rename, so a crash never leaves a partially
+/// written conversation. Appends are serialized within one store; do not
+/// share the directory between processes that write to the same
+/// conversation.
+#[derive(Debug)]
+pub struct FileConversationStore {
+    dir: PathBuf,
+    write_lock: Mutex<()>,
+}
+
+impl FileConversationStore {
+    /// The directory is created on the first write.
+    pub fn new(dir: impl AsRef<Path>) -> Self {
+        FileConversationStore {
+            dir: dir.as_ref().to_path_buf(),
+            write_lock: Mutex::new(()),
+        }
+    }
+
+    fn path(&self, id: &str) -> Result<PathBuf, StoreError> {
+        validate_conversation_id(id)?;
+        Ok(self.dir.join(format!("{id}.json")))
+    }
+
+    async fn write(&self, id: &str, conversation: &Conversation) -> Result<(), StoreError> {
+        let json = conversation.to_json().map_err(|e| StoreError::Corrupted {
+            id: id.to_string(),
+            message: e.to_string(),
+        })?;
+        fs::create_dir_all(&self.dir).await?;
+        let temp = self.dir.join(format!(".{id}.json.tmp"));
+        fs::write(&temp, json).await?;
+        fs::rename(&temp, self.path(id)?).await?;
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl ConversationStore for FileConversationStore {
+    async fn create(&self, messages: Vec<CoreMessage>) -> Result<String, StoreError> {
+        let id = generate_conversation_id();
+        let _guard = self.write_lock.lock().await;
+        self.write(&id, &Conversation::new(messages)).await?;
+        Ok(id)
+    }
+
+    async fn append(&self, id: &str, messages: Vec<CoreMessage>) -> Result<(), StoreError> {
+        let _guard = self.write_lock.lock().await;
+        let mut conversation = self.load(id).await?;
+        conversation.messages.extend(messages);
+        self.write(id, &conversation).await
+    }
+
+    async fn load(&self, id: &str) -> Result<Conversation, StoreError> {
+        let json = fs::read_to_string(self.path(id)?)
+            .await
+            .map_err(|e| not_found_or_io(id, e))?;
+        Conversation::from_json(&json).map_err(|e| StoreError::Corrupted {
+            id: id.to_string(),
+            message: e.to_string(),
+        })
+    }
+
+    async fn list(&self) -> Result<Vec<String>, StoreError> {
+        let mut entries = match fs::read_dir(&self.dir).await {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let mut ids = Vec::new();
+        while let Some(entry) = entries.next_entry().await? {
+            let path = entry.path();
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "json")
+            {
+                if let Some(id) = path.file_stem().and_then(|stem| stem.to_str()) {
+                    if validate_conversation_id(id).is_ok() {
+                        ids.push(id.to_string());
+                    }
+                }
+            }
+        }
+        ids.sort();
+        Ok(ids)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), StoreError> {
+        let _guard = self.write_lock.lock().await;
+        fs::remove_file(self.path(id)?)
+            .await
+            .map_err(|e| not_found_or_io(id, e))
+    }
+}
+
+fn not_found_or_io(id: &str, error: std::io::Error) -> StoreError {
+    if error.kind() == ErrorKind::NotFound {
+        StoreError::NotFound(id.to_string())
+    } else {
+        StoreError::Io(error)
+    }
+}
@@ -0,0 +1,69 @@
+use async_trait::async_trait;
+use std::{collections::HashMap, sync::Mutex};
+
+use super::{generate_conversation_id, validate_conversation_id, ConversationStore};
+use crate::{
+    errors::StoreError,
+    prompt::{Conversation, CoreMessage},
+};
+
+/// Keeps conversations in memory, e.g. for tests.
+#[derive(Debug, Default)]
+pub struct InMemoryConversationStore {
+    conversations: Mutex<HashMap<String, Vec<CoreMessage>>>,
+}
+
+impl InMemoryConversationStore {
+    pub fn new() -> Self {
+        InMemoryConversationStore::default()
+    }
+}
+
+#[async_trait]
+impl ConversationStore for InMemoryConversationStore {
+    async fn create(&self, messages: Vec<CoreMessage>) -> Result<String, StoreError> {
+        let id = generate_conversation_id();
+        self.conversations
+            .lock()
+            .unwrap()
+            .insert(id.clone(), messages);
+        Ok(id)
+    }
+
+    async fn append(&self, id: &str, messages: Vec<CoreMessage>) -> Result<(), StoreError> {
+        validate_conversation_id(id)?;
+        self.conversations
+            .lock()
+            .unwrap()
+            .get_mut(id)
+            .ok_or_else(|| StoreError::NotFound(id.to_string()))?
+            .extend(messages);
+        Ok(())
+    }
+
+    async fn load(&self, id: &str) -> Result<Conversation, StoreError> {
+        validate_conversation_id(id)?;
+        self.conversations
+            .lock()
+            .unwrap()
+            .get(id)
+            .map(|messages| Conversation::new(messages.clone()))
+            .ok_or_else(|| StoreError::NotFound(id.to_string()))
+    }
+
+    async fn list(&self) -> Result<Vec<String>, StoreError> {
+        let mut ids: Vec<String> = self.conversations.lock().unwrap().keys().cloned().collect();
+        ids.sort();
+        Ok(ids)
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), StoreError> {
+        validate_conversation_id(id)?;
+        self.conversations
+            .lock()
+            .unwrap()
+            .remove(id)
+            .map(|_| ())
+            .ok_or_else(|| StoreError::NotFound(id.to_string()))
+    }
+}
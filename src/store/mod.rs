@@ -0,0 +1,143 @@
+//! Persistence of chat histories.
+//!
+//! A [`ConversationStore`] keeps conversations by id. Backends are
+//! [`InMemoryConversationStore`], [`FileConversationStore`] and, with the
+//! `sqlite` feature, [`SqliteConversationStore`].
+
+mod file;
+mod memory;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+
+pub use file::FileConversationStore;
+pub use memory::InMemoryConversationStore;
+#[cfg(feature = "sqlite")]
+pub use sqlite::SqliteConversationStore;
+
+use async_trait::async_trait;
+
+use crate::{
+    errors::StoreError,
+    prompt::{Conversation, CoreMessage},
+};
+
+#[async_trait]
+pub trait ConversationStore: Send + Sync {
+    /// Creates a conversation holding `messages` and returns its id.
+    async fn create(&self, messages: Vec<CoreMessage>) -> Result<String, StoreError>;
+
+    /// Appends `messages` to the conversation. Either all of them are stored
+    /// or none.
+    async fn append(&self, id: &str, messages: Vec<CoreMessage>) -> Result<(), StoreError>;
+
+    async fn load(&self, id: &str) -> Result<Conversation, StoreError>;
+
+    /// Ids of all conversations, sorted.
+    async fn list(&self) -> Result<Vec<String>, StoreError>;
+
+    async fn delete(&self, id: &str) -> Result<(), StoreError>;
+
+    /// Copies the first `len` messages of a conversation, or all of them if
+    /// `len` is `None`, into a new conversation and returns its id.
+    async fn fork(&self, id: &str, len: Option<usize>) -> Result<String, StoreError> {
+        let mut messages = self.load(id).await?.messages;
+        if let Some(len) = len {
+            messages.truncate(len);
+        }
+        self.create(messages).await
+    }
+}
+
+/// A random id that is safe to use as a file name.
+pub(crate) fn generate_conversation_id() -> String {
+    format!("conv_{:032x}", rand::random::<u128>())
+}
+
+/// Ids are restricted to ASCII letters, digits, `-` and `_` so that backends
+/// can use them as file names.
+pub(crate) fn validate_conversation_id(id: &str) -> Result<(), StoreError> {
+    let valid = !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+    if valid {
+        Ok(())
+    } else {
+        Err(StoreError::InvalidId(id.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{ConversationStore, FileConversationStore, InMemoryConversationStore};
+    use crate::{
+        errors::StoreError,
+        prompt::{
+            AssistantContent, CoreAssistantMessage, CoreMessage, CoreUserMessage, UserContent,
+        },
+    };
+
+    fn user(text: &str) -> CoreMessage {
+        CoreMessage::User(CoreUserMessage {
+            content: UserContent::Text(text.to_string()),
+        })
+    }
+
+    fn assistant(text: &str) -> CoreMessage {
+        CoreMessage::Assistant(CoreAssistantMessage {
+            content: AssistantContent::Text(text.to_string()),
+        })
+    }
+
+    async fn check_store(store: &dyn ConversationStore) {
+        let id = store.create(vec![user("Hi")]).await.unwrap();
+        store
+            .append(
+                &id,
+                vec![assistant("Hello!"), user("Weather in Kathmandu?")],
+            )
+            .await
+            .unwrap();
+        let loaded = store.load(&id).await.unwrap();
+        assert_eq!(loaded.messages.len(), 3);
+        assert!(matches!(
+            &loaded.messages[1],
+            CoreMessage::Assistant(CoreAssistantMessage { content: AssistantContent::Text(text) })
+                if text == "Hello!"
+        ));
+
+        let fork = store.fork(&id, Some(2)).await.unwrap();
+        assert_ne!(fork, id);
+        assert_eq!(store.load(&fork).await.unwrap().messages.len(), 2);
+        let mut ids = vec![id.clone(), fork.clone()];
+        ids.sort();
+        assert_eq!(store.list().await.unwrap(), ids);
+
+        store.delete(&id).await.unwrap();
+        assert!(matches!(
+            store.load(&id).await,
+            Err(StoreError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.append(&id, vec![user("Hi")]).await,
+            Err(StoreError::NotFound(_))
+        ));
+        assert!(matches!(
+            store.load("../secrets").await,
+            Err(StoreError::InvalidId(_))
+        ));
+        assert_eq!(store.list().await.unwrap(), vec![fork]);
+    }
+
+    #[tokio::test]
+    async fn test_conversation_stores() {
+        check_store(&InMemoryConversationStore::new()).await;
+
+        let dir = std::env::temp_dir().join(format!("cortex-store-{:016x}", rand::random::<u64>()));
+        check_store(&FileConversationStore::new(&dir)).await;
+        std::fs::remove_dir_all(dir).unwrap();
+
+        #[cfg(feature = "sqlite")]
+        check_store(&super::SqliteConversationStore::open_in_memory().unwrap()).await;
+    }
+}
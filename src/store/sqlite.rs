@@ -0,0 +1,242 @@
+use async_trait::async_trait;
+use rusqlite::{params, Connection, OptionalExtension};
+use std::{
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use super::{generate_conversation_id, validate_conversation_id, ConversationStore};
+use crate::{
+    errors::StoreError,
+    prompt::{Conversation, CoreMessage, CONVERSATION_VERSION},
+};
+
+const SCHEMA: &str = "
+    CREATE TABLE IF NOT EXISTS conversations (
+        id TEXT PRIMARY KEY
+    );
+    CREATE TABLE IF NOT EXISTS messages (
+        conversation_id TEXT NOT NULL,
+        position INTEGER NOT NULL,
+        version INTEGER NOT NULL,
+        message TEXT NOT NULL,
+        PRIMARY KEY (conversation_id, position)
+    );
+";
+
+/// Keeps conversations in a SQLite database, one JSON row per message. Every
+/// row records the [`CONVERSATION_VERSION`] it was written with; loading a
+/// row of another version fails.
+///
+/// Queries run on the blocking thread pool of the tokio runtime.
+#[derive(Debug)]
+pub struct SqliteConversationStore {
+    connection: Arc<Mutex<Connection>>,
+}
+
+impl SqliteConversationStore {
+    /// Opens the database at `path`, creating it and its tables if needed.
+    pub fn open(path: impl AsRef<Path>) -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open(path).map_err(database_error)?)
+    }
+
+    pub fn open_in_memory() -> Result<Self, StoreError> {
+        Self::from_connection(Connection::open_in_memory().map_err(database_error)?)
+    }
+
+    fn from_connection(connection: Connection) -> Result<Self, StoreError> {
+        connection.execute_batch(SCHEMA).map_err(database_error)?;
+        Ok(SqliteConversationStore {
+            connection: Arc::new(Mutex::new(connection)),
+        })
+    }
+
+    async fn run<T: Send + 'static>(
+        &self,
+        query: impl FnOnce(&mut Connection) -> Result<T, StoreError> + Send + 'static,
+    ) -> Result<T, StoreError> {
+        let connection = self.connection.clone();
+        tokio::task::spawn_blocking(move || query(&mut connection.lock().unwrap()))
+            .await
+            .map_err(|e| StoreError::Database(e.to_string()))?
+    }
+}
+
+#[async_trait]
+impl ConversationStore for SqliteConversationStore {
+    async fn create(&self, messages: Vec<CoreMessage>) -> Result<String, StoreError> {
+        let id = generate_conversation_id();
+        self.run(move |connection| {
+            let transaction = connection.transaction().map_err(database_error)?;
+            transaction
+                .execute("INSERT INTO conversations (id) VALUES (?1)", params![id])
+                .map_err(database_error)?;
+            insert_messages(&transaction, &id, 0, &messages)?;
+            transaction.commit().map_err(database_error)?;
+            Ok(id)
+        })
+        .await
+    }
+
+    async fn append(&self, id: &str, messages: Vec<CoreMessage>) -> Result<(), StoreError> {
+        validate_conversation_id(id)?;
+        let id = id.to_string();
+        self.run(move |connection| {
+            let transaction = connection.transaction().map_err(database_error)?;
+            ensure_exists(&transaction, &id)?;
+            let next: i64 = transaction
+                .query_row(
+                    "SELECT COALESCE(MAX(position) + 1, 0) FROM messages WHERE conversation_id = ?1",
+                    params![id],
+                    |row| row.get(0),
+                )
+                .map_err(database_error)?;
+            insert_messages(&transaction, &id, next, &messages)?;
+            transaction.commit().map_err(database_error)
+        })
+        .await
+    }
+
+    async fn load(&self, id: &str) -> Result<Conversation, StoreError> {
+        validate_conversation_id(id)?;
+        let id = id.to_string();
+        self.run(move |connection| {
+            ensure_exists(connection, &id)?;
+            let corrupted = |message: String| StoreError::Corrupted {
+                id: id.clone(),
+                message,
+            };
+            let mut statement = connection
+                .prepare(
+                    "SELECT version, message FROM messages WHERE conversation_id = ?1 ORDER BY position",
+                )
+                .map_err(database_error)?;
+            let rows = statement
+                .query_map(params![id], |row| {
+                    Ok((row.get::<_, u32>(0)?, row.get::<_, String>(1)?))
+                })
+                .map_err(database_error)?;
+            let mut messages = Vec::new();
+            for row in rows {
+                let (version, json) = row.map_err(database_error)?;
+                if version != CONVERSATION_VERSION {
+                    return Err(corrupted(format!(
+                        "unsupported conversation version {version}, expected {CONVERSATION_VERSION}"
+                    )));
+                }
+                messages.push(serde_json::from_str(&json).map_err(|e| corrupted(e.to_string()))?);
+            }
+            Ok(Conversation::new(messages))
+        })
+        .await
+    }
+
+    async fn list(&self) -> Result<Vec<String>, StoreError> {
+        self.run(|connection| {
+            let mut statement = connection
+                .prepare("SELECT id FROM conversations ORDER BY id")
+                .map_err(database_error)?;
+            let ids = statement
+                .query_map([], |row| row.get(0))
+                .map_err(database_error)?
+                .collect::<Result<Vec<String>, _>>()
+                .map_err(database_error)?;
+            Ok(ids)
+        })
+        .await
+    }
+
+    async fn delete(&self, id: &str) -> Result<(), StoreError> {
+        validate_conversation_id(id)?;
+        let id = id.to_string();
+        self.run(move |connection| {
+            let transaction = connection.transaction().map_err(database_error)?;
+            ensure_exists(&transaction, &id)?;
+            transaction
+                .execute(
+                    "DELETE FROM messages WHERE conversation_id = ?1",
+                    params![id],
+                )
+                .map_err(database_error)?;
+            transaction
+                .execute("DELETE FROM conversations WHERE id = ?1", params![id])
+                .map_err(database_error)?;
+            transaction.commit().map_err(database_error)
+        })
+        .await
+    }
+}
+
+fn ensure_exists(connection: &Connection, id: &str) -> Result<(), StoreError> {
+    connection
+        .query_row(
+            "SELECT 1 FROM conversations WHERE id = ?1",
+            params![id],
+            |_| Ok(()),
+        )
+        .optional()
+        .map_err(database_error)?
+        .ok_or_else(|| StoreError::NotFound(id.to_string()))
+}
+
+fn insert_messages(
+    connection: &Connection,
+    id: &str,
+    first_position: i64,
+    messages: &[CoreMessage],
+) -> Result<(), StoreError> {
+    let mut statement = connection
+        .prepare(
+            "INSERT INTO messages (conversation_id, position, version, message) \
+             VALUES (?1, ?2, ?3, ?4)",
+        )
+        .map_err(database_error)?;
+    for (position, message) in (first_position..).zip(messages) {
+        let json = serde_json::to_string(message).map_err(|e| StoreError::Corrupted {
+            id: id.to_string(),
+            message: e.to_string(),
+        })?;
+        statement
+            .execute(params![id, position, CONVERSATION_VERSION, json])
+            .map_err(database_error)?;
+    }
+    Ok(())
+}
+
+fn database_error(error: rusqlite::Error) -> StoreError {
+    StoreError::Database(error.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use rusqlite::Connection;
+
+    use super::SqliteConversationStore;
+    use crate::{
+        errors::StoreError,
+        prompt::{CoreMessage, CoreUserMessage, UserContent},
+        store::ConversationStore,
+    };
+
+    #[tokio::test]
+    async fn test_rows_of_another_version_are_rejected() {
+        let path =
+            std::env::temp_dir().join(format!("cortex-sqlite-{:016x}.db", rand::random::<u64>()));
+        let store = SqliteConversationStore::open(&path).unwrap();
+        let message = CoreMessage::User(CoreUserMessage {
+            content: UserContent::Text("Hi".to_string()),
+        });
+        let id = store.create(vec![message]).await.unwrap();
+        assert_eq!(store.load(&id).await.unwrap().messages.len(), 1);
+
+        Connection::open(&path)
+            .unwrap()
+            .execute("UPDATE messages SET version = 2", [])
+            .unwrap();
+        assert!(matches!(
+            store.load(&id).await,
+            Err(StoreError::Corrupted { message, .. }) if message.contains("version 2")
+        ));
+        std::fs::remove_file(path).unwrap();
+    }
+}
@@ -0,0 +1,330 @@
+use async_trait::async_trait;
+use futures::{stream, StreamExt};
+use std::{
+    collections::VecDeque,
+    sync::{Mutex, MutexGuard},
+    time::Duration,
+};
+
+use crate::{
+    errors::ModelError,
+    model::{
+        finish_reason::LanguageModelFinishReason,
+        function_tool_call::LanguageModelFunctionToolCall,
+        request_metadata::LanguageModelRequestMetadata, stream_part::LanguageModelStreamPart,
+        usage::LanguageModelUsage, LanguageModel, LanguageModelDoGenerateRequest,
+        LanguageModelDoGenerateResponse, LanguageModelDoStreamResponse,
+    },
+};
+
+/// What the mock returns for a single call.
+pub enum MockLanguageModelResponse {
+    /// Returned from `do_generate`.
+    Generate(Box<LanguageModelDoGenerateResponse>),
+    /// Returned from `do_stream`. If `error` is set, the stream fails with it
+    /// after all `parts` were emitted, simulating a connection that breaks
+    /// in the middle of a response.
+    Stream {
+        parts: Vec<LanguageModelStreamPart>,
+        error: Option<ModelError>,
+    },
+    /// Returned from either call as the error of the call itself.
+    Error(ModelError),
+}
+
+/// A [`LanguageModel`] that returns scripted responses, one per call, and
+/// records every request it receives.
+///
+/// ```ignore
+/// let model = MockLanguageModel::new().with_text_response("Kathmandu");
+/// let text = generate_text(&mut model, options).await?;
+/// assert_eq!(model.requests().len(), 1);
+/// ```
+pub struct MockLanguageModel {
+    responses: Mutex<VecDeque<MockLanguageModelResponse>>,
+    requests: Mutex<Vec<LanguageModelDoGenerateRequest>>,
+    latency: Option<Duration>,
+    chunk_delay: Option<Duration>,
+    supports_urls: bool,
+    model_id: String,
+}
+
+impl Default for MockLanguageModel {
+    fn default() -> Self {
+        MockLanguageModel {
+            responses: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(Vec::new()),
+            latency: None,
+            chunk_delay: None,
+            supports_urls: true,
+            model_id: "mock-model".to_string(),
+        }
+    }
+}
+
+impl MockLanguageModel {
+    pub fn new() -> Self {
+        MockLanguageModel::default()
+    }
+
+    /// Queues a response for the next call.
+    pub fn with_response(self, response: MockLanguageModelResponse) -> Self {
+        self.responses.lock().unwrap().push_back(response);
+        self
+    }
+
+    /// Queues a `do_generate` response.
+    pub fn with_generate_response(self, response: LanguageModelDoGenerateResponse) -> Self {
+        self.with_response(MockLanguageModelResponse::Generate(Box::new(response)))
+    }
+
+    /// Queues a `do_generate` response that only contains `text`.
+    pub fn with_text_response(self, text: &str) -> Self {
+        self.with_generate_response(mock_text_response(text))
+    }
+
+    /// Queues a `do_generate` response that calls the given tools.
+    pub fn with_tool_calls_response(self, tool_calls: Vec<LanguageModelFunctionToolCall>) -> Self {
+        self.with_generate_response(LanguageModelDoGenerateResponse {
+            tool_calls,
+            finish_reason: LanguageModelFinishReason::ToolCalls,
+            usage: mock_usage(),
+            ..Default::default()
+        })
+    }
+
+    /// Queues a `do_stream` response that emits `parts`.
+    pub fn with_stream_response(self, parts: Vec<LanguageModelStreamPart>) -> Self {
+        self.with_response(MockLanguageModelResponse::Stream { parts, error: None })
+    }
+
+    /// Queues a `do_stream` response that emits `parts` and then fails with `error`.
+    pub fn with_partial_stream_response(
+        self,
+        parts: Vec<LanguageModelStreamPart>,
+        error: ModelError,
+    ) -> Self {
+        self.with_response(MockLanguageModelResponse::Stream {
+            parts,
+            error: Some(error),
+        })
+    }
+
+    /// Queues an error for the next call.
+    pub fn with_error(self, error: ModelError) -> Self {
+        self.with_response(MockLanguageModelResponse::Error(error))
+    }
+
+    /// Waits `latency` before every call returns.
+    pub fn with_latency(mut self, latency: Duration) -> Self {
+        self.latency = Some(latency);
+        self
+    }
+
+    /// Waits `delay` before every streamed part.
+    pub fn with_chunk_delay(mut self, delay: Duration) -> Self {
+        self.chunk_delay = Some(delay);
+        self
+    }
+
+    /// Sets the value returned by `supports_urls`. Defaults to `true`.
+    pub fn with_supports_urls(mut self, supports_urls: bool) -> Self {
+        self.supports_urls = supports_urls;
+        self
+    }
+
+    /// Sets the value returned by `model_id`. Defaults to `mock-model`.
+    pub fn with_model_id(mut self, model_id: &str) -> Self {
+        self.model_id = model_id.to_string();
+        self
+    }
+
+    /// Every request the model received, in call order.
+    pub fn requests(&self) -> MutexGuard<'_, Vec<LanguageModelDoGenerateRequest>> {
+        self.requests.lock().unwrap()
+    }
+
+    /// Number of scripted responses that were not used yet.
+    pub fn remaining_responses(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    async fn next_response(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<MockLanguageModelResponse, ModelError> {
+        self.requests.lock().unwrap().push(request);
+        let response = self.responses.lock().unwrap().pop_front();
+        if let Some(latency) = self.latency {
+            tokio::time::sleep(latency).await;
+        }
+        match response {
+            Some(MockLanguageModelResponse::Error(error)) => Err(error),
+            Some(response) => Ok(response),
+            None => Err(ModelError::Other(
+                "MockLanguageModel has no scripted response left".to_string(),
+            )),
+        }
+    }
+}
+
+#[async_trait]
+impl LanguageModel for MockLanguageModel {
+    fn provider(&self) -> String {
+        "mock".to_string()
+    }
+
+    fn model_id(&self) -> String {
+        self.model_id.clone()
+    }
+
+    fn supports_urls(&self, _url: String) -> bool {
+        self.supports_urls
+    }
+
+    async fn do_generate(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoGenerateResponse, ModelError> {
+        let call_settings = request.call_settings.clone();
+        match self.next_response(request).await? {
+            MockLanguageModelResponse::Generate(mut response) => {
+                // Report the settings the way a provider would.
+                response
+                    .request_body
+                    .get_or_insert(LanguageModelRequestMetadata {
+                        body: None,
+                        call_settings: None,
+                    })
+                    .call_settings
+                    .get_or_insert_with(|| call_settings.unwrap_or_default());
+                Ok(*response)
+            }
+            _ => Err(ModelError::Other(
+                "MockLanguageModel expected a do_generate response to be scripted".to_string(),
+            )),
+        }
+    }
+
+    async fn do_stream(
+        &self,
+        request: LanguageModelDoGenerateRequest,
+    ) -> Result<LanguageModelDoStreamResponse, ModelError> {
+        let call_settings = request.call_settings.clone();
+        let MockLanguageModelResponse::Stream { parts, error } =
+            self.next_response(request).await?
+        else {
+            return Err(ModelError::Other(
+                "MockLanguageModel expected a do_stream response to be scripted".to_string(),
+            ));
+        };
+
+        let chunk_delay = self.chunk_delay;
+        let stream = stream::iter(parts.into_iter().map(Ok).chain(error.map(Err)))
+            .then(move |part| async move {
+                if let Some(delay) = chunk_delay {
+                    tokio::time::sleep(delay).await;
+                }
+                part
+            })
+            .boxed();
+
+        Ok(LanguageModelDoStreamResponse {
+            stream,
+            request_body: Some(LanguageModelRequestMetadata {
+                body: None,
+                call_settings: Some(call_settings.unwrap_or_default()),
+            }),
+            response_headers: Vec::new(),
+            warnings: Vec::new(),
+        })
+    }
+}
+
+/// A `do_generate` response that only contains `text` and finished normally.
+pub fn mock_text_response(text: &str) -> LanguageModelDoGenerateResponse {
+    LanguageModelDoGenerateResponse {
+        text: Some(text.to_string()),
+        finish_reason: LanguageModelFinishReason::Stop,
+        usage: mock_usage(),
+        ..Default::default()
+    }
+}
+
+/// Stream parts that emit `text` in the given chunks and finish normally.
+pub fn mock_text_stream(chunks: &[&str]) -> Vec<LanguageModelStreamPart> {
+    chunks
+        .iter()
+        .map(|chunk| LanguageModelStreamPart::TextDelta(chunk.to_string()))
+        .chain([LanguageModelStreamPart::Finish {
+            finish_reason: LanguageModelFinishReason::Stop,
+            usage: mock_usage(),
+            provider_metadata: None,
+            logprobs: None,
+        }])
+        .collect()
+}
+
+fn mock_usage() -> LanguageModelUsage {
+    LanguageModelUsage::new(10, 20)
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+
+    use super::{mock_text_stream, MockLanguageModel};
+    use crate::{
+        errors::ModelError,
+        model::{
+            stream_part::LanguageModelStreamPart, LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat,
+        },
+    };
+
+    fn request() -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest {
+            call_settings: None,
+            input_format: LanguageModelDoGenerateRequestInputFormat::Prompt,
+            prompt: Vec::new(),
+            tools: None,
+            tool_choice: None,
+            provider_metadata: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_scripted_calls() {
+        let model = MockLanguageModel::new()
+            .with_error(ModelError::InternalError("rate limited".to_string()))
+            .with_text_response("Kathmandu")
+            .with_partial_stream_response(
+                mock_text_stream(&["Kath", "mandu"])
+                    .into_iter()
+                    .take(1)
+                    .collect(),
+                ModelError::InternalError("connection reset".to_string()),
+            );
+
+        assert!(model.do_generate(request()).await.is_err());
+        let response = model.do_generate(request()).await.unwrap();
+        assert_eq!(response.text.as_deref(), Some("Kathmandu"));
+
+        let parts: Vec<_> = model
+            .do_stream(request())
+            .await
+            .unwrap()
+            .stream
+            .collect()
+            .await;
+        assert!(matches!(
+            &parts[..],
+            [Ok(LanguageModelStreamPart::TextDelta(text)), Err(ModelError::InternalError(_))]
+                if text == "Kath"
+        ));
+
+        assert!(model.do_generate(request()).await.is_err());
+        assert_eq!(model.requests().len(), 4);
+        assert_eq!(model.remaining_responses(), 0);
+    }
+}
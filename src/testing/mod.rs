@@ -0,0 +1,7 @@
+//! Utilities for testing code that talks to a [`LanguageModel`](crate::model::LanguageModel)
+//! without making HTTP calls. Enable the `test-utils` feature to use them
+//! outside of this crate.
+
+mod mock_language_model;
+
+pub use mock_language_model::*;
@@ -0,0 +1,191 @@
+use serde_json::Value as JSONValue;
+
+use super::{Encoding, Tokenizer};
+use crate::{
+    model::{
+        message::{LanguageModelAssistantMessage, LanguageModelMessage, LanguageModelUserMessage},
+        tools::LanguageModelFunctionTool,
+    },
+    providers::openai::chat_model::model_id::OpenAIChatModelId,
+};
+
+/// Tokens OpenAI adds around every message.
+const TOKENS_PER_MESSAGE: usize = 3;
+/// Tokens that prime the assistant's reply.
+const REPLY_PRIMING_TOKENS: usize = 3;
+
+/// Counts the prompt tokens of a chat completion request, including the
+/// per-message overhead and the tool definitions.
+///
+/// Follows https://cookbook.openai.com/examples/how_to_count_tokens_with_tiktoken.
+/// Images and files are not counted.
+pub fn count_chat_tokens(
+    model_id: &OpenAIChatModelId,
+    messages: &[LanguageModelMessage],
+    tools: Option<&[LanguageModelFunctionTool]>,
+) -> usize {
+    let tokenizer = Tokenizer::for_model(model_id);
+    let messages_tokens: usize = messages
+        .iter()
+        .map(|message| count_message_tokens(tokenizer, message))
+        .sum();
+
+    let tools_tokens = tools
+        .filter(|tools| !tools.is_empty())
+        .map(|tools| count_tool_tokens(model_id, tools))
+        .unwrap_or_default();
+
+    messages_tokens + tools_tokens + REPLY_PRIMING_TOKENS
+}
+
+/// Counts the tokens of a single message, including the per-message overhead.
+pub(crate) fn count_message_tokens(tokenizer: &Tokenizer, message: &LanguageModelMessage) -> usize {
+    let count_json = |value: &JSONValue| match value {
+        JSONValue::String(text) => tokenizer.count(text),
+        value => tokenizer.count(&value.to_string()),
+    };
+
+    TOKENS_PER_MESSAGE
+        + match message {
+            LanguageModelMessage::System(text) => tokenizer.count("system") + tokenizer.count(text),
+            LanguageModelMessage::User(parts) => {
+                tokenizer.count("user")
+                    + parts
+                        .iter()
+                        .map(|part| match part {
+                            LanguageModelUserMessage::Text(part) => tokenizer.count(&part.text),
+                            _ => 0,
+                        })
+                        .sum::<usize>()
+            }
+            LanguageModelMessage::Assistant(parts) => {
+                tokenizer.count("assistant")
+                    + parts
+                        .iter()
+                        .map(|part| match part {
+                            LanguageModelAssistantMessage::Text(part) => {
+                                tokenizer.count(&part.text)
+                            }
+                            LanguageModelAssistantMessage::ToolCall(part) => {
+                                tokenizer.count(&part.tool_name) + count_json(&part.args)
+                            }
+                            _ => 0,
+                        })
+                        .sum::<usize>()
+            }
+            LanguageModelMessage::Tool(parts) => parts
+                .iter()
+                .map(|part| tokenizer.count("tool") + count_json(&part.result))
+                .sum(),
+        }
+}
+
+/// Counts the tokens of the tool definitions, which OpenAI renders into the
+/// system prompt. Only top-level parameters are taken into account.
+pub fn count_tool_tokens(
+    model_id: &OpenAIChatModelId,
+    tools: &[LanguageModelFunctionTool],
+) -> usize {
+    let tokenizer = Tokenizer::for_model(model_id);
+    let function_init: isize = match model_id.encoding() {
+        Encoding::Cl100kBase => 10,
+        Encoding::O200kBase => 7,
+    };
+    const PROPERTY_INIT: isize = 3;
+    const PROPERTY_KEY: isize = 3;
+    const ENUM_INIT: isize = -3;
+    const ENUM_ITEM: isize = 3;
+    const FUNCTION_END: isize = 12;
+
+    let count = |text: &str| tokenizer.count(text) as isize;
+    let mut tokens = 0;
+    for tool in tools {
+        let description = tool.description.as_deref().unwrap_or_default();
+        tokens += function_init
+            + count(&format!(
+                "{}:{}",
+                tool.name,
+                description.strip_suffix('.').unwrap_or(description)
+            ));
+
+        let Some(properties) = tool.parameters["properties"]
+            .as_object()
+            .filter(|properties| !properties.is_empty())
+        else {
+            continue;
+        };
+        tokens += PROPERTY_INIT;
+        for (name, property) in properties {
+            tokens += PROPERTY_KEY;
+            if let Some(items) = property["enum"].as_array() {
+                tokens += ENUM_INIT;
+                for item in items {
+                    tokens += ENUM_ITEM
+                        + count(
+                            &item
+                                .as_str()
+                                .map_or_else(|| item.to_string(), str::to_string),
+                        );
+                }
+            }
+            let description = property["description"].as_str().unwrap_or_default();
+            tokens += count(&format!(
+                "{name}:{}:{}",
+                property["type"].as_str().unwrap_or_default(),
+                description.strip_suffix('.').unwrap_or(description)
+            ));
+        }
+    }
+    (tokens + FUNCTION_END).max(0) as usize
+}
+
+#[cfg(test)]
+mod test {
+    use serde_json::json;
+
+    use super::{count_chat_tokens, count_tool_tokens};
+    use crate::{
+        model::{
+            message::{LanguageModelMessage, LanguageModelTextPart, LanguageModelUserMessage},
+            tools::LanguageModelFunctionTool,
+        },
+        providers::openai::chat_model::model_id::OpenAIChatModelId,
+    };
+
+    #[test]
+    fn test_count_chat_tokens() {
+        let messages = vec![
+            LanguageModelMessage::System("You are a helpful assistant.".to_string()),
+            LanguageModelMessage::User(vec![LanguageModelUserMessage::Text(
+                LanguageModelTextPart {
+                    text: "What is the capital of Nepal?".to_string(),
+                    provider_metadata: None,
+                },
+            )]),
+        ];
+        // 3 + 1 + 6 for the system message, 3 + 1 + 7 for the user message
+        // and 3 for the reply.
+        assert_eq!(
+            count_chat_tokens(&OpenAIChatModelId::GPT4oMini, &messages, None),
+            24
+        );
+
+        let tools = vec![LanguageModelFunctionTool {
+            name: "weather".to_string(),
+            description: Some("Get the weather in a city.".to_string()),
+            parameters: json!({
+                "type": "object",
+                "properties": {
+                    "city": { "type": "string", "description": "The city." },
+                    "unit": { "type": "string", "enum": ["celsius", "fahrenheit"] }
+                }
+            }),
+        }];
+        let tool_tokens = count_tool_tokens(&OpenAIChatModelId::GPT4oMini, &tools);
+        assert!(tool_tokens > 20);
+        assert_eq!(
+            count_chat_tokens(&OpenAIChatModelId::GPT4oMini, &messages, Some(&tools)),
+            24 + tool_tokens
+        );
+    }
+}
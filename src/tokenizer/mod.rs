@@ -0,0 +1,216 @@
+//! Offline token counting with the byte pair encodings of the OpenAI models.
+//!
+//! The rank tables of `cl100k_base` and `o200k_base` are embedded in the
+//! binary, see `assets/`. They are the files published by OpenAI for
+//! `tiktoken`.
+
+mod chat;
+
+pub(crate) use chat::count_message_tokens;
+pub use chat::{count_chat_tokens, count_tool_tokens};
+
+use base64::{engine::general_purpose, Engine as _};
+use fancy_regex::Regex;
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::providers::openai::chat_model::model_id::OpenAIChatModelId;
+
+type Rank = u32;
+
+const CL100K_BASE: &str = include_str!("assets/cl100k_base.tiktoken");
+const O200K_BASE: &str = include_str!("assets/o200k_base.tiktoken");
+
+const CL100K_BASE_PATTERN: &str = concat!(
+    r"(?i:'s|'t|'re|'ve|'m|'ll|'d)|[^\r\n\p{L}\p{N}]?\p{L}+|\p{N}{1,3}",
+    r"| ?[^\s\p{L}\p{N}]+[\r\n]*|\s*[\r\n]+|\s+(?!\S)|\s+",
+);
+const O200K_BASE_PATTERN: &str = concat!(
+    r"[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]*[\p{Ll}\p{Lm}\p{Lo}\p{M}]+(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|[^\r\n\p{L}\p{N}]?[\p{Lu}\p{Lt}\p{Lm}\p{Lo}\p{M}]+[\p{Ll}\p{Lm}\p{Lo}\p{M}]*(?i:'s|'t|'re|'ve|'m|'ll|'d)?",
+    r"|\p{N}{1,3}| ?[^\s\p{L}\p{N}]+[\r\n/]*|\s*[\r\n]+|\s+(?!\S)|\s+",
+);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Encoding {
+    /// GPT-4, GPT-4 Turbo and GPT-3.5 Turbo.
+    Cl100kBase,
+    /// GPT-4o, GPT-4.1, GPT-4.5 and the o-series.
+    O200kBase,
+}
+
+impl OpenAIChatModelId {
+    /// The encoding the model tokenizes with. Custom models, including
+    /// fine-tunes such as `ft:gpt-4o-mini:acme`, are matched by their base model.
+    pub fn encoding(&self) -> Encoding {
+        let id = self.to_string();
+        let base = id.strip_prefix("ft:").unwrap_or(&id);
+        if base.starts_with("gpt-4-") || base == "gpt-4" || base.starts_with("gpt-3.5") {
+            Encoding::Cl100kBase
+        } else {
+            Encoding::O200kBase
+        }
+    }
+}
+
+/// A byte pair encoding. Tokenizers are loaded once, on first use.
+pub struct Tokenizer {
+    ranks: HashMap<Vec<u8>, Rank>,
+    tokens: HashMap<Rank, Vec<u8>>,
+    pattern: Regex,
+}
+
+impl Tokenizer {
+    pub fn get(encoding: Encoding) -> &'static Tokenizer {
+        static CL100K: OnceLock<Tokenizer> = OnceLock::new();
+        static O200K: OnceLock<Tokenizer> = OnceLock::new();
+        match encoding {
+            Encoding::Cl100kBase => {
+                CL100K.get_or_init(|| Tokenizer::new(CL100K_BASE, CL100K_BASE_PATTERN))
+            }
+            Encoding::O200kBase => {
+                O200K.get_or_init(|| Tokenizer::new(O200K_BASE, O200K_BASE_PATTERN))
+            }
+        }
+    }
+
+    pub fn for_model(model_id: &OpenAIChatModelId) -> &'static Tokenizer {
+        Tokenizer::get(model_id.encoding())
+    }
+
+    /// Parses a `tiktoken` rank file: one base64 encoded token and its rank
+    /// per line. The embedded files are known to be valid.
+    fn new(rank_file: &str, pattern: &str) -> Self {
+        let ranks: HashMap<Vec<u8>, Rank> = rank_file
+            .lines()
+            .filter_map(|line| {
+                let (token, rank) = line.split_once(' ')?;
+                Some((
+                    general_purpose::STANDARD.decode(token).ok()?,
+                    rank.parse().ok()?,
+                ))
+            })
+            .collect();
+        let tokens = ranks
+            .iter()
+            .map(|(token, rank)| (*rank, token.clone()))
+            .collect();
+        Tokenizer {
+            ranks,
+            tokens,
+            pattern: Regex::new(pattern).expect("valid tokenizer pattern"),
+        }
+    }
+
+    /// Encodes `text`. Special tokens such as `<|endoftext|>` are encoded
+    /// as plain text.
+    pub fn encode(&self, text: &str) -> Vec<Rank> {
+        let mut tokens = Vec::new();
+        // The patterns only use lookahead on whitespace and cannot fail.
+        for piece in self.pattern.find_iter(text).filter_map(Result::ok) {
+            let piece = piece.as_str().as_bytes();
+            match self.ranks.get(piece) {
+                Some(rank) => tokens.push(*rank),
+                None => tokens.extend(self.byte_pair_encode(piece)),
+            }
+        }
+        tokens
+    }
+
+    pub fn count(&self, text: &str) -> usize {
+        self.encode(text).len()
+    }
+
+    /// Decodes `tokens`, replacing invalid UTF-8, e.g. from a token sequence
+    /// cut in the middle of a character.
+    pub fn decode(&self, tokens: &[Rank]) -> String {
+        let bytes: Vec<u8> = tokens
+            .iter()
+            .filter_map(|token| self.tokens.get(token))
+            .flatten()
+            .copied()
+            .collect();
+        String::from_utf8_lossy(&bytes).into_owned()
+    }
+
+    fn byte_pair_encode(&self, piece: &[u8]) -> Vec<Rank> {
+        if piece.len() == 1 {
+            return self.ranks.get(piece).copied().into_iter().collect();
+        }
+        self.byte_pair_merge(piece)
+            .windows(2)
+            .filter_map(|part| self.ranks.get(&piece[part[0].0..part[1].0]).copied())
+            .collect()
+    }
+
+    /// Repeatedly merges the adjacent pair of parts with the lowest rank.
+    /// Returns the start of every remaining part, and the end of the piece.
+    fn byte_pair_merge(&self, piece: &[u8]) -> Vec<(usize, Rank)> {
+        let rank_of = |start: usize, end: usize| {
+            self.ranks
+                .get(&piece[start..end])
+                .copied()
+                .unwrap_or(Rank::MAX)
+        };
+
+        // Every entry holds the start of a part and the rank of the part
+        // merged with its successor.
+        let mut parts: Vec<(usize, Rank)> = (0..piece.len() - 1)
+            .map(|start| (start, rank_of(start, start + 2)))
+            .collect();
+        parts.push((piece.len() - 1, Rank::MAX));
+        parts.push((piece.len(), Rank::MAX));
+
+        let merged_rank = |parts: &[(usize, Rank)], i: usize| {
+            if i + 3 < parts.len() {
+                rank_of(parts[i].0, parts[i + 3].0)
+            } else {
+                Rank::MAX
+            }
+        };
+
+        loop {
+            let Some((i, _)) = parts[..parts.len() - 1]
+                .iter()
+                .enumerate()
+                .filter(|(_, (_, rank))| *rank != Rank::MAX)
+                .min_by_key(|(_, (_, rank))| *rank)
+            else {
+                return parts;
+            };
+            if i > 0 {
+                parts[i - 1].1 = merged_rank(&parts, i - 1);
+            }
+            parts[i].1 = merged_rank(&parts, i);
+            parts.remove(i + 1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Encoding, Tokenizer};
+    use crate::providers::openai::chat_model::model_id::OpenAIChatModelId;
+
+    #[test]
+    fn test_encode_and_decode() {
+        let cl100k = Tokenizer::get(Encoding::Cl100kBase);
+        assert_eq!(cl100k.encode("hello world"), [15339, 1917]);
+        let o200k = Tokenizer::get(Encoding::O200kBase);
+        assert_eq!(o200k.encode("hello world"), [24912, 2375]);
+
+        let text = "Kathmandu is the capital of Nepal 🇳🇵.\n\n  It's at 1,400 m!";
+        for tokenizer in [cl100k, o200k] {
+            assert_eq!(tokenizer.decode(&tokenizer.encode(text)), text);
+        }
+
+        assert_eq!(
+            OpenAIChatModelId::GPT4Turbo.encoding(),
+            Encoding::Cl100kBase
+        );
+        assert_eq!(OpenAIChatModelId::O4Mini.encoding(), Encoding::O200kBase);
+        assert_eq!(
+            OpenAIChatModelId::Custom("ft:gpt-3.5-turbo:acme".to_string()).encoding(),
+            Encoding::Cl100kBase
+        );
+    }
+}
@@ -0,0 +1,595 @@
+//! Record-and-replay of HTTP exchanges.
+//!
+//! A [`RecordingTransport`] forwards requests to a real transport and writes
+//! every request/response pair, including streamed bodies, to a JSON cassette
+//! file with credentials redacted. A [`ReplayTransport`] serves the responses
+//! from that file, so provider tests can run offline.
+
+use async_trait::async_trait;
+use base64::{engine::general_purpose, Engine as _};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JSONValue;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+
+use super::{default_transport, HttpRequest, HttpResponse, HttpTransport};
+use crate::errors::ModelError;
+
+/// Replaces redacted header and query parameter values in cassettes.
+pub const REDACTED: &str = "[REDACTED]";
+
+/// Environment variable that switches [`cassette_transport`] to recording.
+pub const RECORD_ENV_VAR: &str = "CORTEX_RECORD_CASSETTES";
+
+/// Headers whose values are never written to a cassette.
+const DEFAULT_REDACTED_HEADERS: &[&str] = &[
+    "authorization",
+    "x-api-key",
+    "api-key",
+    "x-goog-api-key",
+    "cookie",
+    "set-cookie",
+];
+
+/// Query parameters whose values are never written to a cassette.
+const DEFAULT_REDACTED_QUERY_PARAMS: &[&str] = &["key", "api_key", "api-key", "access_token"];
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    pub interactions: Vec<CassetteInteraction>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteInteraction {
+    pub request: CassetteRequest,
+    pub response: CassetteResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    /// The JSON body, or the raw body as a string if it is not JSON.
+    pub body: JSONValue,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CassetteResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    /// The raw body. Server-sent events are stored as received; bodies that
+    /// are not UTF-8 are stored encoded, see `body_encoding`.
+    pub body: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub body_encoding: Option<CassetteBodyEncoding>,
+}
+
+/// How a binary [`CassetteResponse`] body is stored.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CassetteBodyEncoding {
+    Base64,
+}
+
+impl CassetteResponse {
+    /// Stores `body` as text if it is UTF-8 and as base64 otherwise.
+    pub fn new(status: u16, headers: Vec<(String, String)>, body: &[u8]) -> Self {
+        let (body, body_encoding) = match std::str::from_utf8(body) {
+            Ok(text) => (text.to_string(), None),
+            Err(_) => (
+                general_purpose::STANDARD.encode(body),
+                Some(CassetteBodyEncoding::Base64),
+            ),
+        };
+        CassetteResponse {
+            status,
+            headers,
+            body,
+            body_encoding,
+        }
+    }
+
+    /// The body as it was received.
+    pub fn body_bytes(&self) -> Result<Vec<u8>, ModelError> {
+        match self.body_encoding {
+            None => Ok(self.body.clone().into_bytes()),
+            Some(CassetteBodyEncoding::Base64) => general_purpose::STANDARD
+                .decode(&self.body)
+                .map_err(|e| ModelError::InternalError(format!("Invalid cassette body: {e}"))),
+        }
+    }
+}
+
+impl Cassette {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        let content = fs::read_to_string(path).map_err(|e| {
+            ModelError::InternalError(format!("Failed to read cassette {}: {e}", path.display()))
+        })?;
+        serde_json::from_str(&content).map_err(|e| {
+            ModelError::InternalError(format!("Invalid cassette {}: {e}", path.display()))
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), ModelError> {
+        let path = path.as_ref();
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| {
+                ModelError::InternalError(format!(
+                    "Failed to create cassette directory {}: {e}",
+                    parent.display()
+                ))
+            })?;
+        }
+        let content = serde_json::to_string_pretty(self)
+            .map_err(|e| ModelError::InternalError(format!("Failed to serialize cassette: {e}")))?;
+        fs::write(path, content).map_err(|e| {
+            ModelError::InternalError(format!("Failed to write cassette {}: {e}", path.display()))
+        })
+    }
+}
+
+/// Parses a body so that requests match regardless of JSON formatting and
+/// key order.
+fn normalize_body(body: &str) -> JSONValue {
+    serde_json::from_str(body).unwrap_or_else(|_| JSONValue::String(body.to_string()))
+}
+
+fn default_redacted_query_params() -> Vec<String> {
+    DEFAULT_REDACTED_QUERY_PARAMS
+        .iter()
+        .map(|param| param.to_string())
+        .collect()
+}
+
+/// Replaces the values of the query parameters in `params`.
+fn redact_url(url: &str, params: &[String]) -> String {
+    let Some((base, query)) = url.split_once('?') else {
+        return url.to_string();
+    };
+    let query = query
+        .split('&')
+        .map(|pair| match pair.split_once('=') {
+            Some((key, _)) if params.iter().any(|p| p == key) => format!("{key}={REDACTED}"),
+            _ => pair.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("&");
+    format!("{base}?{query}")
+}
+
+/// Forwards requests to another transport and records them to a cassette.
+///
+/// The cassette file is rewritten after every completed exchange; a streamed
+/// response is complete once its body was read to the end.
+pub struct RecordingTransport {
+    inner: Arc<dyn HttpTransport>,
+    path: PathBuf,
+    cassette: Arc<Mutex<Cassette>>,
+    redacted_headers: Vec<String>,
+    redacted_query_params: Vec<String>,
+}
+
+impl RecordingTransport {
+    /// Records to `path` using the default network transport. An existing
+    /// cassette at `path` is overwritten.
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        RecordingTransport {
+            inner: default_transport(),
+            path: path.into(),
+            cassette: Arc::new(Mutex::new(Cassette::default())),
+            redacted_headers: DEFAULT_REDACTED_HEADERS
+                .iter()
+                .map(|header| header.to_string())
+                .collect(),
+            redacted_query_params: default_redacted_query_params(),
+        }
+    }
+
+    /// Sends the recorded requests through `inner` instead of the network.
+    pub fn with_inner(mut self, inner: Arc<dyn HttpTransport>) -> Self {
+        self.inner = inner;
+        self
+    }
+
+    /// Also redacts the given header, e.g. a provider specific key header.
+    pub fn with_redacted_header(mut self, header: &str) -> Self {
+        self.redacted_headers.push(header.to_lowercase());
+        self
+    }
+
+    /// Also redacts the given URL query parameter.
+    pub fn with_redacted_query_param(mut self, param: &str) -> Self {
+        self.redacted_query_params.push(param.to_string());
+        self
+    }
+
+    /// The exchanges recorded so far.
+    pub fn cassette(&self) -> Cassette {
+        self.cassette.lock().unwrap().clone()
+    }
+
+    fn redact_headers(&self, headers: &[(String, String)]) -> Vec<(String, String)> {
+        headers
+            .iter()
+            .map(|(key, value)| {
+                if self.redacted_headers.contains(&key.to_lowercase()) {
+                    (key.clone(), REDACTED.to_string())
+                } else {
+                    (key.clone(), value.clone())
+                }
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for RecordingTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, ModelError> {
+        let recorded_request = CassetteRequest {
+            method: request.method.clone(),
+            url: redact_url(&request.url, &self.redacted_query_params),
+            headers: self.redact_headers(&request.headers),
+            body: normalize_body(&request.body),
+        };
+        let response = self.inner.send(request).await?;
+
+        let recorder = Recorder {
+            request: recorded_request,
+            status: response.status,
+            headers: self.redact_headers(&response.headers),
+            cassette: self.cassette.clone(),
+            path: self.path.clone(),
+        };
+        let body = stream::unfold(
+            (response.body, Vec::new(), Some(recorder)),
+            |(mut body, mut buffer, recorder)| async move {
+                let recorder = recorder?;
+                match body.next().await {
+                    Some(Ok(chunk)) => {
+                        buffer.extend_from_slice(&chunk);
+                        Some((Ok(chunk), (body, buffer, Some(recorder))))
+                    }
+                    Some(Err(e)) => Some((Err(e), (body, buffer, None))),
+                    None => match recorder.finish(&buffer) {
+                        Ok(()) => None,
+                        Err(e) => Some((Err(e), (body, buffer, None))),
+                    },
+                }
+            },
+        )
+        .boxed();
+
+        Ok(HttpResponse {
+            status: response.status,
+            headers: response.headers,
+            body,
+        })
+    }
+}
+
+struct Recorder {
+    request: CassetteRequest,
+    status: u16,
+    headers: Vec<(String, String)>,
+    cassette: Arc<Mutex<Cassette>>,
+    path: PathBuf,
+}
+
+impl Recorder {
+    fn finish(self, body: &[u8]) -> Result<(), ModelError> {
+        let mut cassette = self.cassette.lock().unwrap();
+        cassette.interactions.push(CassetteInteraction {
+            request: self.request,
+            response: CassetteResponse::new(self.status, self.headers, body),
+        });
+        cassette.save(&self.path)
+    }
+}
+
+/// Serves responses from a cassette instead of the network.
+///
+/// Requests are matched by method, URL and normalized body. Every recorded
+/// interaction is served once, in recording order; a request without an
+/// unused match fails with an error describing what was recorded.
+///
+/// Query parameters are redacted like in [`RecordingTransport`] before
+/// matching, so the redacted parameters must be configured the same way.
+pub struct ReplayTransport {
+    name: String,
+    cassette: Cassette,
+    used: Mutex<Vec<bool>>,
+    redacted_query_params: Vec<String>,
+}
+
+impl ReplayTransport {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ModelError> {
+        let path = path.as_ref();
+        Ok(ReplayTransport::new(
+            &path.display().to_string(),
+            Cassette::load(path)?,
+        ))
+    }
+
+    pub fn new(name: &str, cassette: Cassette) -> Self {
+        let used = vec![false; cassette.interactions.len()];
+        ReplayTransport {
+            name: name.to_string(),
+            cassette,
+            used: Mutex::new(used),
+            redacted_query_params: default_redacted_query_params(),
+        }
+    }
+
+    /// Also redacts the given URL query parameter, see
+    /// [`RecordingTransport::with_redacted_query_param`].
+    pub fn with_redacted_query_param(mut self, param: &str) -> Self {
+        self.redacted_query_params.push(param.to_string());
+        self
+    }
+
+    /// Number of recorded interactions that were not replayed yet.
+    pub fn unused_interactions(&self) -> usize {
+        self.used
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|used| !**used)
+            .count()
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReplayTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, ModelError> {
+        let url = redact_url(&request.url, &self.redacted_query_params);
+        let body = normalize_body(&request.body);
+        let mut used = self.used.lock().unwrap();
+        let index =
+            self.cassette
+                .interactions
+                .iter()
+                .enumerate()
+                .position(|(index, interaction)| {
+                    !used[index]
+                        && interaction
+                            .request
+                            .method
+                            .eq_ignore_ascii_case(&request.method)
+                        && interaction.request.url == url
+                        && interaction.request.body == body
+                });
+
+        let Some(index) = index else {
+            let recorded = self
+                .cassette
+                .interactions
+                .iter()
+                .zip(used.iter())
+                .map(|(interaction, used)| {
+                    format!(
+                        "\n  {} {}{} {}",
+                        interaction.request.method,
+                        interaction.request.url,
+                        if *used { " (used)" } else { "" },
+                        interaction.request.body
+                    )
+                })
+                .collect::<String>();
+            return Err(ModelError::InternalError(format!(
+                "Cassette {} has no unused interaction matching {} {} {}. Recorded:{recorded}",
+                self.name, request.method, url, body
+            )));
+        };
+        used[index] = true;
+
+        let response = &self.cassette.interactions[index].response;
+        let chunk = response.body_bytes()?;
+        let body: BoxStream<'static, Result<Vec<u8>, ModelError>> =
+            stream::once(async move { Ok(chunk) }).boxed();
+        Ok(HttpResponse {
+            status: response.status,
+            headers: response.headers.clone(),
+            body,
+        })
+    }
+}
+
+/// Records to `path` when the `CORTEX_RECORD_CASSETTES` environment variable
+/// is set and replays from it otherwise.
+pub fn cassette_transport(path: impl AsRef<Path>) -> Result<Arc<dyn HttpTransport>, ModelError> {
+    let path = path.as_ref();
+    if std::env::var_os(RECORD_ENV_VAR).is_some() {
+        Ok(Arc::new(RecordingTransport::new(path)))
+    } else {
+        Ok(Arc::new(ReplayTransport::load(path)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use futures::StreamExt;
+    use serde_json::json;
+    use std::sync::Arc;
+    use wiremock::{
+        matchers::{method, path},
+        Mock, MockServer, ResponseTemplate,
+    };
+
+    use super::{Cassette, CassetteBodyEncoding, RecordingTransport, ReplayTransport, REDACTED};
+    use crate::{
+        model::{
+            call_settings::LanguageModelCallSettings,
+            message::{LanguageModelMessage, LanguageModelTextPart, LanguageModelUserMessage},
+            stream_part::LanguageModelStreamPart,
+            LanguageModel, LanguageModelDoGenerateRequest,
+            LanguageModelDoGenerateRequestInputFormat,
+        },
+        provider::LanguageModelProvider,
+        providers::openai::{provider_settings::OpenAIProviderSettings, OpenAIProvider},
+        transport::{HttpRequest, HttpTransport},
+    };
+
+    fn request(text: &str) -> LanguageModelDoGenerateRequest {
+        LanguageModelDoGenerateRequest {
+            call_settings: Some(LanguageModelCallSettings::default()),
+            input_format: LanguageModelDoGenerateRequestInputFormat::Messages,
+            prompt: vec![LanguageModelMessage::User(vec![
+                LanguageModelUserMessage::Text(LanguageModelTextPart {
+                    text: text.to_string(),
+                    provider_metadata: None,
+                }),
+            ])],
+            tools: None,
+            tool_choice: None,
+            provider_metadata: None,
+        }
+    }
+
+    async fn stream_text(
+        transport: Arc<dyn HttpTransport>,
+        base_url: &str,
+        prompt: &str,
+    ) -> String {
+        let provider = OpenAIProvider::new(
+            OpenAIProviderSettings::new("sk-secret".to_string())
+                .base_url(base_url)
+                .transport(transport),
+        );
+        let model = provider.language_model("gpt-4o").unwrap();
+        let response = model.do_stream(request(prompt)).await.unwrap();
+        response
+            .stream
+            .filter_map(|part| async move {
+                match part.unwrap() {
+                    LanguageModelStreamPart::TextDelta(delta) => Some(delta),
+                    _ => None,
+                }
+            })
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_record_and_replay_stream() {
+        let body = [
+            json!({ "id": "chatcmpl-1", "choices": [{ "index": 0, "delta": { "content": "Kath" } }] }),
+            json!({ "id": "chatcmpl-1", "choices": [{ "index": 0, "delta": { "content": "mandu" }, "finish_reason": "stop" }] }),
+        ]
+        .iter()
+        .map(|chunk| format!("data: {chunk}\n\n"))
+        .chain(["data: [DONE]\n\n".to_string()])
+        .collect::<String>();
+
+        let server = MockServer::start().await;
+        Mock::given(method("POST"))
+            .and(path("/chat/completions"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let base_url = server.uri();
+
+        let cassette_path = std::env::temp_dir().join(format!(
+            "cortex-cassette-{}-{}.json",
+            std::process::id(),
+            crate::utils::current_timestamp()
+        ));
+        let recorder = Arc::new(RecordingTransport::new(&cassette_path));
+        let text = stream_text(recorder.clone(), &base_url, "Capital of Nepal?").await;
+        assert_eq!(text, "Kathmandu");
+        drop(server);
+
+        let cassette = Cassette::load(&cassette_path).unwrap();
+        let interaction = &cassette.interactions[0];
+        assert!(interaction
+            .request
+            .headers
+            .iter()
+            .any(|(key, value)| key == "Authorization" && value == REDACTED));
+        assert!(!std::fs::read_to_string(&cassette_path)
+            .unwrap()
+            .contains("sk-secret"));
+        assert!(interaction.response.body.contains("data: [DONE]"));
+
+        let replay = Arc::new(ReplayTransport::load(&cassette_path).unwrap());
+        let text = stream_text(replay.clone(), &base_url, "Capital of Nepal?").await;
+        assert_eq!(text, "Kathmandu");
+        assert_eq!(replay.unused_interactions(), 0);
+
+        let provider = OpenAIProvider::new(
+            OpenAIProviderSettings::new("sk-secret".to_string())
+                .base_url(&base_url)
+                .transport(Arc::new(ReplayTransport::new("memory", cassette))),
+        );
+        let error = provider
+            .language_model("gpt-4o")
+            .unwrap()
+            .do_generate(request("Capital of Bhutan?"))
+            .await
+            .err()
+            .unwrap();
+        assert!(error.to_string().contains("no unused interaction matching"));
+
+        std::fs::remove_file(&cassette_path).unwrap();
+    }
+
+    #[tokio::test]
+    async fn test_replay_redacted_query_and_binary_body() {
+        let image = vec![0xff, 0xd8, 0xff, 0xe0, 0x00, 0x10];
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/image.jpg"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(image.clone(), "image/jpeg"))
+            .expect(1)
+            .mount(&server)
+            .await;
+        let request = HttpRequest {
+            method: "GET".to_string(),
+            url: format!("{}/image.jpg?size=large&key=secret", server.uri()),
+            headers: Vec::new(),
+            body: String::new(),
+        };
+
+        let cassette_path = std::env::temp_dir().join(format!(
+            "cortex-cassette-binary-{}-{}.json",
+            std::process::id(),
+            crate::utils::current_timestamp()
+        ));
+        let recorder = RecordingTransport::new(&cassette_path);
+        let body = recorder
+            .send(request.clone())
+            .await
+            .unwrap()
+            .bytes()
+            .await
+            .unwrap();
+        assert_eq!(body, image);
+        drop(server);
+
+        let cassette = Cassette::load(&cassette_path).unwrap();
+        let interaction = &cassette.interactions[0];
+        assert!(interaction
+            .request
+            .url
+            .ends_with(&format!("/image.jpg?size=large&key={REDACTED}")));
+        assert_eq!(
+            interaction.response.body_encoding,
+            Some(CassetteBodyEncoding::Base64)
+        );
+        std::fs::remove_file(&cassette_path).unwrap();
+
+        let replay = ReplayTransport::new("memory", cassette);
+        let body = replay.send(request).await.unwrap().bytes().await.unwrap();
+        assert_eq!(body, image);
+        assert_eq!(replay.unused_interactions(), 0);
+    }
+}
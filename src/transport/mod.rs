@@ -0,0 +1,177 @@
+//! The HTTP layer used by the providers.
+//!
+//! Every provider sends its requests through an [`HttpTransport`], which
+//! defaults to [`ReqwestTransport`]. Swapping it out allows recording real
+//! exchanges to cassettes and replaying them offline, see [`cassette`].
+
+pub mod cassette;
+
+use async_trait::async_trait;
+use futures::{stream::BoxStream, StreamExt};
+use std::sync::Arc;
+
+use crate::errors::{ApiCallError, ModelError};
+
+/// A request as sent by a provider.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    pub url: String,
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+/// A response whose body is read incrementally, so that server-sent events
+/// can be processed while they arrive.
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: BoxStream<'static, Result<Vec<u8>, ModelError>>,
+}
+
+impl HttpResponse {
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+
+    /// Reads the whole body.
+    pub async fn bytes(self) -> Result<Vec<u8>, ModelError> {
+        let mut body = Vec::new();
+        let mut chunks = self.body;
+        while let Some(chunk) = chunks.next().await {
+            body.extend(chunk?);
+        }
+        Ok(body)
+    }
+
+    /// Reads the whole body into a string.
+    pub async fn text(self) -> Result<String, ModelError> {
+        Ok(String::from_utf8_lossy(&self.bytes().await?).into_owned())
+    }
+}
+
+#[async_trait]
+pub trait HttpTransport: Send + Sync {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, ModelError>;
+}
+
+/// Sends requests over the network with `reqwest`.
+///
+/// The default client does not follow redirects, so that prompt downloads
+/// can check every hop against [`DownloadSettings`]. A client passed to
+/// [`ReqwestTransport::new`] is used as is.
+#[derive(Debug, Clone)]
+pub struct ReqwestTransport {
+    client: reqwest::Client,
+}
+
+impl Default for ReqwestTransport {
+    fn default() -> Self {
+        let client = reqwest::Client::builder()
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("the default reqwest client builds");
+        ReqwestTransport { client }
+    }
+}
+
+impl ReqwestTransport {
+    pub fn new(client: reqwest::Client) -> Self {
+        ReqwestTransport { client }
+    }
+}
+
+#[async_trait]
+impl HttpTransport for ReqwestTransport {
+    async fn send(&self, request: HttpRequest) -> Result<HttpResponse, ModelError> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes()).map_err(|e| {
+            ModelError::InvalidArgument(format!("Invalid HTTP method {}: {e}", request.method))
+        })?;
+        let mut builder = self.client.request(method, &request.url);
+        for (key, value) in &request.headers {
+            builder = builder.header(key, value);
+        }
+
+        let url = request.url.clone();
+        let response = builder.body(request.body).send().await.map_err(|e| {
+            // Timeouts and connection failures, e.g. resets, may succeed on retry.
+            let is_retryable = e.is_timeout() || e.is_connect() || e.is_request();
+            ModelError::from(ApiCallError::connection(&url, e.to_string(), is_retryable))
+        })?;
+
+        let status = response.status().as_u16();
+        let headers = response
+            .headers()
+            .iter()
+            .map(|(key, value)| {
+                (
+                    key.to_string(),
+                    value.to_str().unwrap_or_default().to_string(),
+                )
+            })
+            .collect();
+        let body = response
+            .bytes_stream()
+            .map(|chunk| {
+                chunk.map(|bytes| bytes.to_vec()).map_err(|e| {
+                    ModelError::InternalError(format!("Failed to read response body: {e}"))
+                })
+            })
+            .boxed();
+
+        Ok(HttpResponse {
+            status,
+            headers,
+            body,
+        })
+    }
+}
+
+/// Returns whether a URL may be downloaded.
+pub type UrlFilter = Arc<dyn Fn(&str) -> bool + Send + Sync>;
+
+/// Limits for downloading the image and file URLs of a prompt, see
+/// [`GenerateTextOptions::download_settings`](crate::core::generate_text::GenerateTextOptions::download_settings).
+///
+/// Only `http` and `https` URLs are downloaded.
+#[derive(Clone)]
+pub struct DownloadSettings {
+    /// Maximum size of a downloaded body in bytes. Defaults to 20 MiB.
+    pub max_bytes: usize,
+    /// Rejects URLs, e.g. private network addresses when URLs come from
+    /// untrusted users. It is also applied to every redirect.
+    pub url_filter: Option<UrlFilter>,
+}
+
+impl Default for DownloadSettings {
+    fn default() -> Self {
+        DownloadSettings {
+            max_bytes: 20 * 1024 * 1024,
+            url_filter: None,
+        }
+    }
+}
+
+impl DownloadSettings {
+    pub fn new() -> Self {
+        DownloadSettings::default()
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn with_url_filter(
+        mut self,
+        url_filter: impl Fn(&str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.url_filter = Some(Arc::new(url_filter));
+        self
+    }
+}
+
+/// The transport used by providers unless one is configured explicitly.
+pub fn default_transport() -> Arc<dyn HttpTransport> {
+    Arc::new(ReqwestTransport::default())
+}
@@ -1,7 +1,287 @@
+use base64::{engine::general_purpose, Engine as _};
+use futures::{
+    stream::{self, BoxStream},
+    StreamExt,
+};
+use serde_json::Value as JSONValue;
+use std::time::{SystemTime, UNIX_EPOCH};
+use tracing::{field::Empty, Instrument};
+
+use crate::{
+    errors::{ApiCallError, ApiErrorPayloadParser, ModelError},
+    model::message::{LanguageModelImagePart, LanguageModelImagePartContent},
+    transport::{DownloadSettings, HttpRequest, HttpResponse, HttpTransport},
+};
+
 pub fn without_trailing_slash(url: &str) -> String {
-    if url.ends_with('/') {
-        url[..url.len() - 1].to_string()
-    } else {
-        url.to_string()
+    url.strip_suffix('/').unwrap_or(url).to_string()
+}
+
+/// Current unix timestamp in seconds.
+pub fn current_timestamp() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Current unix timestamp in milliseconds.
+pub fn current_timestamp_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Returns the image as something the provider can fetch: either the original
+/// URL or a `data:` URL with the base64 encoded content.
+pub fn image_part_to_url(part: &LanguageModelImagePart) -> String {
+    let mime_type = part.mime_type.as_deref().unwrap_or("image/jpeg");
+    match &part.image {
+        LanguageModelImagePartContent::Url(url) => url.clone(),
+        LanguageModelImagePartContent::Base64(data) => {
+            format!("data:{mime_type};base64,{data}")
+        }
+        LanguageModelImagePartContent::Buffer(buffer) => format!(
+            "data:{mime_type};base64,{}",
+            general_purpose::STANDARD.encode(buffer)
+        ),
+    }
+}
+
+/// Most redirects [`download`] follows.
+const MAX_REDIRECTS: usize = 10;
+
+/// Downloads `url` with a GET request within the limits of `settings`.
+/// Returns the body and the mime type from the `Content-Type` header.
+///
+/// Redirects are followed here, so that the scheme and the URL filter are
+/// checked on every hop; this relies on a transport that does not follow
+/// them itself, like the default [`ReqwestTransport`](crate::transport::ReqwestTransport).
+pub async fn download(
+    transport: &dyn HttpTransport,
+    url: &str,
+    settings: &DownloadSettings,
+) -> Result<(Vec<u8>, Option<String>), ModelError> {
+    let download_error = |message: String| ModelError::Download {
+        url: url.to_string(),
+        message,
+    };
+    let mut current = url.to_string();
+    let mut redirects = 0;
+    let response = loop {
+        let scheme = current
+            .split_once("://")
+            .map(|(scheme, _)| scheme.to_ascii_lowercase());
+        if !matches!(scheme.as_deref(), Some("http" | "https")) {
+            return Err(download_error(
+                "only http and https URLs are downloaded".to_string(),
+            ));
+        }
+        if let Some(url_filter) = &settings.url_filter {
+            if !url_filter(&current) {
+                return Err(download_error(format!(
+                    "{current} rejected by the URL filter"
+                )));
+            }
+        }
+
+        let response = transport
+            .send(HttpRequest {
+                method: "GET".to_string(),
+                url: current.clone(),
+                headers: Vec::new(),
+                body: String::new(),
+            })
+            .await
+            .map_err(|e| download_error(e.to_string()))?;
+        let location = response
+            .headers
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case("location"))
+            .map(|(_, value)| value.trim());
+        match location {
+            Some(location) if (300..400).contains(&response.status) => {
+                redirects += 1;
+                if redirects > MAX_REDIRECTS {
+                    return Err(download_error(format!(
+                        "more than {MAX_REDIRECTS} redirects"
+                    )));
+                }
+                current = reqwest::Url::parse(&current)
+                    .and_then(|base| base.join(location))
+                    .map_err(|e| download_error(format!("invalid redirect {location}: {e}")))?
+                    .to_string();
+            }
+            _ => break response,
+        }
+    };
+    if !response.is_success() {
+        return Err(download_error(format!("status {}", response.status)));
+    }
+    let too_large = || download_error(format!("larger than {} bytes", settings.max_bytes));
+    let content_length = response
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-length"))
+        .and_then(|(_, value)| value.trim().parse::<usize>().ok());
+    if content_length.is_some_and(|length| length > settings.max_bytes) {
+        return Err(too_large());
+    }
+
+    let mime_type = response
+        .headers
+        .iter()
+        .find(|(key, _)| key.eq_ignore_ascii_case("content-type"))
+        .and_then(|(_, value)| value.split(';').next())
+        .map(|mime_type| mime_type.trim().to_string())
+        .filter(|mime_type| !mime_type.is_empty() && mime_type != "application/octet-stream");
+
+    // The length header is optional, so the limit is also enforced while reading.
+    let mut body = Vec::new();
+    let mut chunks = response.body;
+    while let Some(chunk) = chunks.next().await {
+        body.extend(chunk.map_err(|e| download_error(e.to_string()))?);
+        if body.len() > settings.max_bytes {
+            return Err(too_large());
+        }
+    }
+    Ok((body, mime_type))
+}
+
+/// Sends `body` as JSON to `url` and parses the JSON response.
+///
+/// Returns the parsed body together with the response headers. Error
+/// responses are read with `parse_error` into an [`ApiCallError`].
+pub async fn post_json_to_api(
+    transport: &dyn HttpTransport,
+    url: &str,
+    headers: &[(String, String)],
+    body: &JSONValue,
+    parse_error: ApiErrorPayloadParser,
+) -> Result<(JSONValue, Vec<(String, String)>), ModelError> {
+    let (response, response_headers) =
+        send_json(transport, url, headers, body, parse_error).await?;
+    let text = response.text().await?;
+
+    let json = serde_json::from_str(&text).map_err(|e| ModelError::InvalidResponseJson {
+        url: url.to_string(),
+        message: e.to_string(),
+        text,
+    })?;
+
+    Ok((json, response_headers))
+}
+
+/// Sends `body` as JSON to `url` and parses the response as server-sent events.
+///
+/// Every `data:` line is parsed as JSON; the `[DONE]` marker ends the stream.
+pub async fn post_json_to_api_stream(
+    transport: &dyn HttpTransport,
+    url: &str,
+    headers: &[(String, String)],
+    body: &JSONValue,
+    parse_error: ApiErrorPayloadParser,
+) -> Result<
+    (
+        BoxStream<'static, Result<JSONValue, ModelError>>,
+        Vec<(String, String)>,
+    ),
+    ModelError,
+> {
+    let (response, response_headers) =
+        send_json(transport, url, headers, body, parse_error).await?;
+
+    let events = parse_sse_stream(url.to_string(), response.body);
+    Ok((events, response_headers))
+}
+
+/// Parses a body of server-sent events. Every `data:` line is parsed as
+/// JSON; the `[DONE]` marker is skipped.
+pub fn parse_sse_stream(
+    url: String,
+    body: BoxStream<'static, Result<Vec<u8>, ModelError>>,
+) -> BoxStream<'static, Result<JSONValue, ModelError>> {
+    body
+        // A trailing newline flushes an event that was not terminated.
+        .chain(stream::once(async { Ok(b"\n".to_vec()) }))
+        .scan(Vec::new(), move |buffer: &mut Vec<u8>, chunk| {
+            let events = match chunk {
+                Ok(bytes) => {
+                    buffer.extend_from_slice(&bytes);
+                    let mut events = Vec::new();
+                    while let Some(end) = buffer.iter().position(|b| *b == b'\n') {
+                        let line: Vec<u8> = buffer.drain(..=end).collect();
+                        if let Some(event) = parse_sse_line(&url, &String::from_utf8_lossy(&line)) {
+                            events.push(event);
+                        }
+                    }
+                    events
+                }
+                Err(e) => vec![Err(e)],
+            };
+            futures::future::ready(Some(stream::iter(events)))
+        })
+        .flatten()
+        .boxed()
+}
+
+fn parse_sse_line(url: &str, line: &str) -> Option<Result<JSONValue, ModelError>> {
+    let data = line.trim_end().strip_prefix("data:")?.trim_start();
+    if data.is_empty() || data == "[DONE]" {
+        return None;
+    }
+    Some(
+        serde_json::from_str(data).map_err(|e| ModelError::InvalidResponseJson {
+            url: url.to_string(),
+            message: e.to_string(),
+            text: data.to_string(),
+        }),
+    )
+}
+
+async fn send_json(
+    transport: &dyn HttpTransport,
+    url: &str,
+    headers: &[(String, String)],
+    body: &JSONValue,
+    parse_error: ApiErrorPayloadParser,
+) -> Result<(HttpResponse, Vec<(String, String)>), ModelError> {
+    let span = tracing::info_span!(
+        "http.client",
+        otel.name = "POST",
+        otel.kind = "client",
+        http.request.method = "POST",
+        url.full = url,
+        http.response.status_code = Empty,
+        error.type = Empty,
+    );
+    let response = transport
+        .send(HttpRequest {
+            method: "POST".to_string(),
+            url: url.to_string(),
+            headers: headers.to_vec(),
+            body: body.to_string(),
+        })
+        .instrument(span.clone())
+        .await
+        .inspect_err(|_| {
+            span.record("error.type", "connection");
+        })?;
+
+    let status = response.status;
+    let response_headers = response.headers.clone();
+    span.record("http.response.status_code", status);
+
+    if !response.is_success() {
+        span.record("error.type", status.to_string());
+        let text = response.text().await.unwrap_or_default();
+        return Err(
+            ApiCallError::from_response(url, status, response_headers, text)
+                .with_payload(parse_error)
+                .into(),
+        );
     }
+
+    Ok((response, response_headers))
 }